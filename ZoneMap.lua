@@ -12,6 +12,8 @@ print(ADDON_NAME .. " loaded")
 -- -------------------------
 addon.tileGrids = addon.tileGrids or {}
 addon._tileCache = addon._tileCache or {}
+addon.outlines = addon.outlines or {}
+addon.borders = addon.borders or {}
 
 -- -------------------------
 -- u32 LE reader
@@ -60,21 +62,84 @@ local function base64_decode(s)
 end
 
 -- -------------------------
--- Tile decode: base64 -> raw bytes
+-- Tile decode: base64 -> 256 AreaIDs (index chunkY * 16 + chunkX + 1)
 -- -------------------------
-local function decode_tile_blob(blob)
+local function decode_u32le(raw)
+  local ids = {}
+  for i = 0, 255 do
+    ids[i + 1] = read_u32_le(raw, i * 4 + 1)
+  end
+  return ids
+end
+
+-- u8 palette size - 1, u16 palette entries, then (u8 run - 1, u8 palette index) pairs
+local function decode_palette_rle(raw)
+  local count = raw:byte(1)
+  if not count then return nil end
+  count = count + 1
+  local palette = {}
+  for i = 1, count do
+    local lo, hi = raw:byte(i * 2, i * 2 + 1)
+    if not hi then return nil end
+    palette[i] = lo + hi * 256
+  end
+  local ids, n = {}, 0
+  local pos = 2 + count * 2
+  while n < 256 do
+    local run, index = raw:byte(pos, pos + 1)
+    if not index then return nil end
+    local id = palette[index + 1]
+    for _ = 0, run do
+      n = n + 1
+      ids[n] = id
+    end
+    pos = pos + 2
+  end
+  return ids
+end
+
+local TILE_DECODERS = {
+  ["u32le"] = decode_u32le,
+  ["palette-rle-v1"] = decode_palette_rle,
+}
+
+-- Decoded tiles by format, then by blob (or uniform AreaID); identical tiles decode once
+local _decodedTiles = {}
+
+-- Data files without a format tag predate the compact encoding.
+-- A number instead of a blob is a tile with that AreaID in every chunk.
+local function decode_tile_blob(blob, format)
   if not blob then return nil end
-  return base64_decode(blob)
+  format = format or "u32le"
+  local decoded = _decodedTiles[format]
+  if not decoded then
+    decoded = setmetatable({}, { __mode = "v" })
+    _decodedTiles[format] = decoded
+  end
+  local ids = decoded[blob]
+  if ids then return ids end
+
+  if type(blob) == "number" then
+    ids = {}
+    for i = 1, 256 do ids[i] = blob end
+  else
+    local decoder = TILE_DECODERS[format]
+    if not decoder then return nil end
+    local raw = base64_decode(blob)
+    if not raw then return nil end
+    ids = decoder(raw)
+    if not ids then return nil end
+  end
+  decoded[blob] = ids
+  return ids
 end
 
 local function tile_key(tileX, tileY)
   return tileY * 64 + tileX
 end
 
-local function area_id_from_raw(raw, chunkX, chunkY)
-  local idx = chunkY * 16 + chunkX
-  local offset = idx * 4 + 1
-  return read_u32_le(raw, offset)
+local function area_id_from_tile(ids, chunkX, chunkY)
+  return ids[chunkY * 16 + chunkX + 1]
 end
 
 -- -------------------------
@@ -110,6 +175,9 @@ end
 -- Public API: Register tile grids (called by data files)
 -- -------------------------
 function addon:RegisterTileGrid(name, grid)
+  if grid.format and not TILE_DECODERS[grid.format] then
+    print("|cffff0000ZoneMap ERROR: " .. name .. " uses unknown tile format '" .. grid.format .. "', regenerate Data/ with a matching version|r")
+  end
   self.tileGrids[name] = grid
   addon._tileCache[name] = new_cache(64)
   local count = 0
@@ -117,6 +185,18 @@ function addon:RegisterTileGrid(name, grid)
   print(ADDON_NAME .. ": Registered " .. name .. " (" .. count .. " tiles)")
 end
 
+-- Optional <Grid>_outlines.lua files: areaID -> list of {outerRing, hole, ...},
+-- each ring a flat {x1, y1, x2, y2, ...} list of world coordinates
+function addon:RegisterOutlines(name, outlines)
+  self.outlines[name] = outlines
+end
+
+-- Optional <Grid>_borders.lua files: { zone = {...}, subzone = {...} },
+-- each segment {areaA, areaB, x1, y1, x2, y2} in world coordinates
+function addon:RegisterBorders(name, borders)
+  self.borders[name] = borders
+end
+
 -- -------------------------
 -- Public API: Get area name from ID
 -- -------------------------
@@ -151,6 +231,12 @@ end
 local createVec2 = CreateVector2D or Vector2D_Create
 
 local function continent_name_prefix_grid(continentMapID)
+  -- Grids generated from a config carry the UiMap they belong to
+  for gridName, grid in pairs(addon.tileGrids) do
+    if grid.uiMapId and grid.uiMapId == continentMapID then
+      return grid.name or gridName, nil, gridName
+    end
+  end
   if continentMapID == 1414 then return "Kalimdor", "kalimdor", "Kalimdor" end
   if continentMapID == 1415 then return "Eastern Kingdoms", "azeroth", "Azeroth" end
   return ("continent:" .. tostring(continentMapID)), nil, nil
@@ -428,7 +514,8 @@ local fillTextures = {}
 local fillLabels = {}
 local fillEnabled = false
 
--- Exact area names to exclude (large water bodies that overwhelm zone rendering)
+-- Exact area names to exclude when AreaInfo predates the area rules (large water
+-- bodies that overwhelm zone rendering)
 local EXCLUDED_AREAS = {
   ["The Great Sea"] = true,
   ["South Seas"] = true,
@@ -438,13 +525,29 @@ local EXCLUDED_AREAS = {
   ["Twisting Nether"] = true,
 }
 
+-- Seas and hidden areas come from the generator's area rules (area_rules.toml)
 local function ShouldExcludeArea(areaID)
-  if not addon.AreaInfo or not addon.AreaInfo[areaID] then return false end
-  local name = addon.AreaInfo[areaID].name
-  return name and EXCLUDED_AREAS[name]
+  local info = addon.AreaInfo and addon.AreaInfo[areaID]
+  if not info then return false end
+  if not addon.AreaRulesApplied then
+    return EXCLUDED_AREAS[info.name] or false
+  end
+  return info.sea or info.hidden or false
 end
 
--- Get all area IDs that belong to a root parent (excluding seas/oceans)
+-- Areas marked mergeIntoParent are labelled as their nearest ancestor that is not
+local function LabelAreaID(areaID)
+  local seen = {}
+  local info = addon.AreaInfo and addon.AreaInfo[areaID]
+  while info and info.mergeIntoParent and info.parentId ~= 0 and not seen[areaID] do
+    seen[areaID] = true
+    areaID = info.parentId
+    info = addon.AreaInfo[areaID]
+  end
+  return areaID
+end
+
+-- Get all area IDs that belong to a root parent (excluding seas and hidden areas)
 local function GetAreasForRootParent(rootParentID)
   local areas = {}
   local excluded = 0
@@ -460,6 +563,32 @@ local function GetAreasForRootParent(rootParentID)
   return areas, excluded
 end
 
+-- Every chunk of the given areas as a 1x1 rectangle, in the draw list layout
+local function ChunkRects(grid, validAreas)
+  local rects = {}
+  for key, blob in pairs(grid.tiles) do
+    local ids = decode_tile_blob(blob, grid.format)
+    if ids then
+      local tileY = math.floor(key / 64)
+      local tileX = key % 64
+      for chunkY = 0, 15 do
+        for chunkX = 0, 15 do
+          local areaID = area_id_from_tile(ids, chunkX, chunkY)
+          if areaID and areaID ~= 0 and validAreas[areaID] then
+            local n = #rects
+            rects[n + 1] = tileX * 16 + chunkX
+            rects[n + 2] = tileY * 16 + chunkY
+            rects[n + 3] = 1
+            rects[n + 4] = 1
+            rects[n + 5] = areaID
+          end
+        end
+      end
+    end
+  end
+  return rects
+end
+
 -- Core fill update function
 local function UpdateFillOverlay(silent)
   if not fillEnabled then return end
@@ -484,16 +613,9 @@ local function UpdateFillOverlay(silent)
     return  -- Silently fail for invalid maps
   end
   
-  -- Get grid
+  -- Get the grid this map is drawn on
   local continentMapID = get_continent_map_id(mapID)
   local _, _, gridName = continent_name_prefix_grid(continentMapID)
-  local grid = gridName and addon.tileGrids[gridName]
-  
-  if not grid or not grid.tiles then
-    -- Hide overlay if no grid data (e.g., continent view)
-    if fillOverlay then fillOverlay:Hide() end
-    return
-  end
   
   -- Check required data
   if not addon.AreaInfo or not addon.AreaHierarchy or not addon.MapToArea then
@@ -519,12 +641,12 @@ local function UpdateFillOverlay(silent)
     print(string.format("|cff00ff00Drawing zones for map: %s (mapID: %d, rootAreaID: %d)|r", rootName, mapID, rootParentID))
   end
   
-  -- Get all area IDs that share this root parent (excluding seas/oceans)
+  -- Get all area IDs that share this root parent (excluding seas and hidden areas)
   local validAreas, excludedCount = GetAreasForRootParent(rootParentID)
   local areaCount = 0
   for _ in pairs(validAreas) do areaCount = areaCount + 1 end
   if not silent then
-    local excludeMsg = excludedCount > 0 and string.format(" (excluded %d sea/ocean areas)", excludedCount) or ""
+    local excludeMsg = excludedCount > 0 and string.format(" (excluded %d sea/hidden areas)", excludedCount) or ""
     print(string.format("  Found %d sub-areas in this zone%s", areaCount, excludeMsg))
   end
   
@@ -540,101 +662,97 @@ local function UpdateFillOverlay(silent)
     label:Hide()
   end
   
-  -- First pass: count total chunks to draw
+  -- Rectangles precomputed by the generator (Data/DrawLists.lua): x, y, w, h, areaId.
+  -- Data without a draw list for this map falls back to scanning the grid's chunks.
+  local drawList = addon.DrawLists and addon.DrawLists[mapID]
+  local rects
+  if drawList then
+    rects = gridName and drawList.grids[gridName]
+  else
+    local grid = gridName and addon.tileGrids[gridName]
+    rects = grid and grid.tiles and ChunkRects(grid, validAreas)
+  end
+  if not rects then
+    -- Nothing of this zone on the map's grid (e.g., continent view)
+    fillOverlay:Hide()
+    return
+  end
+  
   local totalChunks = 0
-  for key, blob in pairs(grid.tiles) do
-    local raw = decode_tile_blob(blob)
-    if raw then
-      for chunkY = 0, 15 do
-        for chunkX = 0, 15 do
-          local areaID = area_id_from_raw(raw, chunkX, chunkY)
-          if areaID and areaID ~= 0 and validAreas[areaID] then
-            totalChunks = totalChunks + 1
-          end
-        end
-      end
-    end
+  for i = 1, #rects, 5 do
+    totalChunks = totalChunks + rects[i + 2] * rects[i + 3]
   end
   
   if not silent then
-    print(string.format("  Total chunks to draw: %d", totalChunks))
+    print(string.format("  Total chunks to draw: %d in %d rectangles", totalChunks, #rects / 5))
   end
   
   local texIdx = 0
   local baseChunkSize = ADT_TILE_SIZE / 16
+  local chunkNormWidth = baseChunkSize / math.abs(p11.y - p00.y)
+  local chunkNormHeight = baseChunkSize / math.abs(p11.x - p00.x)
   local allAreaCounts = {}
-  local tilesScanned = 0
   local chunksDrawn = 0
   local areaCentroids = {}
   
-  -- Second pass: draw chunks
-  for key, blob in pairs(grid.tiles) do
-    local raw = decode_tile_blob(blob)
-    if raw then
-      tilesScanned = tilesScanned + 1
-      
-      local tileY = math.floor(key / 64)
-      local tileX = key % 64
-      
-      for chunkY = 0, 15 do
-        for chunkX = 0, 15 do
-          local areaID = area_id_from_raw(raw, chunkX, chunkY)
-          
-          if areaID and areaID ~= 0 and validAreas[areaID] then
-            allAreaCounts[areaID] = (allAreaCounts[areaID] or 0) + 1
-            
-            local chunkOffsetRow = (chunkX - 7.5) / 16
-            local chunkOffsetCol = (chunkY - 7.5) / 16
-            local chunkWorldY = ADT_HALF_SIZE - (tileX + 0.5 + chunkOffsetRow) * ADT_TILE_SIZE
-            local chunkWorldX = ADT_HALF_SIZE - (tileY + 0.5 + chunkOffsetCol) * ADT_TILE_SIZE
-            
-            local nx = (chunkWorldY - p00.y) / (p11.y - p00.y)
-            local ny = (chunkWorldX - p00.x) / (p11.x - p00.x)
-            
-            local chunkNormWidth = baseChunkSize / math.abs(p11.y - p00.y)
-            local chunkNormHeight = baseChunkSize / math.abs(p11.x - p00.x)
-            
-            local pixelX = nx * canvasWidth
-            local pixelY = ny * canvasHeight
-            local pixelW = chunkNormWidth * canvasWidth * 1.05
-            local pixelH = chunkNormHeight * canvasHeight * 1.05
-            
-            if not areaCentroids[areaID] then
-              areaCentroids[areaID] = { sumX = 0, sumY = 0, count = 0 }
-            end
-            areaCentroids[areaID].sumX = areaCentroids[areaID].sumX + pixelX
-            areaCentroids[areaID].sumY = areaCentroids[areaID].sumY + pixelY
-            areaCentroids[areaID].count = areaCentroids[areaID].count + 1
-            
-            texIdx = texIdx + 1
-            local tex = fillTextures[texIdx]
-            if not tex then
-              tex = fillOverlay:CreateTexture(nil, "ARTWORK")
-              fillTextures[texIdx] = tex
-            end
-            
-            local r, g, b = GetAreaColor(areaID)
-            tex:SetColorTexture(r, g, b, 0.4)
-            tex:ClearAllPoints()
-            tex:SetPoint("TOPLEFT", canvas, "TOPLEFT", pixelX - pixelW/2, -(pixelY - pixelH/2))
-            tex:SetSize(pixelW, pixelH)
-            tex:Show()
-            
-            chunksDrawn = chunksDrawn + 1
-          end
-        end
-      end
+  for i = 1, #rects, 5 do
+    local x, y, w, h, areaID = rects[i], rects[i + 1], rects[i + 2], rects[i + 3], rects[i + 4]
+    local chunks = w * h
+    allAreaCounts[areaID] = (allAreaCounts[areaID] or 0) + chunks
+    
+    -- Rectangle center; x runs along world Y and y along world X, as for tiles
+    local rectWorldY = ADT_HALF_SIZE - (x + w / 2) * baseChunkSize
+    local rectWorldX = ADT_HALF_SIZE - (y + h / 2) * baseChunkSize
+    
+    local nx = (rectWorldY - p00.y) / (p11.y - p00.y)
+    local ny = (rectWorldX - p00.x) / (p11.x - p00.x)
+    
+    local pixelX = nx * canvasWidth
+    local pixelY = ny * canvasHeight
+    local pixelW = (w + 0.05) * chunkNormWidth * canvasWidth
+    local pixelH = (h + 0.05) * chunkNormHeight * canvasHeight
+    
+    local labelID = LabelAreaID(areaID)
+    if not areaCentroids[labelID] then
+      areaCentroids[labelID] = { sumX = 0, sumY = 0, count = 0 }
     end
+    areaCentroids[labelID].sumX = areaCentroids[labelID].sumX + pixelX * chunks
+    areaCentroids[labelID].sumY = areaCentroids[labelID].sumY + pixelY * chunks
+    areaCentroids[labelID].count = areaCentroids[labelID].count + chunks
+    
+    texIdx = texIdx + 1
+    local tex = fillTextures[texIdx]
+    if not tex then
+      tex = fillOverlay:CreateTexture(nil, "ARTWORK")
+      fillTextures[texIdx] = tex
+    end
+    
+    local r, g, b = GetAreaColor(areaID)
+    tex:SetColorTexture(r, g, b, 0.4)
+    tex:ClearAllPoints()
+    tex:SetPoint("TOPLEFT", canvas, "TOPLEFT", pixelX - pixelW/2, -(pixelY - pixelH/2))
+    tex:SetSize(pixelW, pixelH)
+    tex:Show()
+    
+    chunksDrawn = chunksDrawn + chunks
   end
   
-  -- Draw labels at centroids
+  -- Draw labels at the generator's anchors, falling back to chunk centroids for old data
   local labelIdx = 0
   for areaID, centroid in pairs(areaCentroids) do
-    if centroid.count >= 2 then
-      local avgX = centroid.sumX / centroid.count
-      local avgY = centroid.sumY / centroid.count
-      
-      if avgX >= 0 and avgX <= canvasWidth and avgY >= 0 and avgY <= canvasHeight then
+    local info = addon.AreaInfo[areaID]
+    local anchor = info and info.labelAnchor
+    local labelX, labelY
+    if anchor then
+      labelX = (anchor[2] - p00.y) / (p11.y - p00.y) * canvasWidth
+      labelY = (anchor[1] - p00.x) / (p11.x - p00.x) * canvasHeight
+    elseif centroid.count >= 2 then
+      labelX = centroid.sumX / centroid.count
+      labelY = centroid.sumY / centroid.count
+    end
+
+    if labelX then
+      if labelX >= 0 and labelX <= canvasWidth and labelY >= 0 and labelY <= canvasHeight then
         labelIdx = labelIdx + 1
         local label = fillLabels[labelIdx]
         if not label then
@@ -646,7 +764,7 @@ local function UpdateFillOverlay(silent)
         local r, g, b = GetAreaColor(areaID)
         label:SetTextColor(r * 0.6, g * 0.6, b * 0.6, 1)
         label:ClearAllPoints()
-        label:SetPoint("CENTER", canvas, "TOPLEFT", avgX, -avgY)
+        label:SetPoint("CENTER", canvas, "TOPLEFT", labelX, -labelY)
         local suffix = AreaGivesExplorationXP(areaID) and "*" or ""
         label:SetText(areaID .. suffix)
         label:Show()
@@ -655,7 +773,7 @@ local function UpdateFillOverlay(silent)
   end
   
   if not silent then
-    print(string.format("Scanned %d tiles, drew %d chunks, %d labels", tilesScanned, chunksDrawn, labelIdx))
+    print(string.format("Drew %d chunks in %d rectangles, %d labels", chunksDrawn, texIdx, labelIdx))
     
     -- Print area ID summary
     print("Sub-zones found:")
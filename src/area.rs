@@ -0,0 +1,256 @@
+// ============================================================================
+// AreaTable and UiMap-to-area CSV inputs
+// ============================================================================
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// Every AreaTable row by ID
+pub type AreaTable = HashMap<u32, AreaInfo>;
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AreaInfo {
+    pub id: u32,
+    pub name: String,
+    pub parent_id: u32,
+    pub exploration_level: i32,
+    /// Map ID the area belongs to, when the export has a `ContinentID` column
+    pub continent_id: Option<u32>,
+    /// AreaTable `Flags_0` (`Flags` in older exports), 0 when the column is missing
+    pub flags: u32,
+}
+
+/// The format tag (`WDC4`, `WDBC`, ...) of a client DB2/DBC table, from its first bytes
+pub fn db2_format(data: &[u8]) -> Option<&str> {
+    let magic = data.get(..4)?;
+    if magic.starts_with(b"WDC") || magic.starts_with(b"WDB") {
+        std::str::from_utf8(magic).ok()
+    } else {
+        None
+    }
+}
+
+/// Only CSV exports are read; a DB2 copied out with `casc-extract` gets a clear error
+/// instead of a missing-column one
+fn open_csv(csv_path: &Path) -> Result<BufReader<File>, Box<dyn std::error::Error>> {
+    let mut reader = BufReader::new(File::open(csv_path)?);
+    if let Some(format) = db2_format(reader.fill_buf()?) {
+        return Err(format!(
+            "{} is a {} DB2 table, not CSV; convert it with a DB2-to-CSV tool first",
+            csv_path.display(),
+            format
+        )
+        .into());
+    }
+    Ok(reader)
+}
+
+pub fn parse_area_table(csv_path: &Path) -> Result<AreaTable, Box<dyn std::error::Error>> {
+    let reader = open_csv(csv_path)?;
+    let mut areas = HashMap::new();
+    
+    let mut lines = reader.lines();
+    let header = lines.next().ok_or("Empty CSV")??;
+    
+    let columns: Vec<&str> = header.split(',').collect();
+    let id_idx = columns.iter().position(|&c| c == "ID").ok_or("No ID column")?;
+    let name_idx = columns.iter().position(|&c| c == "AreaName_lang").ok_or("No AreaName_lang column")?;
+    let parent_idx = columns.iter().position(|&c| c == "ParentAreaID").ok_or("No ParentAreaID column")?;
+    let level_idx = columns.iter().position(|&c| c == "ExplorationLevel").ok_or("No ExplorationLevel column")?;
+    let continent_idx = columns.iter().position(|&c| c == "ContinentID");
+    let flags_idx = columns.iter().position(|&c| c == "Flags_0" || c == "Flags");
+    
+    for line in lines {
+        let line = line?;
+        let fields: Vec<&str> = parse_csv_line(&line);
+        
+        if fields.len() <= id_idx.max(name_idx).max(parent_idx).max(level_idx) {
+            continue;
+        }
+        
+        let id: u32 = match fields[id_idx].parse() {
+            Ok(v) => v,
+            Err(_) => continue,
+        };
+        
+        let name = fields[name_idx].trim_matches('"').to_string();
+        let parent_id: u32 = fields[parent_idx].parse().unwrap_or(0);
+        let exploration_level: i32 = fields[level_idx].parse().unwrap_or(0);
+        let continent_id = continent_idx.and_then(|i| fields.get(i)).and_then(|v| v.parse().ok());
+        let flags = flags_idx.and_then(|i| fields.get(i)).and_then(|v| v.parse().ok()).unwrap_or(0);
+        
+        areas.insert(id, AreaInfo { id, name, parent_id, exploration_level, continent_id, flags });
+    }
+    
+    Ok(areas)
+}
+
+fn parse_csv_line(line: &str) -> Vec<&str> {
+    let mut fields = Vec::new();
+    let mut start = 0;
+    let mut in_quotes = false;
+    let bytes = line.as_bytes();
+    
+    for i in 0..bytes.len() {
+        match bytes[i] {
+            b'"' => in_quotes = !in_quotes,
+            b',' if !in_quotes => {
+                fields.push(&line[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    fields.push(&line[start..]);
+    fields
+}
+
+pub fn find_root_parent(area_id: u32, areas: &AreaTable) -> u32 {
+    let mut current = area_id;
+    let mut visited = BTreeSet::new();
+    
+    while let Some(area) = areas.get(&current) {
+        if area.parent_id == 0 || visited.contains(&current) {
+            return current;
+        }
+        visited.insert(current);
+        current = area.parent_id;
+    }
+    area_id
+}
+
+// ============================================================================
+// Map ID to Area ID mapping
+// ============================================================================
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MapToAreaEntry {
+    pub zone_name: String,
+    pub map_id: u32,
+    pub area_id: u32,
+}
+
+pub fn parse_map_to_area_csv(csv_path: &Path) -> Result<Vec<MapToAreaEntry>, Box<dyn std::error::Error>> {
+    let reader = open_csv(csv_path)?;
+    let mut entries = Vec::new();
+    
+    let mut lines = reader.lines();
+    let header = lines.next().ok_or("Empty CSV")??;
+    
+    // Parse header to find column indices
+    let columns: Vec<&str> = header.split(',').collect();
+    let zone_idx = columns.iter().position(|&c| c.trim() == "Zone").ok_or("No Zone column")?;
+    let map_id_idx = columns.iter().position(|&c| c.trim() == "mapId").ok_or("No mapId column")?;
+    let area_id_idx = columns.iter().position(|&c| c.trim() == "AreaId").ok_or("No AreaId column")?;
+    
+    for line in lines {
+        let line = line?;
+        let fields: Vec<&str> = parse_csv_line(&line);
+        
+        if fields.len() <= zone_idx.max(map_id_idx).max(area_id_idx) {
+            continue;
+        }
+        
+        let zone_name = fields[zone_idx].trim_matches('"').to_string();
+        let map_id: u32 = match fields[map_id_idx].trim().parse() {
+            Ok(v) => v,
+            Err(_) => continue,
+        };
+        let area_id: u32 = match fields[area_id_idx].trim().parse() {
+            Ok(v) => v,
+            Err(_) => continue,
+        };
+        
+        entries.push(MapToAreaEntry { zone_name, map_id, area_id });
+    }
+    
+    Ok(entries)
+}
+
+
+/// `Name (id) -> Parent (id) -> ...` up to the root, so the whole chain is visible
+pub fn parent_chain(area_id: u32, areas: &AreaTable) -> String {
+    let mut chain = Vec::new();
+    let mut visited = BTreeSet::new();
+    let mut current = area_id;
+    while current != 0 {
+        if !visited.insert(current) {
+            chain.push(format!("<cycle back to {}>", current));
+            break;
+        }
+        match areas.get(&current) {
+            Some(area) => {
+                chain.push(format!("{} ({})", area.name, area.id));
+                current = area.parent_id;
+            }
+            None => {
+                chain.push(format!("<missing {}>", current));
+                break;
+            }
+        }
+    }
+    chain.join(" -> ")
+}
+
+/// Root zone -> (area -> name) for every area found on the maps
+pub type AreaHierarchy = BTreeMap<u32, BTreeMap<u32, String>>;
+
+pub fn group_by_root(found_areas: &BTreeSet<u32>, areas: &AreaTable) -> AreaHierarchy {
+    let mut hierarchy: AreaHierarchy = BTreeMap::new();
+    
+    for &area_id in found_areas {
+        if area_id == 0 {
+            continue;
+        }
+        
+        let root_parent = find_root_parent(area_id, areas);
+        let name = if let Some(area) = areas.get(&area_id) {
+            area.name.clone()
+        } else {
+            format!("Unknown_{}", area_id)
+        };
+        
+        hierarchy
+            .entry(root_parent)
+            .or_default()
+            .insert(area_id, name);
+    }
+    hierarchy
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TempDir;
+
+    #[test]
+    fn db2_tables_are_rejected_with_a_hint() {
+        let dir = TempDir::new("area-csv");
+        let db2 = dir.0.join("areatable.db2");
+        std::fs::write(&db2, b"WDC4\x10\x00\x00\x00\xff\xfe binary").unwrap();
+        let error = parse_area_table(&db2).unwrap_err().to_string();
+        assert!(error.contains("is a WDC4 DB2 table, not CSV"), "{}", error);
+        let error = parse_map_to_area_csv(&db2).unwrap_err().to_string();
+        assert!(error.contains("is a WDC4 DB2 table, not CSV"), "{}", error);
+        assert_eq!(db2_format(b"WDBC\x01"), Some("WDBC"));
+        assert_eq!(db2_format(b"ID,Name"), None);
+        assert_eq!(db2_format(b"WDC"), None);
+
+        let csv = dir.0.join("AreaTable.csv");
+        std::fs::write(
+            &csv,
+            "ID,ZoneName,AreaName_lang,ContinentID,ParentAreaID,ExplorationLevel,Flags_0\n\
+             14,Durotar,\"Durotar, the Red\",1,0,10,64\n\
+             1637,Orgrimmar,Orgrimmar,1,14,15,0\n",
+        )
+        .unwrap();
+        let areas = parse_area_table(&csv).unwrap();
+        let durotar = &areas[&14];
+        assert_eq!((durotar.name.as_str(), durotar.continent_id, durotar.flags), ("Durotar, the Red", Some(1), 64));
+        assert_eq!((areas[&1637].parent_id, areas[&1637].exploration_level), (14, 15));
+    }
+}
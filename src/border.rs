@@ -0,0 +1,120 @@
+// ============================================================================
+// Border segments: chunk edges where two different areas meet
+// ============================================================================
+
+use std::collections::BTreeMap;
+
+use crate::grid::{grid_to_world, ChunkGrid};
+
+/// A straight run of chunk edges between the same two areas
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BorderSegment {
+    /// The two AreaIDs, lower first
+    pub areas: (u32, u32),
+    /// World `(x, y)` of both ends
+    pub from: (f64, f64),
+    pub to: (f64, f64),
+}
+
+/// Compare every chunk with its right and lower neighbor, like `find_tile_neighbors` and
+/// `find_inter_tile_neighbors` do (chunks with AreaID 0 are skipped), and merge collinear
+/// edges between the same pair of areas into one segment.
+pub fn find_borders(grid: &ChunkGrid) -> Vec<BorderSegment> {
+    // (vertical, line, low area, high area) -> unit edge starts along the line
+    let mut runs: BTreeMap<(bool, i32, u32, u32), Vec<i32>> = BTreeMap::new();
+    for (x, y, area) in grid.chunks() {
+        let right = grid.area_at(x + 1, y);
+        if right != 0 && right != area {
+            runs.entry((true, x + 1, area.min(right), area.max(right))).or_default().push(y);
+        }
+        let below = grid.area_at(x, y + 1);
+        if below != 0 && below != area {
+            runs.entry((false, y + 1, area.min(below), area.max(below))).or_default().push(x);
+        }
+    }
+
+    let mut segments = Vec::new();
+    for ((vertical, line, a, b), mut starts) in runs {
+        starts.sort_unstable();
+        let mut i = 0;
+        while i < starts.len() {
+            let mut end = starts[i] + 1;
+            let mut j = i + 1;
+            while j < starts.len() && starts[j] == end {
+                end += 1;
+                j += 1;
+            }
+            let (from, to) = if vertical {
+                ((line, starts[i]), (line, end))
+            } else {
+                ((starts[i], line), (end, line))
+            };
+            segments.push(BorderSegment {
+                areas: (a, b),
+                from: grid_to_world(from.0 as f64, from.1 as f64),
+                to: grid_to_world(to.0 as f64, to.1 as f64),
+            });
+            i = j;
+        }
+    }
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::export_borders;
+    use crate::grid::world_to_grid;
+    use crate::testutil::{area_table, tiles_from_rows, TempDir};
+
+    /// 1 and 2 are one zone and its subzone, 3 is another zone. Two columns of 2 split 1,
+    /// so the 1|3 edge along the bottom is broken into two runs; 0 borders nothing.
+    const ROWS: [&str; 3] = ["11221.", "11221.", "33333."];
+
+    fn lattice(point: (f64, f64)) -> (i32, i32) {
+        let (x, y) = world_to_grid(point.0, point.1);
+        (x.round() as i32, y.round() as i32)
+    }
+
+    #[test]
+    fn collinear_edges_merge_per_area_pair() {
+        let segments = find_borders(&ChunkGrid::new(&tiles_from_rows(&ROWS)));
+        let found: Vec<_> = segments.iter().map(|s| (s.areas, lattice(s.from), lattice(s.to))).collect();
+        assert_eq!(
+            found,
+            [
+                ((1, 3), (0, 2), (2, 2)),
+                ((1, 3), (4, 2), (5, 2)),
+                ((2, 3), (2, 2), (4, 2)),
+                ((1, 2), (2, 0), (2, 2)),
+                ((1, 2), (4, 0), (4, 2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn export_splits_zone_and_subzone_borders() {
+        let areas = area_table(&[(1, "Elwynn Forest", 0), (2, "Goldshire", 1), (3, "Westfall", 0)]);
+        let segments = find_borders(&ChunkGrid::new(&tiles_from_rows(&ROWS)));
+        let dir = TempDir::new("borders");
+        let path = dir.0.join("Azeroth_borders.lua");
+        export_borders("Azeroth", &segments, &areas, &path).unwrap();
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        let body = &text[text.find("addon:RegisterBorders").unwrap()..];
+        assert_eq!(
+            body,
+            "addon:RegisterBorders(\"Azeroth\", {\n  \
+             zone = {\n    \
+             {1, 3, 17000.0, 17066.7, 17000.0, 17000.0},\n    \
+             {1, 3, 17000.0, 16933.3, 17000.0, 16900.0},\n    \
+             {2, 3, 17000.0, 17000.0, 17000.0, 16933.3},\n  \
+             },\n  \
+             subzone = {\n    \
+             {1, 2, 17066.7, 17000.0, 17000.0, 17000.0},\n    \
+             {1, 2, 17066.7, 16933.3, 17000.0, 16933.3},\n  \
+             },\n\
+             })\n"
+        );
+    }
+}
@@ -0,0 +1,805 @@
+// ============================================================================
+// Local CASC storage reading (modern Classic Era installs)
+// ============================================================================
+//
+// Only what the generator needs: resolve a FileDataID (or a path, via a listfile or
+// the root's name hashes) to its content key, the content key to an encoding key,
+// the encoding key to a span of a local `data.NNN` archive, and decode the BLTE
+// payload found there. Encrypted and LZ4 blocks are not supported. Files come out as
+// stored: DB2 tables are not parsed here.
+
+use flate2::read::ZlibDecoder;
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+type Key = [u8; 16];
+
+/// Root manifest locale bits, by client locale code
+const LOCALES: [(&str, u32); 15] = [
+    ("enUS", 0x2),
+    ("koKR", 0x4),
+    ("frFR", 0x10),
+    ("deDE", 0x20),
+    ("zhCN", 0x40),
+    ("esES", 0x80),
+    ("zhTW", 0x100),
+    ("enGB", 0x200),
+    ("enCN", 0x400),
+    ("enTW", 0x800),
+    ("esMX", 0x1000),
+    ("ruRU", 0x2000),
+    ("ptBR", 0x4000),
+    ("itIT", 0x8000),
+    ("ptPT", 0x10000),
+];
+pub const DEFAULT_LOCALE: &str = "enUS";
+const CONTENT_LOW_VIOLENCE: u32 = 0x80;
+const CONTENT_NO_NAME_HASH: u32 = 0x1000_0000;
+
+/// Size of the header that precedes every BLTE blob inside `data.NNN`
+const DATA_HEADER_SIZE: usize = 30;
+
+fn be_u32(b: &[u8]) -> u32 {
+    u32::from_be_bytes([b[0], b[1], b[2], b[3]])
+}
+
+fn le_u32(b: &[u8]) -> u32 {
+    u32::from_le_bytes([b[0], b[1], b[2], b[3]])
+}
+
+fn parse_hex_key(s: &str) -> Result<Key, String> {
+    let s = s.trim();
+    if s.len() != 32 {
+        return Err(format!("expected a 32-digit hex key, got '{}'", s));
+    }
+    let mut key = [0u8; 16];
+    for (i, byte) in key.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&s[i * 2..i * 2 + 2], 16).map_err(|_| format!("bad hex key '{}'", s))?;
+    }
+    Ok(key)
+}
+
+/// Root manifest bit of a locale code such as `enUS` or `deDE` (case-insensitive)
+pub fn locale_flag(locale: &str) -> Result<u32, String> {
+    LOCALES
+        .iter()
+        .find(|(code, _)| code.eq_ignore_ascii_case(locale))
+        .map(|&(_, flag)| flag)
+        .ok_or_else(|| {
+            let codes: Vec<&str> = LOCALES.iter().map(|(code, _)| *code).collect();
+            format!("unknown locale '{}' (expected one of: {})", locale, codes.join(", "))
+        })
+}
+
+fn hex(key: &[u8]) -> String {
+    key.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// ----------------------------------------------------------------------------
+// Jenkins lookup3, used for the root manifest's path hashes
+// ----------------------------------------------------------------------------
+
+fn lookup3_mix(a: &mut u32, b: &mut u32, c: &mut u32) {
+    *a = a.wrapping_sub(*c); *a ^= c.rotate_left(4);  *c = c.wrapping_add(*b);
+    *b = b.wrapping_sub(*a); *b ^= a.rotate_left(6);  *a = a.wrapping_add(*c);
+    *c = c.wrapping_sub(*b); *c ^= b.rotate_left(8);  *b = b.wrapping_add(*a);
+    *a = a.wrapping_sub(*c); *a ^= c.rotate_left(16); *c = c.wrapping_add(*b);
+    *b = b.wrapping_sub(*a); *b ^= a.rotate_left(19); *a = a.wrapping_add(*c);
+    *c = c.wrapping_sub(*b); *c ^= b.rotate_left(4);  *b = b.wrapping_add(*a);
+}
+
+fn lookup3_final(a: &mut u32, b: &mut u32, c: &mut u32) {
+    *c ^= *b; *c = c.wrapping_sub(b.rotate_left(14));
+    *a ^= *c; *a = a.wrapping_sub(c.rotate_left(11));
+    *b ^= *a; *b = b.wrapping_sub(a.rotate_left(25));
+    *c ^= *b; *c = c.wrapping_sub(b.rotate_left(16));
+    *a ^= *c; *a = a.wrapping_sub(c.rotate_left(4));
+    *b ^= *a; *b = b.wrapping_sub(a.rotate_left(14));
+    *c ^= *b; *c = c.wrapping_sub(b.rotate_left(24));
+}
+
+/// `hashlittle2` with both seeds zero, combined as `(c << 32) | b`
+fn hashlittle2(key: &[u8]) -> u64 {
+    let init = 0xDEAD_BEEFu32.wrapping_add(key.len() as u32);
+    let (mut a, mut b, mut c) = (init, init, init);
+
+    let mut rest = key;
+    while rest.len() > 12 {
+        a = a.wrapping_add(le_u32(&rest[0..4]));
+        b = b.wrapping_add(le_u32(&rest[4..8]));
+        c = c.wrapping_add(le_u32(&rest[8..12]));
+        lookup3_mix(&mut a, &mut b, &mut c);
+        rest = &rest[12..];
+    }
+
+    if !rest.is_empty() {
+        let mut tail = [0u8; 12];
+        tail[..rest.len()].copy_from_slice(rest);
+        a = a.wrapping_add(le_u32(&tail[0..4]));
+        b = b.wrapping_add(le_u32(&tail[4..8]));
+        c = c.wrapping_add(le_u32(&tail[8..12]));
+        lookup3_final(&mut a, &mut b, &mut c);
+    }
+
+    ((c as u64) << 32) | b as u64
+}
+
+/// Root manifests hash paths upper-cased with backslash separators
+pub fn name_hash(path: &str) -> u64 {
+    let normalized: Vec<u8> = path
+        .bytes()
+        .map(|b| if b == b'/' { b'\\' } else { b.to_ascii_uppercase() })
+        .collect();
+    hashlittle2(&normalized)
+}
+
+// ----------------------------------------------------------------------------
+// BLTE
+// ----------------------------------------------------------------------------
+
+fn decode_blte(data: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    if data.len() < 8 || &data[0..4] != b"BLTE" {
+        return Err("missing BLTE signature".into());
+    }
+    let header_size = be_u32(&data[4..8]) as usize;
+    if header_size == 0 {
+        return decode_blte_chunk(&data[8..]);
+    }
+    if data.len() < 12 || header_size > data.len() {
+        return Err("truncated BLTE header".into());
+    }
+
+    let chunk_count = ((data[9] as usize) << 16) | ((data[10] as usize) << 8) | data[11] as usize;
+    let mut out = Vec::new();
+    let mut pos = header_size;
+    for i in 0..chunk_count {
+        let info = data
+            .get(12 + i * 24..12 + (i + 1) * 24)
+            .ok_or("truncated BLTE chunk table")?;
+        let compressed_size = be_u32(&info[0..4]) as usize;
+        let chunk = data.get(pos..pos + compressed_size).ok_or("truncated BLTE chunk")?;
+        out.extend(decode_blte_chunk(chunk)?);
+        pos += compressed_size;
+    }
+    Ok(out)
+}
+
+fn decode_blte_chunk(chunk: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let (&mode, payload) = chunk.split_first().ok_or("empty BLTE chunk")?;
+    match mode {
+        b'N' => Ok(payload.to_vec()),
+        b'Z' => {
+            let mut out = Vec::new();
+            ZlibDecoder::new(payload).read_to_end(&mut out)?;
+            Ok(out)
+        }
+        b'F' => decode_blte(payload),
+        b'E' => Err("encrypted BLTE chunk (no key support)".into()),
+        other => Err(format!("unsupported BLTE chunk mode '{}'", other as char).into()),
+    }
+}
+
+// ----------------------------------------------------------------------------
+// Config files
+// ----------------------------------------------------------------------------
+
+/// Pick the build key for `product` (or the first active product) out of `.build.info`.
+fn read_build_key(install_dir: &Path, product: Option<&str>) -> Result<String, Box<dyn std::error::Error>> {
+    let path = install_dir.join(".build.info");
+    let text = fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut lines = text.lines().filter(|l| !l.trim().is_empty() && !l.starts_with('#'));
+
+    let header = lines.next().ok_or(".build.info is empty")?;
+    let columns: Vec<&str> = header.split('|').map(|c| c.split('!').next().unwrap_or(c)).collect();
+    let column = |name: &str| columns.iter().position(|&c| c == name);
+    let key_idx = column("Build Key").ok_or(".build.info has no Build Key column")?;
+    let active_idx = column("Active");
+    let product_idx = column("Product");
+
+    let rows: Vec<Vec<&str>> = lines.map(|l| l.split('|').collect()).collect();
+    let matches = |row: &Vec<&str>| match (product, product_idx) {
+        (Some(p), Some(i)) => row.get(i) == Some(&p),
+        (Some(_), None) => false,
+        (None, _) => true,
+    };
+    let is_active = |row: &Vec<&str>| active_idx.is_none_or(|i| row.get(i) == Some(&"1"));
+
+    let row = rows
+        .iter()
+        .filter(|r| matches(r))
+        .find(|r| is_active(r))
+        .or_else(|| rows.iter().find(|r| matches(r)))
+        .ok_or_else(|| match product {
+            Some(p) => format!("product '{}' not found in .build.info", p),
+            None => ".build.info lists no builds".to_string(),
+        })?;
+
+    row.get(key_idx)
+        .map(|k| k.to_string())
+        .ok_or_else(|| "short row in .build.info".into())
+}
+
+/// `key = value` pairs of a build config file
+fn read_config_file(data_dir: &Path, key: &str) -> Result<HashMap<String, String>, Box<dyn std::error::Error>> {
+    if key.len() < 4 {
+        return Err(format!("bad config key '{}'", key).into());
+    }
+    let path = data_dir.join("config").join(&key[0..2]).join(&key[2..4]).join(key);
+    let text = fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(text
+        .lines()
+        .filter(|l| !l.starts_with('#'))
+        .filter_map(|l| l.split_once('='))
+        .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+        .collect())
+}
+
+// ----------------------------------------------------------------------------
+// Local indices, encoding and root
+// ----------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Copy)]
+struct IndexEntry {
+    archive: u32,
+    offset: u64,
+    size: u32,
+}
+
+/// Parse the newest `.idx` file of every bucket in `Data/data`.
+fn load_indices(data_dir: &Path) -> Result<HashMap<[u8; 9], IndexEntry>, Box<dyn std::error::Error>> {
+    let mut newest: HashMap<u8, (u32, PathBuf)> = HashMap::new();
+    for entry in fs::read_dir(data_dir)? {
+        let path = entry?.path();
+        let Some(stem) = path.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        let is_idx = path.extension().and_then(|e| e.to_str()).is_some_and(|e| e.eq_ignore_ascii_case("idx"));
+        if !is_idx || stem.len() != 10 {
+            continue;
+        }
+        let (Ok(bucket), Ok(version)) = (u8::from_str_radix(&stem[0..2], 16), u32::from_str_radix(&stem[2..], 16)) else {
+            continue;
+        };
+        let slot = newest.entry(bucket).or_insert((version, path.clone()));
+        if version > slot.0 {
+            *slot = (version, path);
+        }
+    }
+    if newest.is_empty() {
+        return Err(format!("no .idx files in {}", data_dir.display()).into());
+    }
+
+    let mut index = HashMap::new();
+    for (_, path) in newest.values() {
+        let data = fs::read(path)?;
+        if data.len() < 0x28 {
+            return Err(format!("{}: truncated index header", path.display()).into());
+        }
+        let size_len = data[12] as usize;
+        let offset_len = data[13] as usize;
+        let key_len = data[14] as usize;
+        let offset_bits = data[15] as u32;
+        if key_len != 9 || offset_len != 5 || size_len != 4 {
+            return Err(format!("{}: unsupported index layout", path.display()).into());
+        }
+
+        let entries_len = le_u32(&data[0x20..0x24]) as usize;
+        let entry_size = key_len + offset_len + size_len;
+        let entries = data.get(0x28..0x28 + entries_len).ok_or_else(|| format!("{}: truncated entries", path.display()))?;
+
+        for e in entries.chunks_exact(entry_size) {
+            let mut key = [0u8; 9];
+            key.copy_from_slice(&e[0..9]);
+            let packed = e[9..14].iter().fold(0u64, |acc, &b| (acc << 8) | b as u64);
+            let entry = IndexEntry {
+                archive: (packed >> offset_bits) as u32,
+                offset: packed & ((1u64 << offset_bits) - 1),
+                size: le_u32(&e[14..18]),
+            };
+            index.entry(key).or_insert(entry);
+        }
+    }
+    Ok(index)
+}
+
+/// Content key -> first encoding key, from the encoding file's CKey pages
+fn parse_encoding(data: &[u8]) -> Result<HashMap<Key, Key>, Box<dyn std::error::Error>> {
+    if data.len() < 22 || &data[0..2] != b"EN" {
+        return Err("bad encoding file signature".into());
+    }
+    let ckey_size = data[3] as usize;
+    let ekey_size = data[4] as usize;
+    if ckey_size != 16 || ekey_size != 16 {
+        return Err("unsupported encoding key sizes".into());
+    }
+    let page_size = u16::from_be_bytes([data[5], data[6]]) as usize * 1024;
+    let page_count = be_u32(&data[9..13]) as usize;
+    let espec_size = be_u32(&data[18..22]) as usize;
+
+    let pages_start = 22 + espec_size + page_count * 32;
+    let mut map = HashMap::new();
+    for page in 0..page_count {
+        let start = pages_start + page * page_size;
+        let page = data.get(start..start + page_size).ok_or("truncated encoding page")?;
+        let mut pos = 0;
+        while pos + 22 <= page.len() {
+            let key_count = page[pos] as usize;
+            if key_count == 0 {
+                break;
+            }
+            let record = page.get(pos + 6..pos + 22 + key_count * 16).ok_or("truncated encoding record")?;
+            let mut ckey = [0u8; 16];
+            let mut ekey = [0u8; 16];
+            ckey.copy_from_slice(&record[0..16]);
+            ekey.copy_from_slice(&record[16..32]);
+            map.insert(ckey, ekey);
+            pos += 22 + key_count * 16;
+        }
+    }
+    Ok(map)
+}
+
+#[derive(Default)]
+struct RootManifest {
+    by_fdid: HashMap<u32, Key>,
+    by_name_hash: HashMap<u64, u32>,
+}
+
+/// Parse a root manifest: the legacy layout, `MFST` (8.2+) and the versioned `MFST` header of 10.1.7+.
+/// Only blocks tagged with `locale` (one of the `LOCALES` bits) are kept, so each FileDataID
+/// resolves to that locale's copy.
+fn parse_root(data: &[u8], locale: u32) -> Result<RootManifest, Box<dyn std::error::Error>> {
+    let mut root = RootManifest::default();
+    let take = |pos: &mut usize, len: usize| -> Result<&[u8], String> {
+        let slice = data.get(*pos..*pos + len).ok_or("truncated root manifest")?;
+        *pos += len;
+        Ok(slice)
+    };
+
+    let mut pos = 0usize;
+    let mut version = 0u32;
+    let mut allow_unnamed = false;
+    if data.len() >= 12 && &data[0..4] == b"TSFM" {
+        let (first, second) = (le_u32(&data[4..8]), le_u32(&data[8..12]));
+        let (total, named) = if first == 0x18 && second < 10 && data.len() >= 0x18 {
+            version = second;
+            pos = first as usize;
+            (le_u32(&data[12..16]), le_u32(&data[16..20]))
+        } else {
+            version = 1;
+            pos = 12;
+            (first, second)
+        };
+        allow_unnamed = total != named;
+    }
+
+    while pos < data.len() {
+        let count = le_u32(take(&mut pos, 4)?) as usize;
+        let (content_flags, locale_flags) = if version >= 2 {
+            let locale = le_u32(take(&mut pos, 4)?);
+            let flags1 = le_u32(take(&mut pos, 4)?);
+            let flags2 = le_u32(take(&mut pos, 4)?);
+            let flags3 = take(&mut pos, 1)?[0] as u32;
+            (flags1 | flags2 | (flags3 << 17), locale)
+        } else {
+            let flags = le_u32(take(&mut pos, 4)?);
+            let locale = le_u32(take(&mut pos, 4)?);
+            (flags, locale)
+        };
+
+        let deltas = take(&mut pos, count * 4)?;
+        let mut fdids = Vec::with_capacity(count);
+        let mut next = 0i64;
+        for (i, d) in deltas.chunks_exact(4).enumerate() {
+            let delta = le_u32(d) as i32 as i64;
+            let fdid = if i == 0 { delta } else { next + delta };
+            fdids.push(fdid as u32);
+            next = fdid + 1;
+        }
+
+        let (ckeys, hashes): (Vec<&[u8]>, Vec<Option<u64>>) = if version == 0 {
+            let records = take(&mut pos, count * 24)?;
+            records
+                .chunks_exact(24)
+                .map(|r| (&r[0..16], Some(u64::from_le_bytes(r[16..24].try_into().unwrap_or_default()))))
+                .unzip()
+        } else {
+            let ckeys = take(&mut pos, count * 16)?.chunks_exact(16).collect();
+            let hashes = if allow_unnamed && content_flags & CONTENT_NO_NAME_HASH != 0 {
+                vec![None; count]
+            } else {
+                take(&mut pos, count * 8)?
+                    .chunks_exact(8)
+                    .map(|h| Some(u64::from_le_bytes(h.try_into().unwrap_or_default())))
+                    .collect()
+            };
+            (ckeys, hashes)
+        };
+
+        if locale_flags & locale == 0 || content_flags & CONTENT_LOW_VIOLENCE != 0 {
+            continue;
+        }
+        for ((fdid, ckey), hash) in fdids.into_iter().zip(ckeys).zip(hashes) {
+            let mut key = [0u8; 16];
+            key.copy_from_slice(ckey);
+            root.by_fdid.entry(fdid).or_insert(key);
+            if let Some(hash) = hash {
+                root.by_name_hash.entry(hash).or_insert(fdid);
+            }
+        }
+    }
+    Ok(root)
+}
+
+/// Community listfile (`<FileDataID>;<path>` per line)
+fn load_listfile(path: &Path) -> Result<Vec<(u32, String)>, Box<dyn std::error::Error>> {
+    let text = fs::read_to_string(path)?;
+    Ok(text
+        .lines()
+        .filter_map(|l| l.split_once(';'))
+        .filter_map(|(id, name)| Some((id.trim().parse().ok()?, name.trim().replace('\\', "/"))))
+        .collect())
+}
+
+// ----------------------------------------------------------------------------
+// Storage
+// ----------------------------------------------------------------------------
+
+/// A local CASC install (the directory holding `.build.info` and `Data/`), seen in one locale
+pub struct CascStorage {
+    data_dir: PathBuf,
+    index: HashMap<[u8; 9], IndexEntry>,
+    encoding: HashMap<Key, Key>,
+    root: RootManifest,
+    /// Listfile entries in file order, and the same keyed by lower-cased path
+    listfile: Vec<(u32, String)>,
+    listfile_lookup: HashMap<String, u32>,
+}
+
+impl CascStorage {
+    /// Open `install_dir`. `locale` (default `enUS`) picks which copy of localized files,
+    /// such as the DB2 tables, the root resolves to; ADTs are the same in every locale.
+    pub fn open(
+        install_dir: &Path,
+        product: Option<&str>,
+        locale: Option<&str>,
+        listfile: Option<&Path>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let locale = locale_flag(locale.unwrap_or(DEFAULT_LOCALE))?;
+        let data_dir = install_dir.join("Data");
+        let build_key = read_build_key(install_dir, product)?;
+        let build_config = read_config_file(&data_dir, &build_key)?;
+
+        let encoding_keys: Vec<&str> = build_config
+            .get("encoding")
+            .ok_or("build config has no encoding entry")?
+            .split_whitespace()
+            .collect();
+        let encoding_ekey = parse_hex_key(encoding_keys.get(1).ok_or("build config lacks the encoding EKey")?)?;
+        let root_ckey = parse_hex_key(build_config.get("root").ok_or("build config has no root entry")?)?;
+
+        let mut storage = Self {
+            index: load_indices(&data_dir.join("data"))?,
+            data_dir,
+            encoding: HashMap::new(),
+            root: RootManifest::default(),
+            listfile: Vec::new(),
+            listfile_lookup: HashMap::new(),
+        };
+
+        storage.encoding = parse_encoding(&storage.read_ekey(&encoding_ekey).map_err(|e| format!("encoding: {}", e))?)?;
+        let root_ekey = *storage.encoding.get(&root_ckey).ok_or("root is missing from the encoding table")?;
+        storage.root = parse_root(&storage.read_ekey(&root_ekey).map_err(|e| format!("root: {}", e))?, locale)?;
+
+        if let Some(path) = listfile {
+            storage.listfile = load_listfile(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+            storage.listfile_lookup = storage
+                .listfile
+                .iter()
+                .map(|(fdid, name)| (name.to_ascii_lowercase(), *fdid))
+                .collect();
+        }
+        Ok(storage)
+    }
+
+    pub fn file_count(&self) -> usize {
+        self.root.by_fdid.len()
+    }
+
+    /// `(FileDataID, path)` pairs from the listfile, empty if none was given
+    pub fn listfile(&self) -> &[(u32, String)] {
+        &self.listfile
+    }
+
+    /// Resolve a path through the listfile, falling back to the root's name hashes.
+    pub fn fdid_for_name(&self, name: &str) -> Option<u32> {
+        let normalized = name.replace('\\', "/").to_ascii_lowercase();
+        self.listfile_lookup
+            .get(&normalized)
+            .copied()
+            .or_else(|| self.root.by_name_hash.get(&name_hash(&normalized)).copied())
+    }
+
+    pub fn read_fdid(&self, fdid: u32) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let ckey = self.root.by_fdid.get(&fdid).ok_or_else(|| format!("FileDataID {} not in root", fdid))?;
+        let ekey = self
+            .encoding
+            .get(ckey)
+            .ok_or_else(|| format!("FileDataID {}: content key {} not in encoding", fdid, hex(ckey)))?;
+        self.read_ekey(ekey).map_err(|e| format!("FileDataID {}: {}", fdid, e).into())
+    }
+
+    pub fn read_name(&self, name: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let fdid = self.fdid_for_name(name).ok_or_else(|| format!("{} not found (no listfile entry or name hash)", name))?;
+        self.read_fdid(fdid)
+    }
+
+    fn read_ekey(&self, ekey: &Key) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let mut short = [0u8; 9];
+        short.copy_from_slice(&ekey[..9]);
+        let entry = self.index.get(&short).ok_or_else(|| format!("encoding key {} not in local indices", hex(ekey)))?;
+
+        let path = self.data_dir.join("data").join(format!("data.{:03}", entry.archive));
+        let mut file = File::open(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        file.seek(SeekFrom::Start(entry.offset))?;
+        let mut raw = vec![0u8; entry.size as usize];
+        file.read_exact(&mut raw)?;
+
+        let blte = raw.get(DATA_HEADER_SIZE..).ok_or("data entry shorter than its header")?;
+        decode_blte(blte)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TempDir;
+    use std::io::Write;
+
+    fn zlib(data: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    /// A BLTE blob with one chunk per `(mode, payload)`; a single chunk gets no chunk table
+    fn blte(chunks: &[(u8, Vec<u8>)]) -> Vec<u8> {
+        let encoded: Vec<Vec<u8>> = chunks.iter().map(|(mode, payload)| [&[*mode][..], payload].concat()).collect();
+        let mut out = b"BLTE".to_vec();
+        if let [only] = &encoded[..] {
+            out.extend(0u32.to_be_bytes());
+            out.extend(only);
+            return out;
+        }
+        let header_size = 12 + encoded.len() * 24;
+        out.extend((header_size as u32).to_be_bytes());
+        out.extend([0x0F, 0, 0, encoded.len() as u8]);
+        for chunk in &encoded {
+            out.extend((chunk.len() as u32).to_be_bytes());
+            out.extend([0u8; 20]);
+        }
+        for chunk in encoded {
+            out.extend(chunk);
+        }
+        out
+    }
+
+    fn key(n: u8) -> Key {
+        [n; 16]
+    }
+
+    /// `.idx` file: 9-byte key, 5-byte big-endian (archive << 30 | offset), little-endian size
+    fn idx_file(entries: &[(Key, u32, u64, u32)]) -> Vec<u8> {
+        let mut out = vec![0u8; 0x28];
+        out[12..16].copy_from_slice(&[4, 5, 9, 30]);
+        out[0x20..0x24].copy_from_slice(&(entries.len() as u32 * 18).to_le_bytes());
+        for &(ekey, archive, offset, size) in entries {
+            out.extend(&ekey[..9]);
+            out.extend(&(((archive as u64) << 30) | offset).to_be_bytes()[3..]);
+            out.extend(size.to_le_bytes());
+        }
+        out
+    }
+
+    /// Encoding file with one 1 KB CKey page
+    fn encoding_file(pairs: &[(Key, Key)]) -> Vec<u8> {
+        let mut out = b"EN".to_vec();
+        out.extend([1, 16, 16]);
+        out.extend(1u16.to_be_bytes());
+        out.extend(1u16.to_be_bytes());
+        out.extend(1u32.to_be_bytes());
+        out.extend(0u32.to_be_bytes());
+        out.push(0);
+        out.extend(0u32.to_be_bytes());
+        out.extend([0u8; 32]);
+        let mut page = Vec::new();
+        for (ckey, ekey) in pairs {
+            page.push(1);
+            page.extend([0u8; 5]);
+            page.extend(ckey);
+            page.extend(ekey);
+        }
+        page.resize(1024, 0);
+        out.extend(page);
+        out
+    }
+
+    /// Locale bits and the `(fdid, ckey, path)` of each file
+    type RootBlock<'a> = (u32, Vec<(u32, Key, &'a str)>);
+
+    /// `MFST` root, one block per locale
+    fn root_file(blocks: &[RootBlock]) -> Vec<u8> {
+        let files: usize = blocks.iter().map(|(_, files)| files.len()).sum();
+        let mut out = b"TSFM".to_vec();
+        out.extend((files as u32).to_le_bytes());
+        out.extend((files as u32).to_le_bytes());
+        for (locale, files) in blocks {
+            out.extend((files.len() as u32).to_le_bytes());
+            out.extend(0u32.to_le_bytes());
+            out.extend(locale.to_le_bytes());
+            let mut next = 0i64;
+            for (i, (fdid, _, _)) in files.iter().enumerate() {
+                let delta = if i == 0 { *fdid as i64 } else { *fdid as i64 - next };
+                out.extend((delta as i32).to_le_bytes());
+                next = *fdid as i64 + 1;
+            }
+            for (_, ckey, _) in files {
+                out.extend(ckey);
+            }
+            for (_, _, path) in files {
+                out.extend(name_hash(path).to_le_bytes());
+            }
+        }
+        out
+    }
+
+    const BUILD_KEY: &str = "0123456789abcdef0123456789abcdef";
+    const ADT_FDID: u32 = 775_971;
+    const TABLE_FDID: u32 = 1_353_545;
+
+    /// A storage with one ADT, one localized table (enUS and deDE) and the encoding and
+    /// root files, all in `data.000`
+    fn fixture_storage(dir: &Path) {
+        let adt: Vec<u8> = (0..3000u32).map(|i| (i % 251) as u8).collect();
+        let files: Vec<(Key, Key, Vec<u8>)> = vec![
+            (key(1), key(0x11), blte(&[(b'N', adt[..1000].to_vec()), (b'Z', zlib(&adt[1000..]))])),
+            (key(2), key(0x12), blte(&[(b'N', b"areas enUS".to_vec())])),
+            (key(3), key(0x13), blte(&[(b'Z', zlib(b"areas deDE"))])),
+        ];
+        let root = root_file(&[
+            (
+                0x2,
+                vec![
+                    (ADT_FDID, key(1), "world/maps/test/test_32_48.adt"),
+                    (TABLE_FDID, key(2), "dbfilesclient/areatable.db2"),
+                ],
+            ),
+            (0x20, vec![(TABLE_FDID, key(3), "dbfilesclient/areatable.db2")]),
+        ]);
+        let mut encoding_pairs: Vec<(Key, Key)> = files.iter().map(|(ckey, ekey, _)| (*ckey, *ekey)).collect();
+        encoding_pairs.push((key(0xA0), key(0xB0)));
+        let blobs: Vec<(Key, Vec<u8>)> = files
+            .into_iter()
+            .map(|(_, ekey, blob)| (ekey, blob))
+            .chain([
+                (key(0xB0), blte(&[(b'N', root)])),
+                (key(0xE0), blte(&[(b'N', encoding_file(&encoding_pairs))])),
+            ])
+            .collect();
+
+        let data_dir = dir.join("Data").join("data");
+        fs::create_dir_all(&data_dir).unwrap();
+        let mut archive = Vec::new();
+        let mut entries = Vec::new();
+        for (ekey, blob) in blobs {
+            entries.push((ekey, 0, archive.len() as u64, (DATA_HEADER_SIZE + blob.len()) as u32));
+            archive.extend([0u8; DATA_HEADER_SIZE]);
+            archive.extend(blob);
+        }
+        fs::write(data_dir.join("data.000"), archive).unwrap();
+        // An older index for the same bucket that must be ignored
+        fs::write(data_dir.join("0000000001.idx"), idx_file(&[(key(0x11), 0, 0, 1)])).unwrap();
+        fs::write(data_dir.join("0000000002.idx"), idx_file(&entries)).unwrap();
+
+        let config_dir = dir.join("Data").join("config").join(&BUILD_KEY[0..2]).join(&BUILD_KEY[2..4]);
+        fs::create_dir_all(&config_dir).unwrap();
+        let config = format!(
+            "# Build Configuration\nroot = {}\nencoding = {} {}\n",
+            hex(&key(0xA0)),
+            hex(&key(0xEE)),
+            hex(&key(0xE0))
+        );
+        fs::write(config_dir.join(BUILD_KEY), config).unwrap();
+        let build_info = format!(
+            "Branch!STRING:0|Active!DEC:1|Build Key!HEX:16|Product!STRING:0\nus|1|{}|wow_classic_era\n",
+            BUILD_KEY
+        );
+        fs::write(dir.join(".build.info"), build_info).unwrap();
+    }
+
+    #[test]
+    fn hashlittle2_matches_lookup3_driver() {
+        assert_eq!(hashlittle2(b""), 0xDEAD_BEEF_DEAD_BEEF);
+        assert_eq!(hashlittle2(b"Four score and seven years ago"), 0x1777_0551_CE72_26E6);
+    }
+
+    #[test]
+    fn name_hash_ignores_case_and_separators() {
+        assert_eq!(name_hash("World/Maps/Azeroth/Azeroth.wdt"), hashlittle2(b"WORLD\\MAPS\\AZEROTH\\AZEROTH.WDT"));
+        assert_eq!(name_hash("world\\maps\\azeroth\\azeroth.wdt"), name_hash("WORLD/MAPS/AZEROTH/AZEROTH.WDT"));
+    }
+
+    #[test]
+    fn decode_blte_frames() {
+        assert_eq!(decode_blte(&blte(&[(b'N', b"plain".to_vec())])).unwrap(), b"plain");
+        assert_eq!(decode_blte(&blte(&[(b'Z', zlib(b"packed"))])).unwrap(), b"packed");
+        let mixed = blte(&[(b'N', b"one, ".to_vec()), (b'Z', zlib(b"two, ")), (b'N', b"three".to_vec())]);
+        assert_eq!(decode_blte(&mixed).unwrap(), b"one, two, three");
+
+        assert!(decode_blte(b"BLT").is_err());
+        assert!(decode_blte(&blte(&[(b'E', vec![0; 8])])).unwrap_err().to_string().contains("encrypted"));
+        let mut truncated = mixed.clone();
+        truncated.truncate(mixed.len() - 2);
+        assert!(decode_blte(&truncated).is_err());
+    }
+
+    #[test]
+    fn parse_encoding_maps_content_to_encoding_keys() {
+        let map = parse_encoding(&encoding_file(&[(key(1), key(0x11)), (key(2), key(0x12))])).unwrap();
+        assert_eq!(map.len(), 2);
+        assert_eq!(map[&key(2)], key(0x12));
+        assert!(parse_encoding(b"XX").is_err());
+    }
+
+    #[test]
+    fn parse_root_keeps_the_chosen_locale() {
+        let data = root_file(&[
+            (0x2, vec![(10, key(1), "a.txt"), (12, key(2), "b.txt")]),
+            (0x20, vec![(12, key(3), "b.txt")]),
+        ]);
+        let en = parse_root(&data, locale_flag("enUS").unwrap()).unwrap();
+        assert_eq!(en.by_fdid.len(), 2);
+        assert_eq!(en.by_fdid[&12], key(2));
+        assert_eq!(en.by_name_hash[&name_hash("a.txt")], 10);
+
+        let de = parse_root(&data, locale_flag("dede").unwrap()).unwrap();
+        assert_eq!(de.by_fdid.len(), 1);
+        assert_eq!(de.by_fdid[&12], key(3));
+        assert!(locale_flag("xxXX").is_err());
+    }
+
+    #[test]
+    fn load_indices_reads_the_newest_version() {
+        let dir = TempDir::new("casc-idx");
+        fs::write(dir.0.join("0000000001.idx"), idx_file(&[(key(1), 0, 5, 1)])).unwrap();
+        fs::write(dir.0.join("0000000002.idx"), idx_file(&[(key(1), 3, 0x1234, 99), (key(2), 1, 7, 8)])).unwrap();
+        fs::write(dir.0.join("0100000001.idx"), idx_file(&[(key(4), 2, 0, 16)])).unwrap();
+
+        let index = load_indices(&dir.0).unwrap();
+        assert_eq!(index.len(), 3);
+        let entry = index[&key(1)[..9]];
+        assert_eq!((entry.archive, entry.offset, entry.size), (3, 0x1234, 99));
+        assert_eq!(index[&key(4)[..9]].archive, 2);
+    }
+
+    #[test]
+    fn storage_reads_files_end_to_end() {
+        let dir = TempDir::new("casc-storage");
+        fixture_storage(&dir.0);
+
+        let storage = CascStorage::open(&dir.0, Some("wow_classic_era"), None, None).unwrap();
+        assert_eq!(storage.file_count(), 2);
+        let adt: Vec<u8> = (0..3000u32).map(|i| (i % 251) as u8).collect();
+        assert_eq!(storage.read_fdid(ADT_FDID).unwrap(), adt);
+        assert_eq!(storage.read_name("World\\Maps\\Test\\Test_32_48.adt").unwrap(), adt);
+        assert_eq!(storage.read_name("DBFilesClient/AreaTable.db2").unwrap(), b"areas enUS");
+        assert!(storage.read_fdid(1).is_err());
+
+        let german = CascStorage::open(&dir.0, None, Some("deDE"), None).unwrap();
+        assert_eq!(german.read_fdid(TABLE_FDID).unwrap(), b"areas deDE");
+        assert!(CascStorage::open(&dir.0, Some("wow_beta"), None, None).is_err());
+    }
+}
@@ -0,0 +1,480 @@
+// ============================================================================
+// Command-line parsing
+// ============================================================================
+
+use std::path::PathBuf;
+
+use zone_map::component::Connectivity;
+use zone_map::config::{OutputFormat, TileEncoding};
+
+pub const USAGE: &str = "\
+Usage: zone-map [COMMAND] [OPTIONS]
+
+Commands:
+  generate      Parse ADTs and write the addon data files (default)
+  validate      Check the AreaTable and ADTs for inconsistencies (takes the
+                generate input options; exits non-zero on errors)
+  diff          Compare two input sets or two generated Data directories
+  render        Draw a generated grid (or one root zone) to PNG or SVG in its addon colors
+  query         Look up areas in the AreaTable by ID or name
+  lookup        Find the area at a world position on a map
+  casc-extract  Copy files (e.g. AreaTable/UiMap DB2s) out of a local CASC install
+  help          Print this message (so does -h or --help after any command)
+
+Common options:
+  --config <TOML>         Project config listing inputs and maps [default: zonemap.toml
+                          if present, otherwise Kalimdor and Azeroth from the repo root]
+  --area-table <CSV>      AreaTable CSV export (overrides the config)
+
+Generate options:
+  --map-to-area <CSV>     UiMap ID to area ID CSV (overrides the config)
+  --rules <TOML>          Area rules marking areas as sea, hidden or merged into their
+                          parent (overrides the config)
+  --adt-dir <MAP>=<DIR>   Directory of root ADTs for a map (repeatable, overrides or
+                          adds to the maps in the config)
+  --adt-root <DIR>        Search a directory tree for <Map>_<X>_<Y>.adt and export
+                          every map found; configured maps then only supply grid
+                          names, UiMap IDs and output files
+  --mpq-dir <DIR>         Like --adt-root, but read the ADTs straight out of a client
+                          Data directory's MPQs (patch-N.MPQ overrides earlier ones)
+  --casc-dir <DIR>        Like --adt-root, but read the ADTs from a local CASC install
+                          (the directory holding .build.info)
+  --out-dir <DIR>         Output directory for generated Lua (overrides the config)
+  --tile-encoding <ENC>   palette-rle (compact, default) or u32 (raw u32 per chunk)
+  --format <FMT>          lua (for the addon, default), json (versioned schema for other
+                          tools, see the README) or both
+  --outlines              Also write <Grid>_outlines.lua with every area as polygons
+  --simplify <YARDS>      Douglas-Peucker tolerance for outlines (implies --outlines)
+  --borders               Also write <Grid>_borders.lua with zone and subzone borders
+  --geojson               Also write <Grid>.geojson: every area as a (multi)polygon in
+                          world coordinates with its AreaInfo as properties
+  --connectivity <4|8>    Whether corner-touching chunks join one fragment [default: 4]
+  --fragment-report <FILE>
+                          Write every exclave, enclave and single-chunk speck to FILE
+  --strict                Fail when validation finds errors (dangling or cyclic parents,
+                          unknown area IDs, ContinentID mismatches)
+
+CASC options (generate and casc-extract):
+  --casc-product <NAME>   .build.info product, e.g. wow_classic_era [default: first active]
+  --casc-locale <CODE>    Locale of localized files such as the DB2s, e.g. deDE [default: enUS]
+  --listfile <CSV>        Community listfile (<FileDataID>;<path>); without one only
+                          configured maps (via their WDTs) and hashed names resolve
+
+Diff options:
+  --old <PATH>            Config file (ADTs + AreaTable are read) or generated Data dir
+  --new <PATH>            The same for the dataset to compare against
+  --report <FILE>         Write the change report to FILE instead of printing it
+  --mask-dir <DIR>        Write <Grid>_changes.png (one pixel per chunk) for every grid
+
+Render options:
+  --data <DIR>            Generated Data directory [default: the config's out_dir]
+  --grid <NAME>           Only render this grid [default: every grid]
+  --zone <AREA>           Only render one root zone (ID or name; a subzone selects
+                          its root zone), cropped to it
+  --scale <PX>            Pixels per chunk [default: 4]
+  --borders               Black lines between zones, darker lines between subzones
+  --labels                Area IDs at their label anchors
+  --svg                   Write one <path> per area with its AreaInfo as data-*
+                          attributes and a <title>, instead of a PNG
+  --out-dir <DIR>         Where to write <Grid>.png or <Grid>_<ZoneID>.png (.svg with
+                          --svg) [default: .]
+
+Query options:
+  <AREA>                  Area ID or (case-insensitive) name fragment
+
+Lookup options:
+  --map <MAP>             Grid name, ADT prefix or map ID (from the config)
+  <X> <Y>                 World coordinates in yards
+
+Casc-extract options:
+  --out-dir <DIR>         Where to write the files [default: .]
+  <FILE>...               FileDataIDs (written as <id>.bin) or paths such as
+                          dbfilesclient/areatable.db2
+";
+
+#[derive(Debug)]
+pub enum Command {
+    Generate(GenerateArgs),
+    Validate(GenerateArgs),
+    Diff(DiffArgs),
+    Render(RenderArgs),
+    Query(QueryArgs),
+    Lookup(LookupArgs),
+    CascExtract(CascExtractArgs),
+    Help,
+}
+
+/// Path overrides are `None` when the config (or its defaults) should be used
+#[derive(Debug)]
+pub struct GenerateArgs {
+    pub config: Option<PathBuf>,
+    pub area_table: Option<PathBuf>,
+    pub map_to_area: Option<PathBuf>,
+    pub rules: Option<PathBuf>,
+    pub adt_dirs: Vec<(String, PathBuf)>,
+    pub adt_root: Option<PathBuf>,
+    pub mpq_dir: Option<PathBuf>,
+    pub casc_dir: Option<PathBuf>,
+    pub casc_product: Option<String>,
+    pub casc_locale: Option<String>,
+    pub listfile: Option<PathBuf>,
+    pub out_dir: Option<PathBuf>,
+    pub tile_encoding: Option<TileEncoding>,
+    pub format: Option<OutputFormat>,
+    pub outlines: bool,
+    pub simplify: Option<f64>,
+    pub borders: bool,
+    pub geojson: bool,
+    pub connectivity: Option<Connectivity>,
+    pub fragment_report: Option<PathBuf>,
+    pub strict: bool,
+}
+
+#[derive(Debug)]
+pub struct CascExtractArgs {
+    pub config: Option<PathBuf>,
+    pub casc_dir: Option<PathBuf>,
+    pub casc_product: Option<String>,
+    pub casc_locale: Option<String>,
+    pub listfile: Option<PathBuf>,
+    pub out_dir: PathBuf,
+    pub files: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct DiffArgs {
+    pub old: PathBuf,
+    pub new: PathBuf,
+    pub report: Option<PathBuf>,
+    pub mask_dir: Option<PathBuf>,
+}
+
+#[derive(Debug)]
+pub struct RenderArgs {
+    pub config: Option<PathBuf>,
+    pub data: Option<PathBuf>,
+    pub grid: Option<String>,
+    pub zone: Option<String>,
+    pub scale: u32,
+    pub borders: bool,
+    pub labels: bool,
+    pub svg: bool,
+    pub out_dir: PathBuf,
+}
+
+#[derive(Debug)]
+pub struct QueryArgs {
+    pub config: Option<PathBuf>,
+    pub area_table: Option<PathBuf>,
+    pub area: String,
+}
+
+#[derive(Debug)]
+pub struct LookupArgs {
+    pub config: Option<PathBuf>,
+    pub area_table: Option<PathBuf>,
+    pub map: String,
+    pub x: f64,
+    pub y: f64,
+}
+
+/// Walks the raw argument list, handing out flag values as they are requested.
+struct ArgCursor {
+    args: std::vec::IntoIter<String>,
+}
+
+impl ArgCursor {
+    fn next(&mut self) -> Option<String> {
+        self.args.next()
+    }
+
+    /// Splits `--flag=value` or reads the value from the next argument.
+    fn value(&mut self, flag: &str, inline: Option<String>) -> Result<String, String> {
+        match inline {
+            Some(v) => Ok(v),
+            None => self.next().ok_or_else(|| format!("missing value for {}", flag)),
+        }
+    }
+}
+
+fn split_flag(arg: &str) -> (&str, Option<String>) {
+    match arg.split_once('=') {
+        Some((flag, value)) if arg.starts_with("--") => (flag, Some(value.to_string())),
+        _ => (arg, None),
+    }
+}
+
+fn parse_map_dir(value: &str) -> Result<(String, PathBuf), String> {
+    match value.split_once('=') {
+        Some((map, dir)) if !map.is_empty() && !dir.is_empty() => {
+            Ok((map.to_string(), PathBuf::from(dir)))
+        }
+        _ => Err(format!("expected <MAP>=<DIR>, got '{}'", value)),
+    }
+}
+
+pub fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<Command, String> {
+    let mut cursor = ArgCursor { args: args.into_iter().collect::<Vec<_>>().into_iter() };
+
+    // A bare `zone-map` (or one starting with options) keeps the old `cargo run` behaviour
+    let mut pending = None;
+    let command = match cursor.next() {
+        None => "generate".to_string(),
+        Some(arg) if arg.starts_with("--") && arg != "--help" => {
+            pending = Some(arg);
+            "generate".to_string()
+        }
+        Some(arg) => arg,
+    };
+
+    // `--help` anywhere after a command prints the usage instead of parsing the rest
+    let rest: Vec<String> = cursor.args.collect();
+    if rest.iter().any(|arg| arg == "--help" || arg == "-h") {
+        return Ok(Command::Help);
+    }
+    let cursor = ArgCursor { args: rest.into_iter() };
+
+    match command.as_str() {
+        "generate" => parse_generate(cursor, pending).map(Command::Generate),
+        "validate" => parse_generate(cursor, None).map(Command::Validate),
+        "diff" => parse_diff(cursor).map(Command::Diff),
+        "render" => parse_render(cursor).map(Command::Render),
+        "query" => parse_query(cursor).map(Command::Query),
+        "lookup" => parse_lookup(cursor).map(Command::Lookup),
+        "casc-extract" => parse_casc_extract(cursor).map(Command::CascExtract),
+        "help" | "-h" | "--help" => Ok(Command::Help),
+        other => Err(format!("unknown command '{}'", other)),
+    }
+}
+
+fn parse_generate(mut cursor: ArgCursor, pending: Option<String>) -> Result<GenerateArgs, String> {
+    let mut args = GenerateArgs {
+        config: None,
+        area_table: None,
+        map_to_area: None,
+        rules: None,
+        adt_dirs: Vec::new(),
+        adt_root: None,
+        mpq_dir: None,
+        casc_dir: None,
+        casc_product: None,
+        casc_locale: None,
+        listfile: None,
+        out_dir: None,
+        tile_encoding: None,
+        format: None,
+        outlines: false,
+        simplify: None,
+        borders: false,
+        geojson: false,
+        connectivity: None,
+        fragment_report: None,
+        strict: false,
+    };
+
+    let mut next = pending;
+    while let Some(arg) = next.take().or_else(|| cursor.next()) {
+        let (flag, inline) = split_flag(&arg);
+        match flag {
+            "--config" => args.config = Some(cursor.value(flag, inline)?.into()),
+            "--area-table" => args.area_table = Some(cursor.value(flag, inline)?.into()),
+            "--map-to-area" => args.map_to_area = Some(cursor.value(flag, inline)?.into()),
+            "--adt-dir" => args.adt_dirs.push(parse_map_dir(&cursor.value(flag, inline)?)?),
+            "--adt-root" => args.adt_root = Some(cursor.value(flag, inline)?.into()),
+            "--mpq-dir" => args.mpq_dir = Some(cursor.value(flag, inline)?.into()),
+            "--casc-dir" => args.casc_dir = Some(cursor.value(flag, inline)?.into()),
+            "--casc-product" => args.casc_product = Some(cursor.value(flag, inline)?),
+            "--casc-locale" => args.casc_locale = Some(cursor.value(flag, inline)?),
+            "--listfile" => args.listfile = Some(cursor.value(flag, inline)?.into()),
+            "--out-dir" => args.out_dir = Some(cursor.value(flag, inline)?.into()),
+            "--tile-encoding" => {
+                args.tile_encoding = Some(TileEncoding::parse(&cursor.value(flag, inline)?)?)
+            }
+            "--format" => args.format = Some(OutputFormat::parse(&cursor.value(flag, inline)?)?),
+            "--outlines" => args.outlines = true,
+            "--borders" => args.borders = true,
+            "--geojson" => args.geojson = true,
+            "--strict" => args.strict = true,
+            "--connectivity" => args.connectivity = Some(Connectivity::parse(&cursor.value(flag, inline)?)?),
+            "--fragment-report" => args.fragment_report = Some(cursor.value(flag, inline)?.into()),
+            "--rules" => args.rules = Some(cursor.value(flag, inline)?.into()),
+            "--simplify" => {
+                let value = cursor.value(flag, inline)?;
+                let yards = value
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|y| *y >= 0.0)
+                    .ok_or_else(|| format!("--simplify expects a number of yards, got '{}'", value))?;
+                args.simplify = Some(yards);
+            }
+            _ => return Err(format!("unknown option '{}' for generate", arg)),
+        }
+    }
+
+    Ok(args)
+}
+
+fn parse_diff(mut cursor: ArgCursor) -> Result<DiffArgs, String> {
+    let mut old = None;
+    let mut new = None;
+    let mut report = None;
+    let mut mask_dir = None;
+
+    while let Some(arg) = cursor.next() {
+        let (flag, inline) = split_flag(&arg);
+        match flag {
+            "--old" => old = Some(cursor.value(flag, inline)?.into()),
+            "--new" => new = Some(cursor.value(flag, inline)?.into()),
+            "--report" => report = Some(cursor.value(flag, inline)?.into()),
+            "--mask-dir" => mask_dir = Some(cursor.value(flag, inline)?.into()),
+            _ => return Err(format!("unknown option '{}' for diff", arg)),
+        }
+    }
+
+    Ok(DiffArgs {
+        old: old.ok_or("diff needs --old")?,
+        new: new.ok_or("diff needs --new")?,
+        report,
+        mask_dir,
+    })
+}
+
+fn parse_render(mut cursor: ArgCursor) -> Result<RenderArgs, String> {
+    let mut args = RenderArgs {
+        config: None,
+        data: None,
+        grid: None,
+        zone: None,
+        scale: 4,
+        borders: false,
+        labels: false,
+        svg: false,
+        out_dir: PathBuf::from("."),
+    };
+
+    while let Some(arg) = cursor.next() {
+        let (flag, inline) = split_flag(&arg);
+        match flag {
+            "--config" => args.config = Some(cursor.value(flag, inline)?.into()),
+            "--data" => args.data = Some(cursor.value(flag, inline)?.into()),
+            "--grid" => args.grid = Some(cursor.value(flag, inline)?),
+            "--zone" => args.zone = Some(cursor.value(flag, inline)?),
+            "--scale" => {
+                let value = cursor.value(flag, inline)?;
+                args.scale = value
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|px| (1..=64).contains(px))
+                    .ok_or_else(|| format!("--scale expects 1 to 64 pixels per chunk, got '{}'", value))?;
+            }
+            "--borders" => args.borders = true,
+            "--labels" => args.labels = true,
+            "--svg" => args.svg = true,
+            "--out-dir" => args.out_dir = cursor.value(flag, inline)?.into(),
+            _ => return Err(format!("unknown option '{}' for render", arg)),
+        }
+    }
+
+    Ok(args)
+}
+
+fn parse_query(mut cursor: ArgCursor) -> Result<QueryArgs, String> {
+    let mut config = None;
+    let mut area_table = None;
+    let mut area = None;
+
+    while let Some(arg) = cursor.next() {
+        let (flag, inline) = split_flag(&arg);
+        match flag {
+            "--config" => config = Some(cursor.value(flag, inline)?.into()),
+            "--area-table" => area_table = Some(cursor.value(flag, inline)?.into()),
+            _ if !arg.starts_with("--") && area.is_none() => area = Some(arg),
+            _ => return Err(format!("unexpected argument '{}' for query", arg)),
+        }
+    }
+
+    let area = area.ok_or("query needs an area ID or name")?;
+    Ok(QueryArgs { config, area_table, area })
+}
+
+fn parse_lookup(mut cursor: ArgCursor) -> Result<LookupArgs, String> {
+    let mut config = None;
+    let mut area_table = None;
+    let mut map = None;
+    let mut coords = Vec::new();
+
+    while let Some(arg) = cursor.next() {
+        let (flag, inline) = split_flag(&arg);
+        match flag {
+            "--config" => config = Some(cursor.value(flag, inline)?.into()),
+            "--area-table" => area_table = Some(cursor.value(flag, inline)?.into()),
+            "--map" => map = Some(cursor.value(flag, inline)?),
+            // Negative coordinates start with '-', so only `--` marks a flag
+            _ if !arg.starts_with("--") && coords.len() < 2 => {
+                coords.push(arg.parse::<f64>().map_err(|_| format!("expected a world coordinate, got '{}'", arg))?)
+            }
+            _ => return Err(format!("unexpected argument '{}' for lookup", arg)),
+        }
+    }
+
+    let [x, y] = coords[..] else {
+        return Err("lookup needs world X and Y coordinates".into());
+    };
+    Ok(LookupArgs { config, area_table, map: map.ok_or("lookup needs --map")?, x, y })
+}
+
+fn parse_casc_extract(mut cursor: ArgCursor) -> Result<CascExtractArgs, String> {
+    let mut args = CascExtractArgs {
+        config: None,
+        casc_dir: None,
+        casc_product: None,
+        casc_locale: None,
+        listfile: None,
+        out_dir: PathBuf::from("."),
+        files: Vec::new(),
+    };
+
+    while let Some(arg) = cursor.next() {
+        let (flag, inline) = split_flag(&arg);
+        match flag {
+            "--config" => args.config = Some(cursor.value(flag, inline)?.into()),
+            "--casc-dir" => args.casc_dir = Some(cursor.value(flag, inline)?.into()),
+            "--casc-product" => args.casc_product = Some(cursor.value(flag, inline)?),
+            "--casc-locale" => args.casc_locale = Some(cursor.value(flag, inline)?),
+            "--listfile" => args.listfile = Some(cursor.value(flag, inline)?.into()),
+            "--out-dir" => args.out_dir = cursor.value(flag, inline)?.into(),
+            _ if !arg.starts_with("--") => args.files.push(arg),
+            _ => return Err(format!("unknown option '{}' for casc-extract", arg)),
+        }
+    }
+
+    if args.files.is_empty() {
+        return Err("casc-extract needs at least one FileDataID or path".into());
+    }
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> Result<Command, String> {
+        parse_args(line.split_whitespace().map(str::to_string))
+    }
+
+    #[test]
+    fn help_works_after_every_command() {
+        for command in ["", "generate", "validate", "diff", "render", "query", "lookup", "casc-extract", "help"] {
+            for flag in ["--help", "-h"] {
+                let line = format!("{} {}", command, flag);
+                assert!(matches!(parse(&line), Ok(Command::Help)), "{}", line);
+            }
+        }
+        // After other options, and before arguments a command would reject
+        assert!(matches!(parse("--out-dir Data --help"), Ok(Command::Help)));
+        assert!(matches!(parse("lookup --map Kalimdor -h"), Ok(Command::Help)));
+        assert!(matches!(parse("diff --bogus --help"), Ok(Command::Help)));
+        assert!(parse("diff --bogus").is_err());
+        assert!(parse("frobnicate").is_err());
+    }
+}
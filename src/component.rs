@@ -0,0 +1,245 @@
+// ============================================================================
+// Connected components: each contiguous patch of one area on the chunk grid
+// ============================================================================
+
+use std::collections::{BTreeSet, HashMap};
+
+use crate::area::AreaTable;
+use crate::grid::ChunkGrid;
+
+/// Which chunks count as touching
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Connectivity {
+    /// Shared edges only
+    Four,
+    /// Edges and corners
+    Eight,
+}
+
+impl Connectivity {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "4" => Ok(Self::Four),
+            "8" => Ok(Self::Eight),
+            _ => Err(format!("connectivity must be 4 or 8, got '{}'", s)),
+        }
+    }
+
+    fn offsets(self) -> &'static [(i32, i32)] {
+        match self {
+            Self::Four => &[(1, 0), (-1, 0), (0, 1), (0, -1)],
+            Self::Eight => &[(1, 0), (-1, 0), (0, 1), (0, -1), (1, 1), (1, -1), (-1, 1), (-1, -1)],
+        }
+    }
+}
+
+/// One contiguous patch of an area
+pub struct Fragment {
+    pub area: u32,
+    pub chunks: u32,
+    /// Global `(x, y)` of every chunk in the fragment
+    pub cells: Vec<(i32, i32)>,
+    /// Lowest (y, x) chunk of the fragment, to point at it in reports
+    pub first_chunk: (i32, i32),
+    /// The only other area touching the fragment's edges, when it is entirely surrounded
+    /// by one area (chunks with AreaID 0 and the map edge count as touching nothing else)
+    pub surrounded_by: Option<u32>,
+}
+
+/// Label every fragment on the grid, largest first within each area
+pub fn find_fragments(grid: &ChunkGrid, connectivity: Connectivity) -> Vec<Fragment> {
+    let chunks = grid.chunks();
+    let index: HashMap<(i32, i32), usize> = chunks.iter().enumerate().map(|(i, &(x, y, _))| ((x, y), i)).collect();
+    let mut seen = vec![false; chunks.len()];
+    let mut fragments = Vec::new();
+
+    for start in 0..chunks.len() {
+        if seen[start] {
+            continue;
+        }
+        let area = chunks[start].2;
+        let mut cells = Vec::new();
+        let mut first_chunk = (i32::MAX, i32::MAX);
+        let mut touching = BTreeSet::new();
+        let mut stack = vec![start];
+        seen[start] = true;
+
+        while let Some(i) = stack.pop() {
+            let (x, y, _) = chunks[i];
+            cells.push((x, y));
+            first_chunk = first_chunk.min((y, x));
+
+            for &(dx, dy) in connectivity.offsets() {
+                let (nx, ny) = (x + dx, y + dy);
+                let other = grid.area_at(nx, ny);
+                if other != area {
+                    // Enclosure is judged on shared edges only, and AreaID 0 encloses nothing
+                    if (dx == 0 || dy == 0) && other != 0 {
+                        touching.insert(other);
+                    }
+                    continue;
+                }
+                let j = index[&(nx, ny)];
+                if !seen[j] {
+                    seen[j] = true;
+                    stack.push(j);
+                }
+            }
+        }
+
+        let surrounded_by = match (touching.len(), touching.first()) {
+            (1, Some(&other)) => Some(other),
+            _ => None,
+        };
+        fragments.push(Fragment { area, chunks: cells.len() as u32, cells, first_chunk, surrounded_by });
+    }
+
+    fragments.sort_by(|a, b| a.area.cmp(&b.area).then(b.chunks.cmp(&a.chunks)).then(a.first_chunk.cmp(&b.first_chunk)));
+    fragments
+}
+
+/// How many of one map's fragments each section of the report lists
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FragmentCounts {
+    pub fragments: usize,
+    pub exclaves: usize,
+    pub enclaves: usize,
+    pub specks: usize,
+}
+
+/// Append one map's exclaves, enclaves and specks to the fragment report
+pub fn report_fragments(
+    report: &mut String,
+    grid_name: &str,
+    fragments: &[Fragment],
+    areas: &AreaTable,
+) -> Result<FragmentCounts, std::fmt::Error> {
+    use std::fmt::Write as _;
+
+    let name = |id: u32| areas.get(&id).map(|a| a.name.clone()).unwrap_or_else(|| format!("Unknown_{}", id));
+    let at = |f: &Fragment| {
+        let (y, x) = f.first_chunk;
+        format!("tile {}_{} chunk {},{}", x / 16, y / 16, x % 16, y % 16)
+    };
+
+    // Fragments come largest first within each area, so the first one is the main body
+    let mut exclaves = Vec::new();
+    let mut enclaves = Vec::new();
+    let mut specks = Vec::new();
+    let mut main_body: HashMap<u32, u32> = HashMap::new();
+    for f in fragments {
+        match main_body.get(&f.area) {
+            Some(&main) => exclaves.push(format!(
+                "  {} {}: {} chunks at {} (main body {} chunks)",
+                f.area, name(f.area), f.chunks, at(f), main
+            )),
+            None => {
+                main_body.insert(f.area, f.chunks);
+            }
+        }
+        if let Some(outer) = f.surrounded_by {
+            enclaves.push(format!(
+                "  {} {}: {} chunks at {} inside {} {}",
+                f.area, name(f.area), f.chunks, at(f), outer, name(outer)
+            ));
+        }
+        if f.chunks == 1 {
+            specks.push(format!("  {} {}: {}", f.area, name(f.area), at(f)));
+        }
+    }
+
+    writeln!(report, "== {} ==", grid_name)?;
+    for (title, lines) in [
+        ("Exclaves (fragments detached from the area's largest one)", &exclaves),
+        ("Enclaves (fragments surrounded by a single other area)", &enclaves),
+        ("Specks (single-chunk fragments)", &specks),
+    ] {
+        writeln!(report, "{}: {}", title, lines.len())?;
+        for line in lines {
+            writeln!(report, "{}", line)?;
+        }
+    }
+    writeln!(report)?;
+
+    Ok(FragmentCounts {
+        fragments: fragments.len(),
+        exclaves: exclaves.len(),
+        enclaves: enclaves.len(),
+        specks: specks.len(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{area_table, tiles_from_rows};
+
+    fn fragments(rows: &[&str], connectivity: Connectivity) -> Vec<Fragment> {
+        find_fragments(&ChunkGrid::new(&tiles_from_rows(rows)), connectivity)
+    }
+
+    #[test]
+    fn diagonal_links_only_join_with_eight_connectivity() {
+        let rows = ["1.2", ".1.", "2.1"];
+        let four = fragments(&rows, Connectivity::Four);
+        let eight = fragments(&rows, Connectivity::Eight);
+        assert_eq!(four.iter().filter(|f| f.area == 1).count(), 3);
+        assert_eq!(four.iter().filter(|f| f.area == 2).count(), 2);
+        assert_eq!(eight.iter().filter(|f| f.area == 1).count(), 1);
+        assert_eq!(eight.iter().filter(|f| f.area == 2).count(), 2);
+
+        let diagonal = &eight[0];
+        assert_eq!((diagonal.area, diagonal.chunks, diagonal.first_chunk), (1, 3, (0, 0)));
+        let mut cells = diagonal.cells.clone();
+        cells.sort_unstable();
+        assert_eq!(cells, [(0, 0), (1, 1), (2, 2)]);
+    }
+
+    #[test]
+    fn fragments_come_largest_first() {
+        let found = fragments(&["1.11", "..11", "1..."], Connectivity::Four);
+        let sizes: Vec<(u32, u32)> = found.iter().map(|f| (f.area, f.chunks)).collect();
+        assert_eq!(sizes, [(1, 4), (1, 1), (1, 1)]);
+        // Equal sizes fall back to the first chunk in (y, x) order
+        assert_eq!(found[1].first_chunk, (0, 0));
+        assert_eq!(found[2].first_chunk, (2, 0));
+    }
+
+    #[test]
+    fn empty_chunks_and_the_map_edge_do_not_break_enclosure() {
+        // 2 sits on the map edge and next to an empty chunk, otherwise only touching 1
+        let found = fragments(&["21.", "113"], Connectivity::Four);
+        let two = found.iter().find(|f| f.area == 2).unwrap();
+        assert_eq!(two.surrounded_by, Some(1));
+        // 1 touches both 2 and 3; a fragment touching only empty chunks is not enclosed either
+        assert_eq!(found.iter().find(|f| f.area == 1).unwrap().surrounded_by, None);
+        assert_eq!(fragments(&["...", ".3.", "..."], Connectivity::Four)[0].surrounded_by, None);
+
+        // Touching two areas along edges is not an enclosure, and corners never count
+        let found = fragments(&["111", "132", "222"], Connectivity::Eight);
+        assert_eq!(found.iter().find(|f| f.area == 3).unwrap().surrounded_by, None);
+        let found = fragments(&["211", "131", "111"], Connectivity::Four);
+        assert_eq!(found.iter().find(|f| f.area == 3).unwrap().surrounded_by, Some(1));
+    }
+
+    #[test]
+    fn report_sections() {
+        let areas = area_table(&[(1, "Elwynn Forest", 0), (2, "Goldshire", 1), (3, "Crystal Lake", 1)]);
+        let found = fragments(&["1111.1", "1211..", "111133"], Connectivity::Four);
+        let mut report = String::new();
+        let counts = report_fragments(&mut report, "Azeroth", &found, &areas).unwrap();
+        assert_eq!((counts.fragments, counts.exclaves, counts.enclaves, counts.specks), (4, 1, 2, 2));
+        assert_eq!(
+            report,
+            "== Azeroth ==\n\
+             Exclaves (fragments detached from the area's largest one): 1\n  \
+             1 Elwynn Forest: 1 chunks at tile 0_0 chunk 5,0 (main body 11 chunks)\n\
+             Enclaves (fragments surrounded by a single other area): 2\n  \
+             2 Goldshire: 1 chunks at tile 0_0 chunk 1,1 inside 1 Elwynn Forest\n  \
+             3 Crystal Lake: 2 chunks at tile 0_0 chunk 4,2 inside 1 Elwynn Forest\n\
+             Specks (single-chunk fragments): 2\n  \
+             1 Elwynn Forest: tile 0_0 chunk 5,0\n  \
+             2 Goldshire: tile 0_0 chunk 1,1\n\n"
+        );
+    }
+}
@@ -0,0 +1,381 @@
+// ============================================================================
+// Project configuration (zonemap.toml)
+// ============================================================================
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::component::Connectivity;
+
+pub const DEFAULT_CONFIG: &str = "zonemap.toml";
+
+/// Everything `generate` needs to know: global inputs plus the list of maps to export.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub area_table: PathBuf,
+    pub map_to_area: PathBuf,
+    /// Directory searched recursively for `<Map>_<X>_<Y>.adt`; every map prefix found becomes a grid
+    pub adt_root: Option<PathBuf>,
+    /// Client `Data` directory whose MPQs are searched for `World\Maps\<Map>\<Map>_<X>_<Y>.adt`
+    pub mpq_dir: Option<PathBuf>,
+    /// Install directory (holding `.build.info` and `Data/`) of a CASC client
+    pub casc_dir: Option<PathBuf>,
+    /// `.build.info` product to read, e.g. `wow_classic_era`; the first active one if unset
+    pub casc_product: Option<String>,
+    /// Locale whose copy of localized CASC files (the DB2 tables) is read, e.g. `deDE`; `enUS` if unset
+    pub casc_locale: Option<String>,
+    /// Community listfile (`<FileDataID>;<path>`) used to find ADTs and files by name in CASC
+    pub listfile: Option<PathBuf>,
+    /// Area rules file marking areas as sea, hidden or merged into their parent
+    pub rules: Option<PathBuf>,
+    pub out_dir: PathBuf,
+    pub tile_encoding: TileEncoding,
+    /// Lua for the addon, JSON for other tools, or both
+    pub format: OutputFormat,
+    /// Also write `<Grid>_outlines.lua` with each area traced into polygons
+    pub outlines: bool,
+    /// Douglas-Peucker tolerance for outlines, in yards (0 keeps the chunk staircase)
+    pub simplify: f64,
+    /// Also write `<Grid>_borders.lua` with the edges between areas
+    pub borders: bool,
+    /// Also write `<Grid>.geojson` with every area's polygons and AreaInfo properties
+    pub geojson: bool,
+    /// Whether chunks touching only at a corner belong to the same fragment
+    pub connectivity: Connectivity,
+    /// Where to write the exclave/enclave/speck report, if anywhere
+    pub fragment_report: Option<PathBuf>,
+    /// Fail `generate` when validation finds errors
+    pub strict: bool,
+    pub maps: Vec<MapConfig>,
+}
+
+/// How each tile's 256 area IDs are packed before base64 encoding
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TileEncoding {
+    /// 256 little-endian u32s (the original format)
+    U32,
+    /// Per-tile u16 palette followed by (run length, palette index) byte pairs
+    PaletteRle,
+}
+
+impl TileEncoding {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "u32" => Ok(Self::U32),
+            "palette-rle" => Ok(Self::PaletteRle),
+            _ => Err(format!("unknown tile encoding '{}' (expected u32 or palette-rle)", s)),
+        }
+    }
+
+    /// Inverse of `format_tag`; grids without a tag predate it and are `u32le`
+    pub fn from_tag(tag: Option<&str>) -> Result<Self, String> {
+        match tag {
+            None | Some("u32le") => Ok(Self::U32),
+            Some("palette-rle-v1") => Ok(Self::PaletteRle),
+            Some(other) => Err(format!("unknown tile format '{}'", other)),
+        }
+    }
+
+    /// Tag written as `format` in `RegisterTileGrid` so the addon picks the right decoder
+    pub fn format_tag(self) -> &'static str {
+        match self {
+            Self::U32 => "u32le",
+            Self::PaletteRle => "palette-rle-v1",
+        }
+    }
+}
+
+/// Which files `generate` writes for the tiles, AreaInfo, AreaHierarchy and MapToArea
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Lua,
+    Json,
+    Both,
+}
+
+impl OutputFormat {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "lua" => Ok(Self::Lua),
+            "json" => Ok(Self::Json),
+            "both" => Ok(Self::Both),
+            _ => Err(format!("unknown output format '{}' (expected lua, json or both)", s)),
+        }
+    }
+
+    pub fn lua(self) -> bool {
+        self != Self::Json
+    }
+
+    pub fn json(self) -> bool {
+        self != Self::Lua
+    }
+}
+
+/// One map (continent, battleground, ...) exported as its own tile grid.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MapConfig {
+    /// Grid name passed to `RegisterTileGrid`
+    pub grid: String,
+    /// ADT file prefix (`PVPZone01` in `PVPZone01_31_30.adt`), defaults to the grid name
+    pub map: Option<String>,
+    /// Directory of root ADTs; when unset the tiles come from `adt_root` discovery
+    pub adt_dir: Option<PathBuf>,
+    /// UiMap ID of the continent map the addon shows this grid on
+    pub ui_map_id: Option<u32>,
+    /// Map.db2 ID (0 Eastern Kingdoms, 1 Kalimdor, ...), checked against AreaTable `ContinentID`
+    pub map_id: Option<u32>,
+    /// Output file name, relative to `out_dir`
+    pub output: String,
+}
+
+impl MapConfig {
+    pub fn new(grid: &str) -> Self {
+        Self {
+            grid: grid.to_string(),
+            map: None,
+            adt_dir: None,
+            ui_map_id: None,
+            map_id: None,
+            output: format!("{}_tiles.lua", grid),
+        }
+    }
+
+    pub fn adt_prefix(&self) -> &str {
+        self.map.as_deref().unwrap_or(&self.grid)
+    }
+}
+
+impl Default for Config {
+    /// The two classic continents, laid out the way this repo has always been
+    fn default() -> Self {
+        let mut kalimdor = MapConfig::new("Kalimdor");
+        kalimdor.adt_dir = Some(PathBuf::from("kalimdor_adts"));
+        kalimdor.ui_map_id = Some(1414);
+        kalimdor.map_id = Some(1);
+        let mut azeroth = MapConfig::new("Azeroth");
+        azeroth.adt_dir = Some(PathBuf::from("azeroth_adts"));
+        azeroth.ui_map_id = Some(1415);
+        azeroth.map_id = Some(0);
+
+        Self {
+            area_table: PathBuf::from("AreaTable.1.15.8.64907.csv"),
+            map_to_area: PathBuf::from("mapIdToArea.csv"),
+            adt_root: None,
+            mpq_dir: None,
+            casc_dir: None,
+            casc_product: None,
+            casc_locale: None,
+            listfile: None,
+            rules: None,
+            out_dir: PathBuf::from("Data"),
+            tile_encoding: TileEncoding::PaletteRle,
+            format: OutputFormat::Lua,
+            outlines: false,
+            simplify: 0.0,
+            borders: false,
+            geojson: false,
+            connectivity: Connectivity::Four,
+            fragment_report: None,
+            strict: false,
+            maps: vec![kalimdor, azeroth],
+        }
+    }
+}
+
+impl Config {
+    /// Load a config file. Relative paths inside it are resolved against the file's directory.
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let text = fs::read_to_string(path)?;
+        let table: toml::Table = text.parse()?;
+        let base = path.parent().unwrap_or(Path::new(""));
+
+        let mut config = Config::default();
+        check_keys(&table, "", &["inputs", "output", "analysis", "maps"])?;
+
+        if let Some(inputs) = table_at(&table, "inputs")? {
+            check_keys(
+                inputs,
+                "inputs",
+                &[
+                    "area_table",
+                    "map_to_area",
+                    "adt_root",
+                    "mpq_dir",
+                    "casc_dir",
+                    "casc_product",
+                    "casc_locale",
+                    "listfile",
+                    "rules",
+                ],
+            )?;
+            if let Some(p) = string_at(inputs, "inputs", "area_table")? {
+                config.area_table = base.join(p);
+            }
+            if let Some(p) = string_at(inputs, "inputs", "map_to_area")? {
+                config.map_to_area = base.join(p);
+            }
+            if let Some(p) = string_at(inputs, "inputs", "adt_root")? {
+                config.adt_root = Some(base.join(p));
+            }
+            if let Some(p) = string_at(inputs, "inputs", "mpq_dir")? {
+                config.mpq_dir = Some(base.join(p));
+            }
+            if let Some(p) = string_at(inputs, "inputs", "casc_dir")? {
+                config.casc_dir = Some(base.join(p));
+            }
+            config.casc_product = string_at(inputs, "inputs", "casc_product")?;
+            config.casc_locale = string_at(inputs, "inputs", "casc_locale")?;
+            if let Some(p) = string_at(inputs, "inputs", "listfile")? {
+                config.listfile = Some(base.join(p));
+            }
+            if let Some(p) = string_at(inputs, "inputs", "rules")? {
+                config.rules = Some(base.join(p));
+            }
+        }
+
+        if let Some(output) = table_at(&table, "output")? {
+            check_keys(
+                output,
+                "output",
+                &["dir", "tile_encoding", "format", "outlines", "simplify", "borders", "geojson"],
+            )?;
+            if let Some(p) = string_at(output, "output", "dir")? {
+                config.out_dir = base.join(p);
+            }
+            if let Some(e) = string_at(output, "output", "tile_encoding")? {
+                config.tile_encoding = TileEncoding::parse(&e).map_err(|e| format!("output.tile_encoding: {}", e))?;
+            }
+            if let Some(f) = string_at(output, "output", "format")? {
+                config.format = OutputFormat::parse(&f).map_err(|e| format!("output.format: {}", e))?;
+            }
+            if let Some(b) = bool_at(output, "output", "outlines")? {
+                config.outlines = b;
+            }
+            if let Some(y) = f64_at(output, "output", "simplify")? {
+                config.simplify = y;
+            }
+            if let Some(b) = bool_at(output, "output", "borders")? {
+                config.borders = b;
+            }
+            if let Some(b) = bool_at(output, "output", "geojson")? {
+                config.geojson = b;
+            }
+        }
+
+        if let Some(analysis) = table_at(&table, "analysis")? {
+            check_keys(analysis, "analysis", &["connectivity", "fragment_report", "strict"])?;
+            if let Some(n) = u32_at(analysis, "analysis", "connectivity")? {
+                config.connectivity =
+                    Connectivity::parse(&n.to_string()).map_err(|e| format!("analysis.{}", e))?;
+            }
+            if let Some(p) = string_at(analysis, "analysis", "fragment_report")? {
+                config.fragment_report = Some(base.join(p));
+            }
+            if let Some(b) = bool_at(analysis, "analysis", "strict")? {
+                config.strict = b;
+            }
+        }
+
+        // Discovery replaces the built-in continent directories unless maps are listed
+        if config.adt_root.is_some() || config.mpq_dir.is_some() || config.casc_dir.is_some() {
+            config.maps.clear();
+        }
+
+        if let Some(maps) = table.get("maps") {
+            let maps = maps.as_array().ok_or("'maps' must be an array of tables ([[maps]])")?;
+            config.maps.clear();
+
+            for (i, entry) in maps.iter().enumerate() {
+                let ctx = format!("maps[{}]", i);
+                let entry = entry.as_table().ok_or_else(|| format!("{} must be a table", ctx))?;
+                check_keys(entry, &ctx, &["grid", "map", "adt_dir", "ui_map_id", "map_id", "output"])?;
+
+                let grid = string_at(entry, &ctx, "grid")?
+                    .ok_or_else(|| format!("{} is missing 'grid'", ctx))?;
+
+                let mut map = MapConfig::new(&grid);
+                map.map = string_at(entry, &ctx, "map")?;
+                map.adt_dir = string_at(entry, &ctx, "adt_dir")?.map(|d| base.join(d));
+                map.ui_map_id = u32_at(entry, &ctx, "ui_map_id")?;
+                map.map_id = u32_at(entry, &ctx, "map_id")?;
+                if let Some(output) = string_at(entry, &ctx, "output")? {
+                    map.output = output;
+                }
+                config.maps.push(map);
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// Point the map named `grid` at a different ADT directory, adding it if it is not listed.
+    pub fn set_adt_dir(&mut self, grid: &str, adt_dir: PathBuf) {
+        match self.maps.iter_mut().find(|m| m.grid == grid) {
+            Some(map) => map.adt_dir = Some(adt_dir),
+            None => {
+                let mut map = MapConfig::new(grid);
+                map.adt_dir = Some(adt_dir);
+                self.maps.push(map);
+            }
+        }
+    }
+}
+
+/// Typos would otherwise silently fall back to the default
+fn check_keys(table: &toml::Table, ctx: &str, known: &[&str]) -> Result<(), String> {
+    match table.keys().find(|key| !known.contains(&key.as_str())) {
+        None => Ok(()),
+        Some(key) if ctx.is_empty() => Err(format!("unknown key '{}' (expected one of: {})", key, known.join(", "))),
+        Some(key) => Err(format!("unknown key '{}.{}' (expected one of: {})", ctx, key, known.join(", "))),
+    }
+}
+
+fn table_at<'a>(table: &'a toml::Table, key: &str) -> Result<Option<&'a toml::Table>, String> {
+    match table.get(key) {
+        None => Ok(None),
+        Some(v) => v.as_table().map(Some).ok_or_else(|| format!("'{}' must be a table", key)),
+    }
+}
+
+pub(crate) fn string_at(table: &toml::Table, ctx: &str, key: &str) -> Result<Option<String>, String> {
+    match table.get(key) {
+        None => Ok(None),
+        Some(v) => v
+            .as_str()
+            .map(|s| Some(s.to_string()))
+            .ok_or_else(|| format!("{}.{} must be a string", ctx, key)),
+    }
+}
+
+pub(crate) fn u32_at(table: &toml::Table, ctx: &str, key: &str) -> Result<Option<u32>, String> {
+    match table.get(key) {
+        None => Ok(None),
+        Some(v) => v
+            .as_integer()
+            .and_then(|n| u32::try_from(n).ok())
+            .map(Some)
+            .ok_or_else(|| format!("{}.{} must be a non-negative integer", ctx, key)),
+    }
+}
+
+fn bool_at(table: &toml::Table, ctx: &str, key: &str) -> Result<Option<bool>, String> {
+    match table.get(key) {
+        None => Ok(None),
+        Some(v) => v.as_bool().map(Some).ok_or_else(|| format!("{}.{} must be true or false", ctx, key)),
+    }
+}
+
+/// Accepts integers too, so `simplify = 8` works
+fn f64_at(table: &toml::Table, ctx: &str, key: &str) -> Result<Option<f64>, String> {
+    match table.get(key) {
+        None => Ok(None),
+        Some(v) => v
+            .as_float()
+            .or_else(|| v.as_integer().map(|n| n as f64))
+            .filter(|n| *n >= 0.0)
+            .map(Some)
+            .ok_or_else(|| format!("{}.{} must be a non-negative number", ctx, key)),
+    }
+}
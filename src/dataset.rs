@@ -0,0 +1,266 @@
+// ============================================================================
+// Datasets: areas plus every grid's raw tiles, read back from generated Lua
+// ============================================================================
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+use crate::area::find_root_parent;
+use crate::config::TileEncoding;
+use crate::tiles::{decode_tile_b64, TileGrid};
+use crate::wdt::TileInventory;
+use crate::world::World;
+
+/// Tile key -> 256 AreaIDs, like `TileGrid::tiles_raw`
+pub type GridTiles = HashMap<u32, Vec<u32>>;
+
+#[derive(Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AreaRecord {
+    pub name: String,
+    pub parent_id: u32,
+    pub root_parent_id: u32,
+    pub exploration_level: i32,
+    /// Only known when read from a generated `AreaInfo.lua`
+    pub color: Option<(f32, f32, f32)>,
+    pub label_anchor: Option<(f64, f64)>,
+}
+
+/// Everything `diff` and `render` work from: the areas and every grid's raw tiles
+#[derive(Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Dataset {
+    pub areas: BTreeMap<u32, AreaRecord>,
+    pub grids: BTreeMap<String, GridTiles>,
+}
+
+impl Dataset {
+    /// The AreaTable and tiles read from the inputs; colors and anchors stay unknown
+    pub fn from_world(world: World) -> Self {
+        let areas = world
+            .areas
+            .values()
+            .map(|a| {
+                let record = AreaRecord {
+                    name: a.name.clone(),
+                    parent_id: a.parent_id,
+                    root_parent_id: find_root_parent(a.id, &world.areas),
+                    exploration_level: a.exploration_level,
+                    ..AreaRecord::default()
+                };
+                (a.id, record)
+            })
+            .collect();
+        let grids = world.maps.into_values().map(|grid| (grid.config.grid, grid.tiles.tiles_raw)).collect();
+        Self { areas, grids }
+    }
+}
+
+/// Read a generated `Data/` directory back: `AreaInfo.lua` plus every file that calls
+/// `RegisterTileGrid`.
+pub fn load_data_dir(dir: &Path) -> Result<Dataset, Box<dyn std::error::Error>> {
+    let mut dataset = Dataset::default();
+
+    let area_info = dir.join("AreaInfo.lua");
+    let text = fs::read_to_string(&area_info).map_err(|e| format!("failed to read {}: {}", area_info.display(), e))?;
+    parse_area_info_lua(&text, &mut dataset.areas);
+
+    let mut paths: Vec<_> = fs::read_dir(dir)?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|e| e == "lua"))
+        .collect();
+    paths.sort();
+    for path in paths {
+        let text = fs::read_to_string(&path)?;
+        if !text.contains("addon:RegisterTileGrid(") {
+            continue;
+        }
+        let grid = parse_tiles_lua(&text).map_err(|e| format!("failed to parse {}: {}", path.display(), e))?;
+        dataset.grids.insert(grid.continent_name, grid.tiles_raw);
+    }
+    Ok(dataset)
+}
+
+fn parse_area_info_lua(text: &str, areas: &mut BTreeMap<u32, AreaRecord>) {
+    let mut current: Option<u32> = None;
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if let Some(id) = trimmed.strip_prefix('[').and_then(|r| r.strip_suffix("] = {")) {
+            current = id.parse().ok();
+            if let Some(id) = current {
+                areas.insert(id, AreaRecord { root_parent_id: id, ..AreaRecord::default() });
+            }
+        } else if let (Some(id), Some(name)) = (current, trimmed.strip_prefix("name = \"")) {
+            let name = name.strip_suffix("\",").unwrap_or(name);
+            if let Some(area) = areas.get_mut(&id) {
+                area.name = name.replace("\\\"", "\"");
+            }
+        } else if let (Some(id), Some(parent)) = (current, trimmed.strip_prefix("parentId = ")) {
+            if let Some(area) = areas.get_mut(&id) {
+                area.parent_id = parent.trim_end_matches(',').parse().unwrap_or(0);
+            }
+        } else if let (Some(id), Some(root)) = (current, trimmed.strip_prefix("rootParentId = ")) {
+            if let Some(area) = areas.get_mut(&id) {
+                area.root_parent_id = root.trim_end_matches(',').parse().unwrap_or(id);
+            }
+        } else if let (Some(id), Some(level)) = (current, trimmed.strip_prefix("explorationLevel = ")) {
+            if let Some(area) = areas.get_mut(&id) {
+                area.exploration_level = level.trim_end_matches(',').parse().unwrap_or(0);
+            }
+        } else if let (Some(id), Some(rgb)) = (current, trimmed.strip_prefix("color = ")) {
+            if let (Some(area), [r, g, b]) = (areas.get_mut(&id), lua_numbers(rgb).as_slice()) {
+                area.color = Some((*r as f32, *g as f32, *b as f32));
+            }
+        } else if let (Some(id), Some(xy)) = (current, trimmed.strip_prefix("labelAnchor = ")) {
+            if let (Some(area), [x, y]) = (areas.get_mut(&id), lua_numbers(xy).as_slice()) {
+                area.label_anchor = Some((*x, *y));
+            }
+        }
+    }
+}
+
+/// The numbers in a flat Lua table such as `{0.900, 0.300, 0.300},`
+fn lua_numbers(value: &str) -> Vec<f64> {
+    value
+        .trim_end_matches(',')
+        .trim_start_matches('{')
+        .trim_end_matches('}')
+        .split(',')
+        .filter_map(|n| n.trim().parse().ok())
+        .collect()
+}
+
+/// Parse a `<Grid>_tiles.lua` in any format this tool has written: the name, UiMap ID,
+/// tiles and WDT tile list. Liquid masks are not read back.
+pub fn parse_tiles_lua(text: &str) -> Result<TileGrid, Box<dyn std::error::Error>> {
+    let mut name = None;
+    let mut ui_map_id = None;
+    let mut existing_tiles = None;
+    let mut format = None;
+    let mut shared: Vec<&str> = Vec::new();
+    // Tile key -> raw Lua value, decoded once the format tag has been seen
+    let mut values: Vec<(u32, &str)> = Vec::new();
+    let mut section = "";
+
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if trimmed == "local shared = {" {
+            section = "shared";
+        } else if trimmed == "local tiles = {" {
+            section = "tiles";
+        } else if trimmed == "}" {
+            section = "";
+        } else if let Some(rest) = trimmed.strip_prefix("addon:RegisterTileGrid(\"") {
+            name = rest.split('"').next().map(str::to_string);
+        } else if let Some(rest) = trimmed.strip_prefix("format = \"") {
+            format = rest.split('"').next();
+        } else if let Some(rest) = trimmed.strip_prefix("uiMapId = ") {
+            ui_map_id = Some(rest.trim_end_matches(',').parse()?);
+        } else if let Some(rest) = trimmed.strip_prefix("existingTiles = [[") {
+            existing_tiles = Some(TileInventory::from_bitmap_b64(rest.trim_end_matches(',').trim_end_matches("]]"))?);
+        } else if section == "shared" {
+            shared.push(trimmed.trim_start_matches("[[").trim_end_matches(',').trim_end_matches("]]"));
+        } else if section == "tiles" {
+            let Some((key, value)) = trimmed.strip_prefix('[').and_then(|r| r.split_once("] = ")) else {
+                continue;
+            };
+            values.push((key.parse()?, value.trim_end_matches(',')));
+        }
+    }
+
+    let mut grid = TileGrid::new(&name.ok_or("no RegisterTileGrid call")?);
+    grid.ui_map_id = ui_map_id;
+    grid.existing_tiles = existing_tiles;
+    let encoding = TileEncoding::from_tag(format)?;
+    for (key, value) in values {
+        let area_ids = if let Some(blob) = value.strip_prefix("[[").and_then(|v| v.strip_suffix("]]")) {
+            decode_tile_b64(blob, encoding)?
+        } else if let Some(i) = value.strip_prefix("shared[").and_then(|v| v.strip_suffix(']')) {
+            let blob = shared.get(i.parse::<usize>()?.wrapping_sub(1)).ok_or("shared index out of range")?;
+            decode_tile_b64(blob, encoding)?
+        } else {
+            vec![value.parse::<u32>()?; 256]
+        };
+        grid.found_areas.extend(area_ids.iter().copied().filter(|&id| id != 0));
+        grid.tiles_raw.insert(key, area_ids);
+    }
+    Ok(grid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TempDir;
+    use base64::{engine::general_purpose, Engine as _};
+
+    fn inventory(keys: &[u32]) -> TileInventory {
+        let mut bits = vec![0u8; 64 * 64 / 8];
+        for &key in keys {
+            bits[key as usize / 8] |= 1 << (key % 8);
+        }
+        TileInventory::from_bitmap_b64(&general_purpose::STANDARD.encode(bits)).unwrap()
+    }
+
+    /// One uniform tile, two tiles sharing a blob, one tile of its own, and a WDT list
+    /// that has a tile without an ADT (2049) and lacks one that has one (4095)
+    fn sample_grid() -> TileGrid {
+        let mut grid = TileGrid::new("Kalimdor");
+        grid.ui_map_id = Some(1414);
+        let split: Vec<u32> = (0..256).map(|i| if i < 100 { 141 } else { 148 }).collect();
+        let mixed: Vec<u32> = (0..256).map(|i| [0, 331, 4464, 17][i % 4]).collect();
+        grid.tiles_raw.insert(2000, vec![14; 256]);
+        grid.tiles_raw.insert(2001, split.clone());
+        grid.tiles_raw.insert(2064, split);
+        grid.tiles_raw.insert(2065, mixed);
+        grid.found_areas = grid.tiles_raw.values().flatten().copied().filter(|&id| id != 0).collect();
+        grid.existing_tiles = Some(inventory(&[2000, 2001, 2049, 2064, 2065]));
+        grid
+    }
+
+    #[test]
+    fn exported_tiles_parse_back() {
+        let grid = sample_grid();
+        let dir = TempDir::new("tiles");
+        for encoding in [TileEncoding::PaletteRle, TileEncoding::U32] {
+            let path = dir.0.join(format!("{}.lua", encoding.format_tag()));
+            let stats = grid.export_lua(&path, encoding).unwrap();
+            let text = fs::read_to_string(&path).unwrap();
+
+            assert_eq!((stats.uniform, stats.shared_blobs, stats.shared_tiles), (1, 1, 2));
+            assert!(text.contains("  [2000] = 14,"));
+            assert!(text.contains("  [2001] = shared[1],") && text.contains("  [2064] = shared[1],"));
+
+            let parsed = parse_tiles_lua(&text).unwrap();
+            assert_eq!(parsed.continent_name, "Kalimdor");
+            assert_eq!(parsed.ui_map_id, Some(1414));
+            assert_eq!(parsed.tiles_raw, grid.tiles_raw, "{:?}", encoding);
+            assert_eq!(parsed.found_areas, grid.found_areas);
+            let existing: Vec<u32> = parsed.existing_tiles.as_ref().unwrap().keys().collect();
+            assert_eq!(existing, [2000, 2001, 2049, 2064, 2065]);
+        }
+    }
+
+    #[test]
+    fn tiles_without_wdt_or_ui_map_parse_back() {
+        let mut grid = sample_grid();
+        grid.ui_map_id = None;
+        grid.existing_tiles = None;
+        let dir = TempDir::new("tiles-bare");
+        let path = dir.0.join("Kalimdor_tiles.lua");
+        grid.export_lua(&path, TileEncoding::PaletteRle).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+
+        let parsed = parse_tiles_lua(&text).unwrap();
+        assert_eq!(parsed.ui_map_id, None);
+        assert!(parsed.existing_tiles.is_none());
+        assert_eq!(parsed.tiles_raw, grid.tiles_raw);
+    }
+
+    #[test]
+    fn parse_tiles_lua_reports_bad_references() {
+        let text = "local tiles = {\n  [5] = shared[2],\n}\naddon:RegisterTileGrid(\"X\", {\n})\n";
+        assert!(parse_tiles_lua(text).err().unwrap().to_string().contains("shared index"));
+        assert!(parse_tiles_lua("local tiles = {\n}\n").is_err());
+    }
+}
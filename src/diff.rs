@@ -0,0 +1,289 @@
+// ============================================================================
+// Diff: compare two datasets (input sets or generated Data/ directories)
+// ============================================================================
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use crate::dataset::Dataset;
+use crate::png::Image;
+
+/// Tiles listed per grid before the rest are summarized
+const SHOWN_TILES: usize = 40;
+
+/// Per-area and per-tile change report
+pub fn report(old: &Dataset, new: &Dataset) -> String {
+    let mut out = String::new();
+    // Writing to a String cannot fail
+    let _ = write_report(&mut out, old, new);
+    out
+}
+
+fn write_report(out: &mut String, old: &Dataset, new: &Dataset) -> std::fmt::Result {
+    let name_of = |id: u32| {
+        if id == 0 {
+            return "(no area)".to_string();
+        }
+        new.areas
+            .get(&id)
+            .or_else(|| old.areas.get(&id))
+            .map(|a| a.name.clone())
+            .unwrap_or_else(|| format!("Unknown_{}", id))
+    };
+
+    // Areas
+    let added: Vec<u32> = new.areas.keys().filter(|id| !old.areas.contains_key(id)).copied().collect();
+    let removed: Vec<u32> = old.areas.keys().filter(|id| !new.areas.contains_key(id)).copied().collect();
+    let mut renamed = Vec::new();
+    let mut reparented = Vec::new();
+    for (id, o) in &old.areas {
+        if let Some(n) = new.areas.get(id) {
+            if o.name != n.name {
+                renamed.push(format!("  renamed {}: \"{}\" -> \"{}\"", id, o.name, n.name));
+            }
+            if o.parent_id != n.parent_id {
+                reparented.push(format!("  parent  {} {}: {} -> {}", id, n.name, o.parent_id, n.parent_id));
+            }
+        }
+    }
+    writeln!(
+        out,
+        "Areas: {} added, {} removed, {} renamed, {} with a new parent",
+        added.len(),
+        removed.len(),
+        renamed.len(),
+        reparented.len()
+    )?;
+    for id in &added {
+        writeln!(out, "  added   {} {} (parent {})", id, new.areas[id].name, new.areas[id].parent_id)?;
+    }
+    for id in &removed {
+        writeln!(out, "  removed {} {} (parent {})", id, old.areas[id].name, old.areas[id].parent_id)?;
+    }
+    for line in renamed.iter().chain(&reparented) {
+        writeln!(out, "{}", line)?;
+    }
+
+    // Grids
+    let names: BTreeSet<&String> = old.grids.keys().chain(new.grids.keys()).collect();
+    for name in names {
+        writeln!(out)?;
+        let (Some(o), Some(n)) = (old.grids.get(name), new.grids.get(name)) else {
+            let side = if old.grids.contains_key(name) { "old" } else { "new" };
+            writeln!(out, "Grid {}: only in the {} dataset", name, side)?;
+            continue;
+        };
+
+        let tiles_added: BTreeSet<u32> = n.keys().filter(|k| !o.contains_key(k)).copied().collect();
+        let tiles_removed: BTreeSet<u32> = o.keys().filter(|k| !n.contains_key(k)).copied().collect();
+        let mut lost: BTreeMap<u32, usize> = BTreeMap::new();
+        let mut gained: BTreeMap<u32, usize> = BTreeMap::new();
+        let mut per_tile: BTreeMap<u32, BTreeMap<(u32, u32), usize>> = BTreeMap::new();
+        for (key, old_ids) in o {
+            let Some(new_ids) = n.get(key) else { continue };
+            for (&a, &b) in old_ids.iter().zip(new_ids) {
+                if a != b {
+                    *lost.entry(a).or_default() += 1;
+                    *gained.entry(b).or_default() += 1;
+                    *per_tile.entry(*key).or_default().entry((a, b)).or_default() += 1;
+                }
+            }
+        }
+        let changed: usize = lost.values().sum();
+
+        writeln!(
+            out,
+            "Grid {}: {} chunks changed in {} tiles, {} tiles added, {} tiles removed",
+            name,
+            changed,
+            per_tile.len(),
+            tiles_added.len(),
+            tiles_removed.len()
+        )?;
+
+        let areas: BTreeSet<u32> = lost.keys().chain(gained.keys()).copied().collect();
+        if !areas.is_empty() {
+            writeln!(out, "  Per area (chunks lost / gained):")?;
+            for id in areas {
+                writeln!(
+                    out,
+                    "    {} {}: -{} +{}",
+                    id,
+                    name_of(id),
+                    lost.get(&id).unwrap_or(&0),
+                    gained.get(&id).unwrap_or(&0)
+                )?;
+            }
+        }
+
+        if !per_tile.is_empty() {
+            writeln!(out, "  Per tile (old area -> new area x chunks):")?;
+            for (key, transitions) in per_tile.iter().take(SHOWN_TILES) {
+                let mut transitions: Vec<_> = transitions.iter().collect();
+                transitions.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+                let parts: Vec<String> = transitions.iter().map(|((a, b), c)| format!("{} -> {} x{}", a, b, c)).collect();
+                writeln!(out, "    {}_{}: {}", key % 64, key / 64, parts.join(", "))?;
+            }
+            if per_tile.len() > SHOWN_TILES {
+                writeln!(out, "    ... and {} more tiles", per_tile.len() - SHOWN_TILES)?;
+            }
+        }
+        for (label, keys) in [("Tiles added", &tiles_added), ("Tiles removed", &tiles_removed)] {
+            if !keys.is_empty() {
+                let coords: Vec<String> = keys.iter().map(|k| format!("{}_{}", k % 64, k / 64)).collect();
+                writeln!(out, "  {}: {}", label, coords.join(", "))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// One image per grid in either dataset, one pixel per chunk over the tiles either has:
+/// red where the area changed, gray where it did not, green/magenta for tiles only in the
+/// new/old dataset.
+pub fn write_masks(old: &Dataset, new: &Dataset, dir: &Path) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    const CHANGED: [u8; 3] = [230, 40, 40];
+    const SAME: [u8; 3] = [70, 70, 70];
+    const ONLY_NEW: [u8; 3] = [60, 170, 60];
+    const ONLY_OLD: [u8; 3] = [170, 60, 170];
+
+    fs::create_dir_all(dir)?;
+    let mut written = Vec::new();
+    let empty = HashMap::new();
+    // Grids dropped from the new dataset still get a mask, all in the removed color
+    let names: BTreeSet<&String> = old.grids.keys().chain(new.grids.keys()).collect();
+    for name in names {
+        let o = old.grids.get(name).unwrap_or(&empty);
+        let n = new.grids.get(name).unwrap_or(&empty);
+        let keys: BTreeSet<u32> = o.keys().chain(n.keys()).copied().collect();
+        let (Some(min_x), Some(max_x)) = (keys.iter().map(|k| k % 64).min(), keys.iter().map(|k| k % 64).max()) else {
+            continue;
+        };
+        let min_y = keys.iter().map(|k| k / 64).min().unwrap_or(0);
+        let max_y = keys.iter().map(|k| k / 64).max().unwrap_or(0);
+
+        let mut image = Image::new((max_x - min_x + 1) * 16, (max_y - min_y + 1) * 16, [0, 0, 0]);
+        for key in keys {
+            let (ox, oy) = ((key % 64 - min_x) as i64 * 16, (key / 64 - min_y) as i64 * 16);
+            for i in 0..256 {
+                let color = match (o.get(&key), n.get(&key)) {
+                    (Some(a), Some(b)) if a[i] != b[i] => CHANGED,
+                    (Some(_), Some(_)) => SAME,
+                    (None, Some(_)) => ONLY_NEW,
+                    _ => ONLY_OLD,
+                };
+                image.set(ox + (i % 16) as i64, oy + (i / 16) as i64, color);
+            }
+        }
+
+        let path = dir.join(format!("{}_changes.png", name));
+        image.write(&path).map_err(|e| format!("failed to write {}: {}", path.display(), e))?;
+        written.push(path.display().to_string());
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dataset::AreaRecord;
+    use crate::tiles::tile_key;
+
+    /// Tile key and 256 AreaIDs
+    type TileList = [(u32, Vec<u32>)];
+
+    fn dataset(areas: &[(u32, &str, u32)], grids: &[(&str, &TileList)]) -> Dataset {
+        Dataset {
+            areas: areas
+                .iter()
+                .map(|&(id, name, parent_id)| {
+                    (id, AreaRecord { name: name.to_string(), parent_id, ..AreaRecord::default() })
+                })
+                .collect(),
+            grids: grids
+                .iter()
+                .map(|(name, tiles)| (name.to_string(), tiles.iter().cloned().collect()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn report_lists_area_and_chunk_changes() {
+        let mut durotar = vec![1; 256];
+        let mut razor_hill = vec![3; 256];
+        let old = dataset(
+            &[(1, "Durotar", 0), (2, "Orgrimmar", 1), (3, "Razor Hill", 1), (4, "Sen'jin Village", 1)],
+            &[
+                (
+                    "Kalimdor",
+                    &[
+                        (tile_key(29, 20), vec![1; 256]),
+                        (tile_key(30, 20), durotar.clone()),
+                        (tile_key(31, 20), razor_hill.clone()),
+                    ],
+                ),
+                ("Azeroth", &[(tile_key(32, 48), vec![12; 256])]),
+            ],
+        );
+
+        durotar[..3].fill(2);
+        durotar[100..102].fill(5);
+        razor_hill[255] = 0;
+        let new = dataset(
+            &[(1, "Durotar", 0), (2, "Orgrimmar City", 1), (3, "Razor Hill", 2), (5, "Echo Isles", 1)],
+            &[(
+                "Kalimdor",
+                &[(tile_key(30, 20), durotar), (tile_key(31, 20), razor_hill), (tile_key(32, 20), vec![1; 256])],
+            )],
+        );
+
+        assert_eq!(
+            report(&old, &new),
+            "Areas: 1 added, 1 removed, 1 renamed, 1 with a new parent\n\
+             \x20 added   5 Echo Isles (parent 1)\n\
+             \x20 removed 4 Sen'jin Village (parent 1)\n\
+             \x20 renamed 2: \"Orgrimmar\" -> \"Orgrimmar City\"\n\
+             \x20 parent  3 Razor Hill: 1 -> 2\n\
+             \n\
+             Grid Azeroth: only in the old dataset\n\
+             \n\
+             Grid Kalimdor: 6 chunks changed in 2 tiles, 1 tiles added, 1 tiles removed\n\
+             \x20 Per area (chunks lost / gained):\n\
+             \x20   0 (no area): -0 +1\n\
+             \x20   1 Durotar: -5 +0\n\
+             \x20   2 Orgrimmar City: -0 +3\n\
+             \x20   3 Razor Hill: -1 +0\n\
+             \x20   5 Echo Isles: -0 +2\n\
+             \x20 Per tile (old area -> new area x chunks):\n\
+             \x20   30_20: 1 -> 2 x3, 1 -> 5 x2\n\
+             \x20   31_20: 3 -> 0 x1\n\
+             \x20 Tiles added: 32_20\n\
+             \x20 Tiles removed: 29_20\n"
+        );
+    }
+
+    #[test]
+    fn identical_datasets_report_no_changes() {
+        let tiles = [(tile_key(30, 20), vec![1; 256])];
+        let a = dataset(&[(1, "Durotar", 0)], &[("Kalimdor", &tiles)]);
+        let b = dataset(&[(1, "Durotar", 0)], &[("Kalimdor", &tiles)]);
+        assert_eq!(
+            report(&a, &b),
+            "Areas: 0 added, 0 removed, 0 renamed, 0 with a new parent\n\
+             \n\
+             Grid Kalimdor: 0 chunks changed in 0 tiles, 0 tiles added, 0 tiles removed\n"
+        );
+    }
+
+    #[test]
+    fn long_tile_lists_are_cut_short() {
+        let old_tiles: Vec<(u32, Vec<u32>)> = (0..45).map(|x| (tile_key(x, 0), vec![1; 256])).collect();
+        let new_tiles: Vec<(u32, Vec<u32>)> = (0..45).map(|x| (tile_key(x, 0), vec![2; 256])).collect();
+        let areas = [(1, "Old", 0), (2, "New", 0)];
+        let text = report(&dataset(&areas, &[("Map", &old_tiles)]), &dataset(&areas, &[("Map", &new_tiles)]));
+        assert!(text.contains("    39_0: 1 -> 2 x256\n    ... and 5 more tiles\n"), "{}", text);
+        assert!(!text.contains("40_0"));
+    }
+}
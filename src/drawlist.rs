@@ -0,0 +1,216 @@
+// ============================================================================
+// Draw lists: for every UiMap, its root zone's chunks merged into rectangles
+// ============================================================================
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::area::{find_root_parent, AreaHierarchy, MapToAreaEntry};
+use crate::rules::AreaMarks;
+use crate::world::World;
+
+/// `(x, y, area)` of every chunk on one map, by root zone
+type ChunksByRoot = HashMap<u32, Vec<(i32, i32, u32)>>;
+
+/// `w` x `h` chunks of one area; `(x, y)` is the top-left chunk in global chunk
+/// coordinates (`tileX * 16 + chunkX`, `tileY * 16 + chunkY`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub w: i32,
+    pub h: i32,
+    pub area: u32,
+}
+
+/// What the addon fills when a UiMap is open
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DrawList {
+    pub ui_map_id: u32,
+    pub area_id: u32,
+    pub root_area_id: u32,
+    /// Grid name and its rectangles, for every grid with chunks of the zone
+    pub grids: Vec<(String, Vec<Rect>)>,
+}
+
+impl DrawList {
+    pub fn rect_count(&self) -> usize {
+        self.grids.iter().map(|(_, rects)| rects.len()).sum()
+    }
+
+    pub fn chunk_count(&self) -> usize {
+        self.grids.iter().flat_map(|(_, rects)| rects).map(|r| (r.w * r.h) as usize).sum()
+    }
+}
+
+/// Runs of same-area chunks along each row, stacked into taller rectangles wherever
+/// the row below has a run with the same columns and area
+pub fn merge_rects(mut chunks: Vec<(i32, i32, u32)>) -> Vec<Rect> {
+    chunks.sort_unstable_by_key(|&(x, y, _)| (y, x));
+
+    let mut rects: Vec<Rect> = Vec::new();
+    // (x, w, area) of the runs in the previous row -> their rectangle
+    let mut open: HashMap<(i32, i32, u32), usize> = HashMap::new();
+    let mut row_y = None;
+    let mut row_runs: Vec<(i32, i32, u32)> = Vec::new();
+
+    let mut flush = |y: i32, runs: &mut Vec<(i32, i32, u32)>, open: &mut HashMap<(i32, i32, u32), usize>| {
+        let mut next_open = HashMap::new();
+        for &(x, w, area) in runs.iter() {
+            let index = match open.get(&(x, w, area)) {
+                Some(&i) if rects[i].y + rects[i].h == y => {
+                    rects[i].h += 1;
+                    i
+                }
+                _ => {
+                    rects.push(Rect { x, y, w, h: 1, area });
+                    rects.len() - 1
+                }
+            };
+            next_open.insert((x, w, area), index);
+        }
+        *open = next_open;
+        runs.clear();
+    };
+
+    for (x, y, area) in chunks {
+        if row_y != Some(y) {
+            if let Some(prev) = row_y {
+                flush(prev, &mut row_runs, &mut open);
+            }
+            row_y = Some(y);
+        }
+        match row_runs.last_mut() {
+            Some((run_x, w, run_area)) if *run_area == area && *run_x + *w == x => *w += 1,
+            _ => row_runs.push((x, 1, area)),
+        }
+    }
+    if let Some(y) = row_y {
+        flush(y, &mut row_runs, &mut open);
+    }
+    rects
+}
+
+/// One draw list per UiMap in `mapIdToArea.csv` (later rows win, as in MapToArea.lua):
+/// every found area under the UiMap's root zone except seas and hidden areas
+pub fn build_draw_lists(
+    entries: &[MapToAreaEntry],
+    world: &World,
+    hierarchy: &AreaHierarchy,
+    marks: &HashMap<u32, AreaMarks>,
+) -> Vec<DrawList> {
+    let mut by_ui_map: BTreeMap<u32, &MapToAreaEntry> = BTreeMap::new();
+    for entry in entries {
+        by_ui_map.insert(entry.map_id, entry);
+    }
+
+    // Every map's chunks grouped by root zone, so each UiMap only filters its own zone
+    let mut roots: HashMap<u32, u32> = HashMap::new();
+    let mut chunks_by_root: Vec<(String, ChunksByRoot)> = Vec::new();
+    for grid in world.maps.values() {
+        let mut by_root = ChunksByRoot::new();
+        for (x, y, area) in grid.chunk_grid().chunks() {
+            let root = *roots.entry(area).or_insert_with(|| find_root_parent(area, &world.areas));
+            by_root.entry(root).or_default().push((x, y, area));
+        }
+        chunks_by_root.push((grid.name().to_string(), by_root));
+    }
+
+    let mut lists = Vec::new();
+    for (&ui_map_id, entry) in &by_ui_map {
+        let root = find_root_parent(entry.area_id, &world.areas);
+        let Some(children) = hierarchy.get(&root) else {
+            continue;
+        };
+        let drawn: HashSet<u32> = children
+            .keys()
+            .copied()
+            .filter(|id| marks.get(id).is_none_or(|m| !m.sea && !m.hidden))
+            .collect();
+
+        let mut grids = Vec::new();
+        for (name, by_root) in &chunks_by_root {
+            let Some(chunks) = by_root.get(&root) else {
+                continue;
+            };
+            let chunks: Vec<(i32, i32, u32)> = chunks.iter().copied().filter(|(_, _, a)| drawn.contains(a)).collect();
+            if !chunks.is_empty() {
+                grids.push((name.clone(), merge_rects(chunks)));
+            }
+        }
+        if !grids.is_empty() {
+            lists.push(DrawList { ui_map_id, area_id: entry.area_id, root_area_id: root, grids });
+        }
+    }
+    lists
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::ChunkGrid;
+    use crate::testutil::tiles_from_rows;
+
+    fn chunks(rows: &[&str]) -> Vec<(i32, i32, u32)> {
+        ChunkGrid::new(&tiles_from_rows(rows)).chunks()
+    }
+
+    /// Merge, then check that the rectangles cover exactly the input chunks, once each
+    fn merge_exact(rows: &[&str]) -> Vec<Rect> {
+        let input = chunks(rows);
+        let rects = merge_rects(input.clone());
+        let mut covered: HashMap<(i32, i32), u32> = HashMap::new();
+        for r in &rects {
+            assert!(r.w > 0 && r.h > 0, "{:?}", r);
+            for y in r.y..r.y + r.h {
+                for x in r.x..r.x + r.w {
+                    assert_eq!(covered.insert((x, y), r.area), None, "({}, {}) covered twice", x, y);
+                }
+            }
+        }
+        let expected: HashMap<(i32, i32), u32> = input.into_iter().map(|(x, y, a)| ((x, y), a)).collect();
+        assert_eq!(covered, expected);
+        rects
+    }
+
+    #[test]
+    fn l_shape() {
+        let rects = merge_exact(&["11..", "11..", "1111", "1111"]);
+        assert_eq!(
+            rects,
+            [Rect { x: 0, y: 0, w: 2, h: 2, area: 1 }, Rect { x: 0, y: 2, w: 4, h: 2, area: 1 }]
+        );
+    }
+
+    #[test]
+    fn disjoint_runs_stack_separately() {
+        let rects = merge_exact(&["11.22.1", "11.22.1", ".......", "11.22.1"]);
+        assert_eq!(rects.len(), 6);
+        // The empty row breaks the stacks even though the runs below line up again
+        assert!(rects.iter().all(|r| r.h == 2 || r.y == 3));
+    }
+
+    #[test]
+    fn mixed_areas_never_share_a_rectangle() {
+        let rects = merge_exact(&["1122", "1212", "2211", "2211"]);
+        assert_eq!(rects.iter().filter(|r| r.area == 1).map(|r| r.w * r.h).sum::<i32>(), 8);
+        assert!(rects.contains(&Rect { x: 0, y: 2, w: 2, h: 2, area: 2 }));
+        assert!(rects.contains(&Rect { x: 2, y: 2, w: 2, h: 2, area: 1 }));
+
+        // Same columns but a different area below: a new rectangle
+        let rects = merge_exact(&["111", "222", "222"]);
+        assert_eq!(
+            rects,
+            [Rect { x: 0, y: 0, w: 3, h: 1, area: 1 }, Rect { x: 0, y: 1, w: 3, h: 2, area: 2 }]
+        );
+    }
+
+    #[test]
+    fn input_order_does_not_matter() {
+        let rows = ["1.11", "1.11", "3333"];
+        let mut shuffled = chunks(&rows);
+        shuffled.reverse();
+        assert_eq!(merge_rects(shuffled), merge_exact(&rows));
+        assert!(merge_rects(Vec::new()).is_empty());
+    }
+}
@@ -0,0 +1,277 @@
+// ============================================================================
+// Lua exporters: the files the addon loads
+// ============================================================================
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+use crate::area::{find_root_parent, AreaHierarchy, AreaTable, MapToAreaEntry};
+use crate::border::BorderSegment;
+use crate::drawlist::DrawList;
+use crate::geometry::AreaGeometry;
+use crate::neighbors::NeighborGraph;
+use crate::outline::Polygon;
+use crate::rules::AreaMarks;
+
+pub fn export_map_to_area(entries: &[MapToAreaEntry], out_path: &Path) -> std::io::Result<()> {
+    let mut f = File::create(out_path)?;
+    
+    writeln!(f, "-- Auto-generated Map ID to Area ID mapping")?;
+    writeln!(f, "-- Maps WoW UI map IDs to parent area IDs")?;
+    writeln!(f)?;
+    writeln!(f, "local _, addon = ...")?;
+    writeln!(f)?;
+    writeln!(f, "addon.MapToArea = {{")?;
+    
+    for entry in entries {
+        let escaped_name = entry.zone_name.replace("\"", "\\\"");
+        writeln!(f, "  [{}] = {{ areaId = {}, name = \"{}\" }},", 
+            entry.map_id, entry.area_id, escaped_name)?;
+    }
+    
+    writeln!(f, "}}")?;
+    
+    // Also create reverse lookup (areaId -> mapId)
+    writeln!(f)?;
+    writeln!(f, "addon.AreaToMap = {{")?;
+    
+    for entry in entries {
+        writeln!(f, "  [{}] = {},", entry.area_id, entry.map_id)?;
+    }
+    
+    writeln!(f, "}}")?;
+    
+    Ok(())
+}
+
+/// Export the per-UiMap draw lists to Lua
+pub fn export_draw_lists(lists: &[DrawList], out_path: &Path) -> std::io::Result<()> {
+    let mut f = File::create(out_path)?;
+
+    writeln!(f, "-- Auto-generated draw lists")?;
+    writeln!(f, "-- For every UiMap in MapToArea: the chunks of its root zone, seas and hidden areas left out,")?;
+    writeln!(f, "-- merged into rectangles. Each grid's list is flat, x, y, w, h, areaId per rectangle, where")?;
+    writeln!(f, "-- x = tileX * 16 + chunkX and y = tileY * 16 + chunkY of the rectangle's top-left chunk")?;
+    writeln!(f)?;
+    writeln!(f, "local _, addon = ...")?;
+    writeln!(f)?;
+    writeln!(f, "addon.DrawLists = {{")?;
+
+    for list in lists {
+        writeln!(f, "  [{}] = {{ areaId = {}, rootAreaId = {}, grids = {{", list.ui_map_id, list.area_id, list.root_area_id)?;
+        for (grid, rects) in &list.grids {
+            let values: Vec<String> = rects
+                .iter()
+                .map(|r| format!("{}, {}, {}, {}, {}", r.x, r.y, r.w, r.h, r.area))
+                .collect();
+            writeln!(f, "    [\"{}\"] = {{{}}},", grid, values.join(", "))?;
+        }
+        writeln!(f, "  }} }},")?;
+    }
+
+    writeln!(f, "}}")?;
+    Ok(())
+}
+
+/// Export area info to Lua
+pub fn export_area_info(
+    found_areas: &BTreeSet<u32>,
+    areas: &AreaTable,
+    colors: &HashMap<u32, (f32, f32, f32)>,
+    neighbors: &NeighborGraph,
+    geometry: &AreaGeometry,
+    marks: &HashMap<u32, AreaMarks>,
+    out_path: &Path,
+) -> std::io::Result<()> {
+    let mut f = File::create(out_path)?;
+    
+    writeln!(f, "-- Auto-generated Area Info")?;
+    writeln!(f, "-- Contains name, parent, level, color, and neighbors for each area")?;
+    writeln!(f, "-- labelAnchor: world {{x, y}} in the largest fragment, as far from its border as possible")?;
+    writeln!(f, "-- fragmentAnchors: that point for every fragment in fragmentSizes, in the same order")?;
+    writeln!(f, "-- bounds = {{minX, minY, maxX, maxY}} in world coordinates and tileKeyRange = {{min, max}}")?;
+    writeln!(f, "-- on the continent holding most of the area's chunks; chunkCount and areaYards cover all maps")?;
+    writeln!(f, "-- maps: every grid the area has chunks on")?;
+    writeln!(f, "-- sea, hidden, mergeIntoParent: set by the area rules file, left out when false")?;
+    writeln!(f, "-- waterFraction: share of the area's chunks (on all maps) mostly covered by water")?;
+    writeln!(f, "-- fragmentSizes: chunk count of every connected fragment on the continent ({}-connectivity), largest first", geometry.connectivity_label())?;
+    writeln!(f)?;
+    writeln!(f, "local _, addon = ...")?;
+    writeln!(f)?;
+    writeln!(f, "-- The sea and hidden flags below are authoritative (older files had none)")?;
+    writeln!(f, "addon.AreaRulesApplied = true")?;
+    writeln!(f)?;
+    writeln!(f, "addon.AreaInfo = {{")?;
+    
+    for &area_id in found_areas {
+        if area_id == 0 {
+            continue;
+        }
+        
+        let (name, parent_id, root_parent, level) = if let Some(area) = areas.get(&area_id) {
+            let root = find_root_parent(area_id, areas);
+            (area.name.clone(), area.parent_id, root, area.exploration_level)
+        } else {
+            (format!("Unknown_{}", area_id), 0, area_id, 0)
+        };
+        
+        let (r, g, b) = colors.get(&area_id).copied().unwrap_or((0.5, 0.5, 0.5));
+        let escaped_name = name.replace("\"", "\\\"");
+        
+        // Get neighbor count for info
+        let neighbor_count = neighbors.get(&area_id).map(|n| n.len()).unwrap_or(0);
+        
+        writeln!(f, "  [{}] = {{", area_id)?;
+        writeln!(f, "    name = \"{}\",", escaped_name)?;
+        writeln!(f, "    parentId = {},", parent_id)?;
+        writeln!(f, "    rootParentId = {},", root_parent)?;
+        writeln!(f, "    explorationLevel = {},", level)?;
+        writeln!(f, "    color = {{{:.3}, {:.3}, {:.3}}},", r, g, b)?;
+        writeln!(f, "    neighborCount = {},", neighbor_count)?;
+        let area_marks = marks.get(&area_id).copied().unwrap_or_default();
+        if area_marks.sea {
+            writeln!(f, "    sea = true,")?;
+        }
+        if area_marks.hidden {
+            writeln!(f, "    hidden = true,")?;
+        }
+        if area_marks.merge_into_parent {
+            writeln!(f, "    mergeIntoParent = true,")?;
+        }
+        if let Some((continent, extent)) = geometry.extents.get(&area_id) {
+            let (min_x, min_y, max_x, max_y) = extent.world_bounds();
+            writeln!(f, "    continent = \"{}\",", continent)?;
+            writeln!(f, "    bounds = {{{:.1}, {:.1}, {:.1}, {:.1}}},", min_x, min_y, max_x, max_y)?;
+            writeln!(f, "    tileKeyRange = {{{}, {}}},", extent.min_tile_key, extent.max_tile_key)?;
+            writeln!(f, "    chunkCount = {},", geometry.chunk_count(area_id))?;
+            writeln!(f, "    areaYards = {:.0},", geometry.area_yards(area_id))?;
+        }
+        if let Some(maps) = geometry.maps.get(&area_id) {
+            let names: Vec<String> = maps.iter().map(|m| format!("\"{}\"", m)).collect();
+            writeln!(f, "    maps = {{{}}},", names.join(", "))?;
+        }
+        if let Some(fraction) = geometry.water_fraction(area_id) {
+            writeln!(f, "    waterFraction = {:.3},", fraction)?;
+        }
+        let fragments = geometry.continent_fragments(area_id);
+        if let Some((_, ((x, y), _))) = fragments.first() {
+            let sizes: Vec<String> = fragments.iter().map(|(chunks, _)| chunks.to_string()).collect();
+            writeln!(f, "    fragmentCount = {},", sizes.len())?;
+            writeln!(f, "    fragmentSizes = {{{}}},", sizes.join(", "))?;
+            writeln!(f, "    labelAnchor = {{{:.1}, {:.1}}},", x, y)?;
+            let points: Vec<String> = fragments.iter().map(|(_, ((x, y), _))| format!("{{{:.1}, {:.1}}}", x, y)).collect();
+            writeln!(f, "    fragmentAnchors = {{{}}},", points.join(", "))?;
+        }
+        writeln!(f, "  }},")?;
+    }
+    
+    writeln!(f, "}}")?;
+    Ok(())
+}
+
+pub fn export_outlines(
+    grid_name: &str,
+    polygons: &BTreeMap<u32, Vec<Polygon>>,
+    out_path: &Path,
+) -> std::io::Result<()> {
+    let mut f = File::create(out_path)?;
+
+    writeln!(f, "-- Auto-generated area outlines for {}", grid_name)?;
+    writeln!(f, "-- Each area is a list of polygons; a polygon is its outer ring followed by its holes.")?;
+    writeln!(f, "-- Rings are flat {{x1, y1, x2, y2, ...}} lists of world coordinates.")?;
+    writeln!(f)?;
+    writeln!(f, "local _, addon = ...")?;
+    writeln!(f)?;
+    writeln!(f, "addon:RegisterOutlines(\"{}\", {{", grid_name)?;
+
+    for (area_id, area_polygons) in polygons {
+        writeln!(f, "  [{}] = {{", area_id)?;
+        for polygon in area_polygons {
+            writeln!(f, "    {{")?;
+            for ring in std::iter::once(&polygon.outer).chain(&polygon.holes) {
+                let coords: Vec<String> = ring.iter().map(|(x, y)| format!("{:.1}, {:.1}", x, y)).collect();
+                writeln!(f, "      {{{}}},", coords.join(", "))?;
+            }
+            writeln!(f, "    }},")?;
+        }
+        writeln!(f, "  }},")?;
+    }
+
+    writeln!(f, "}})")?;
+    Ok(())
+}
+
+/// Borders go in `zone` when the two areas have different root zones, otherwise in `subzone`
+pub fn export_borders(
+    grid_name: &str,
+    segments: &[BorderSegment],
+    areas: &AreaTable,
+    out_path: &Path,
+) -> std::io::Result<()> {
+    let (zone, subzone): (Vec<&BorderSegment>, Vec<&BorderSegment>) = segments
+        .iter()
+        .partition(|s| find_root_parent(s.areas.0, areas) != find_root_parent(s.areas.1, areas));
+
+    let mut f = File::create(out_path)?;
+
+    writeln!(f, "-- Auto-generated area borders for {}", grid_name)?;
+    writeln!(f, "-- Each segment is {{areaA, areaB, x1, y1, x2, y2}} in world coordinates.")?;
+    writeln!(f, "-- zone: the two areas belong to different root zones; subzone: same root zone.")?;
+    writeln!(f)?;
+    writeln!(f, "local _, addon = ...")?;
+    writeln!(f)?;
+    writeln!(f, "addon:RegisterBorders(\"{}\", {{", grid_name)?;
+    for (name, list) in [("zone", &zone), ("subzone", &subzone)] {
+        writeln!(f, "  {} = {{", name)?;
+        for s in list {
+            writeln!(
+                f,
+                "    {{{}, {}, {:.1}, {:.1}, {:.1}, {:.1}}},",
+                s.areas.0, s.areas.1, s.from.0, s.from.1, s.to.0, s.to.1
+            )?;
+        }
+        writeln!(f, "  }},")?;
+    }
+    writeln!(f, "}})")?;
+    Ok(())
+}
+
+pub fn export_area_hierarchy(
+    hierarchy: &AreaHierarchy,
+    areas: &AreaTable,
+    out_path: &Path,
+) -> std::io::Result<()> {
+    let mut f = File::create(out_path)?;
+    
+    writeln!(f, "-- Auto-generated Area Hierarchy")?;
+    writeln!(f, "-- Groups areas by their root parent zone")?;
+    writeln!(f)?;
+    writeln!(f, "local _, addon = ...")?;
+    writeln!(f)?;
+    writeln!(f, "addon.AreaHierarchy = {{")?;
+    
+    for (root_id, children) in hierarchy {
+        let root_name = if let Some(area) = areas.get(root_id) {
+            area.name.replace("\"", "\\\"")
+        } else {
+            format!("Unknown_{}", root_id)
+        };
+        
+        writeln!(f, "  [{}] = {{  -- {}", root_id, root_name)?;
+        writeln!(f, "    name = \"{}\",", root_name)?;
+        writeln!(f, "    children = {{")?;
+        
+        for (child_id, child_name) in children {
+            let escaped = child_name.replace("\"", "\\\"");
+            writeln!(f, "      [{}] = \"{}\",", child_id, escaped)?;
+        }
+        
+        writeln!(f, "    }},")?;
+        writeln!(f, "  }},")?;
+    }
+    
+    writeln!(f, "}}")?;
+    Ok(())
+}
@@ -0,0 +1,261 @@
+// ============================================================================
+// Generate: the whole `zone-map generate` pipeline, from a config to written files
+// ============================================================================
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::area::{group_by_root, parse_map_to_area_csv};
+use crate::border;
+use crate::component::{self, report_fragments, FragmentCounts};
+use crate::config::{Config, TileEncoding};
+use crate::drawlist::{build_draw_lists, DrawList};
+use crate::export::{
+    export_area_hierarchy, export_area_info, export_borders, export_draw_lists, export_map_to_area, export_outlines,
+};
+use crate::geojson;
+use crate::geometry::AreaGeometry;
+use crate::json;
+use crate::neighbors::generate_colors_with_graph;
+use crate::outline::{self, Polygon};
+use crate::rules::{fold_merged, merge_target, AreaMarks, AreaRules};
+use crate::sources::plan_maps;
+use crate::tiles::TileDedupStats;
+use crate::validate::Validator;
+use crate::world::{MapKey, World};
+
+/// What one map's export produced
+pub struct MapOutput {
+    pub name: String,
+    pub key: MapKey,
+    /// The configured encoding, or u32 when area IDs above 65535 forced it
+    pub encoding: TileEncoding,
+    /// Set when the Lua tile file was written
+    pub dedup: Option<TileDedupStats>,
+    /// `(encoded, u32)` tile payload bytes, when the Lua tiles are not already u32
+    pub payload_len: Option<(usize, usize)>,
+    pub fragments: FragmentCounts,
+    /// Polygons in `<Grid>_outlines.lua`, when outlines were written
+    pub outline_polygons: Option<usize>,
+    /// Segments in `<Grid>_borders.lua`, when borders were written
+    pub border_segments: Option<usize>,
+}
+
+/// Everything `generate` read, computed and wrote, for the caller to report
+pub struct Outputs {
+    /// `plan_maps`'s log of what was scanned
+    pub plan_log: Vec<String>,
+    pub world: World,
+    pub found_areas: BTreeSet<u32>,
+    /// Rules in the config's area rules file (0 without one)
+    pub rule_count: usize,
+    pub created_out_dir: bool,
+    pub maps: Vec<MapOutput>,
+    pub validator: Validator,
+    /// Areas with at least one neighbor, across all maps
+    pub neighbor_areas: usize,
+    /// Areas with chunks on more than one map
+    pub shared_areas: usize,
+    pub marks: HashMap<u32, AreaMarks>,
+    /// Rules that matched no area
+    pub unused_rules: Vec<String>,
+    pub root_zones: usize,
+    pub map_to_area_entries: usize,
+    pub draw_lists: Vec<DrawList>,
+    /// Every file written, in the order written
+    pub written: Vec<PathBuf>,
+}
+
+/// Read the config's inputs and write every addon (and, as configured, JSON, outline,
+/// border, GeoJSON and report) file to its `out_dir`. With `strict` set, validation
+/// errors fail the run after the tile files are written, before anything else is.
+pub fn generate(config: &Config) -> Result<Outputs, Box<dyn std::error::Error>> {
+    let out_dir = config.out_dir.as_path();
+    let created_out_dir = !out_dir.exists();
+    if created_out_dir {
+        fs::create_dir_all(out_dir).map_err(|e| format!("failed to create {}: {}", out_dir.display(), e))?;
+    }
+
+    let rules = match &config.rules {
+        Some(path) => AreaRules::load(path).map_err(|e| format!("failed to load rules {}: {}", path.display(), e))?,
+        None => AreaRules::default(),
+    };
+
+    // Every map keeps its own tiles and neighbor graph
+    let plan = plan_maps(config)?;
+    let world = World::load(config, plan.maps)?;
+    let areas = &world.areas;
+    let found_areas = world.found_areas();
+    let mut written = Vec::new();
+
+    let mut geometry = AreaGeometry::new(config.connectivity);
+    let mut fragment_report = String::new();
+    let mut validator = Validator::default();
+    validator.check_area_table(areas);
+    // GeoJSON needs the colors, which are only known once every map is in the graph
+    let mut geojson_polygons: Vec<(String, BTreeMap<u32, Vec<Polygon>>)> = Vec::new();
+    let mut maps = Vec::new();
+
+    for grid in world.maps.values() {
+        let name = grid.name();
+        let export = &grid.tiles;
+        validator.check_map(name, grid.config.map_id, &export.tiles_raw, areas);
+
+        let out_path = out_dir.join(&grid.config.output);
+        let encoding = export.effective_encoding(config.tile_encoding);
+        let mut dedup = None;
+        if config.format.lua() {
+            let result = export.export_lua(&out_path, encoding).map(|stats| dedup = Some(stats));
+            record(&mut written, out_path.clone(), result)?;
+        }
+        if config.format.json() {
+            let json_path = out_path.with_extension("json");
+            record(&mut written, json_path.clone(), json::export_tiles(export, &json_path))?;
+        }
+
+        let chunk_grid = grid.chunk_grid();
+        geometry.add_extents(name, &chunk_grid);
+        geometry.add_liquids(export);
+        let fragments = component::find_fragments(&chunk_grid, config.connectivity);
+        geometry.add_fragments(name, &fragments);
+        let fragment_counts = report_fragments(&mut fragment_report, name, &fragments, areas)?;
+
+        let polygons = if config.outlines || config.geojson {
+            outline::trace_areas(&chunk_grid, config.simplify)
+        } else {
+            BTreeMap::new()
+        };
+        let mut outline_polygons = None;
+        if config.outlines {
+            let outline_path = out_dir.join(format!("{}_outlines.lua", name));
+            record(&mut written, outline_path.clone(), export_outlines(name, &polygons, &outline_path))?;
+            outline_polygons = Some(polygons.values().map(Vec::len).sum());
+        }
+        if config.geojson {
+            geojson_polygons.push((name.to_string(), polygons));
+        }
+
+        let mut border_segments = None;
+        if config.borders {
+            let segments = border::find_borders(&chunk_grid);
+            let border_path = out_dir.join(format!("{}_borders.lua", name));
+            record(&mut written, border_path.clone(), export_borders(name, &segments, areas, &border_path))?;
+            border_segments = Some(segments.len());
+        }
+        let payload_len = if config.format.lua() && encoding != TileEncoding::U32 {
+            Some((export.payload_len(encoding)?, export.payload_len(TileEncoding::U32)?))
+        } else {
+            None
+        };
+
+        maps.push(MapOutput {
+            name: name.to_string(),
+            key: grid.key(),
+            encoding,
+            dedup,
+            payload_len,
+            fragments: fragment_counts,
+            outline_polygons,
+            border_segments,
+        });
+    }
+
+    if config.strict && !validator.errors.is_empty() {
+        return Err(format!("validation found {} errors (strict mode):\n{}", validator.errors.len(), validator).into());
+    }
+
+    // Areas on several maps get one color, so it must differ from their neighbors on each
+    let neighbor_graph = world.neighbor_graph();
+    let shared_areas = found_areas.iter().filter(|&&id| world.maps_of(id).len() > 1).count();
+    let (marks, unused_rules) = rules.apply(&found_areas, areas);
+
+    // A merged area is drawn as its parent, so it takes the parent's color and the
+    // parent has to differ from the merged area's neighbors too
+    let drawn: BTreeSet<u32> = found_areas.iter().map(|&id| merge_target(id, &marks, areas)).collect();
+    let mut colors = generate_colors_with_graph(&drawn, &fold_merged(&neighbor_graph, &marks, areas), areas);
+    for &area_id in &found_areas {
+        let target = merge_target(area_id, &marks, areas);
+        if let Some(&color) = colors.get(&target).filter(|_| target != area_id) {
+            colors.insert(area_id, color);
+        }
+    }
+
+    if config.format.lua() {
+        let path = out_dir.join("AreaInfo.lua");
+        let result = export_area_info(&found_areas, areas, &colors, &neighbor_graph, &geometry, &marks, &path);
+        record(&mut written, path, result)?;
+    }
+    if config.format.json() {
+        let path = out_dir.join("AreaInfo.json");
+        let result = json::export_area_info(&found_areas, areas, &colors, &neighbor_graph, &geometry, &marks, &path);
+        record(&mut written, path, result)?;
+    }
+    for (grid, polygons) in &geojson_polygons {
+        let path = out_dir.join(format!("{}.geojson", grid));
+        record(&mut written, path.clone(), geojson::export(grid, polygons, areas, &colors, &path))?;
+    }
+    if let Some(path) = &config.fragment_report {
+        record(&mut written, path.clone(), fs::write(path, &fragment_report))?;
+    }
+
+    // Area hierarchy grouped by root parent
+    let hierarchy = group_by_root(&found_areas, areas);
+    if config.format.lua() {
+        let path = out_dir.join("AreaHierarchy.lua");
+        record(&mut written, path.clone(), export_area_hierarchy(&hierarchy, areas, &path))?;
+    }
+    if config.format.json() {
+        let path = out_dir.join("AreaHierarchy.json");
+        record(&mut written, path.clone(), json::export_area_hierarchy(&hierarchy, areas, &path))?;
+    }
+
+    // UiMap ID to area ID mapping
+    let entries = parse_map_to_area_csv(&config.map_to_area)
+        .map_err(|e| format!("failed to parse {}: {}", config.map_to_area.display(), e))?;
+    if config.format.lua() {
+        let path = out_dir.join("MapToArea.lua");
+        record(&mut written, path.clone(), export_map_to_area(&entries, &path))?;
+    }
+    if config.format.json() {
+        let path = out_dir.join("MapToArea.json");
+        record(&mut written, path.clone(), json::export_map_to_area(&entries, &path))?;
+    }
+
+    // Precomputed fills, so the addon never scans the tile grids
+    let draw_lists = build_draw_lists(&entries, &world, &hierarchy, &marks);
+    if config.format.lua() {
+        let path = out_dir.join("DrawLists.lua");
+        record(&mut written, path.clone(), export_draw_lists(&draw_lists, &path))?;
+    }
+    if config.format.json() {
+        let path = out_dir.join("DrawLists.json");
+        record(&mut written, path.clone(), json::export_draw_lists(&draw_lists, &path))?;
+    }
+
+    Ok(Outputs {
+        plan_log: plan.log,
+        found_areas,
+        rule_count: rules.rules.len(),
+        created_out_dir,
+        maps,
+        validator,
+        neighbor_areas: neighbor_graph.len(),
+        shared_areas,
+        marks,
+        unused_rules,
+        root_zones: hierarchy.len(),
+        map_to_area_entries: entries.len(),
+        draw_lists,
+        written,
+        world,
+    })
+}
+
+/// Note a written file, or turn the writer's error into one naming the file
+fn record<E: fmt::Display>(written: &mut Vec<PathBuf>, path: PathBuf, result: Result<(), E>) -> Result<(), String> {
+    result.map_err(|e| format!("failed to write {}: {}", path.display(), e))?;
+    written.push(path);
+    Ok(())
+}
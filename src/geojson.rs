@@ -0,0 +1,101 @@
+// ============================================================================
+// GeoJSON export: one FeatureCollection of area polygons per grid
+// ============================================================================
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::json;
+use crate::outline::{Polygon, Ring};
+use crate::area::{find_root_parent, AreaTable};
+
+/// Positions are WoW world `[x, y]` in yards (X points north, Y points west), so GIS
+/// tools show the map rotated unless they swap and negate the axes.
+pub fn export(
+    grid_name: &str,
+    polygons: &BTreeMap<u32, Vec<Polygon>>,
+    areas: &AreaTable,
+    colors: &HashMap<u32, (f32, f32, f32)>,
+    out_path: &Path,
+) -> std::io::Result<()> {
+    let mut f = BufWriter::new(File::create(out_path)?);
+
+    writeln!(f, "{{")?;
+    writeln!(f, "  \"type\": \"FeatureCollection\",")?;
+    writeln!(f, "  \"name\": {},", json::string(grid_name))?;
+    writeln!(f, "  \"features\": [")?;
+
+    let mut first = true;
+    for (&area_id, area_polygons) in polygons {
+        if area_polygons.is_empty() {
+            continue;
+        }
+        let (name, parent_id, level) = match areas.get(&area_id) {
+            Some(area) => (area.name.clone(), area.parent_id, area.exploration_level),
+            None => (format!("Unknown_{}", area_id), 0, 0),
+        };
+        let (r, g, b) = colors.get(&area_id).copied().unwrap_or((0.5, 0.5, 0.5));
+
+        let rings: Vec<String> = area_polygons.iter().map(polygon_coordinates).collect();
+        let geometry = if let [single] = rings.as_slice() {
+            format!("{{\"type\": \"Polygon\", \"coordinates\": {}}}", single)
+        } else {
+            format!("{{\"type\": \"MultiPolygon\", \"coordinates\": [{}]}}", rings.join(", "))
+        };
+
+        if !first {
+            writeln!(f, ",")?;
+        }
+        first = false;
+        write!(
+            f,
+            "    {{\"type\": \"Feature\", \"id\": {}, \"properties\": {{\"areaId\": {}, \"name\": {}, \"parentId\": {}, \
+             \"rootParentId\": {}, \"explorationLevel\": {}, \"color\": [{:.3}, {:.3}, {:.3}]}}, \"geometry\": {}}}",
+            area_id,
+            area_id,
+            json::string(&name),
+            parent_id,
+            find_root_parent(area_id, areas),
+            level,
+            r,
+            g,
+            b,
+            geometry
+        )?;
+    }
+
+    if !first {
+        writeln!(f)?;
+    }
+    writeln!(f, "  ]")?;
+    writeln!(f, "}}")?;
+    f.flush()
+}
+
+/// Outer ring counterclockwise and holes clockwise (RFC 7946), each ring closed
+fn polygon_coordinates(polygon: &Polygon) -> String {
+    let rings: Vec<String> = std::iter::once(ring_coordinates(&polygon.outer, true))
+        .chain(polygon.holes.iter().map(|hole| ring_coordinates(hole, false)))
+        .collect();
+    format!("[{}]", rings.join(", "))
+}
+
+fn ring_coordinates(ring: &Ring, counterclockwise: bool) -> String {
+    let signed_area: f64 = (0..ring.len())
+        .map(|i| {
+            let (a, b) = (ring[i], ring[(i + 1) % ring.len()]);
+            a.0 * b.1 - b.0 * a.1
+        })
+        .sum();
+    let mut points: Vec<(f64, f64)> = ring.clone();
+    if (signed_area > 0.0) != counterclockwise {
+        points.reverse();
+    }
+    if let Some(&start) = points.first() {
+        points.push(start);
+    }
+    let coords: Vec<String> = points.iter().map(|(x, y)| format!("[{:.1}, {:.1}]", x, y)).collect();
+    format!("[{}]", coords.join(", "))
+}
@@ -0,0 +1,130 @@
+// ============================================================================
+// Per-area geometry gathered from every map's chunk grid
+// ============================================================================
+
+use std::collections::HashMap;
+
+use crate::component::{Connectivity, Fragment};
+use crate::grid::{self, AreaExtent, ChunkGrid, CHUNK_SIZE};
+use crate::liquid;
+use crate::outline;
+use crate::tiles::TileGrid;
+
+/// A fragment's label point (world coordinates) and its size in square yards
+pub type FragmentAnchor = ((f64, f64), f64);
+
+/// Per-area facts gathered from every map's chunk grid for AreaInfo.lua
+pub struct AreaGeometry {
+    pub connectivity: Connectivity,
+    /// The map holding most of the area's chunks, and the area's extent there
+    pub extents: HashMap<u32, (String, AreaExtent)>,
+    /// Per area and map, the chunk count and label point of every connected fragment,
+    /// largest first. Maps are kept apart because their world coordinates overlap.
+    fragments: HashMap<u32, HashMap<String, Vec<(u32, FragmentAnchor)>>>,
+    /// Every map the area has chunks on, in the order the maps were added
+    pub maps: HashMap<u32, Vec<String>>,
+    /// Chunks across all maps
+    chunk_counts: HashMap<u32, u32>,
+    /// `(water chunks, all chunks)` across all maps
+    pub water_chunks: HashMap<u32, (u32, u32)>,
+}
+
+impl AreaGeometry {
+    pub fn new(connectivity: Connectivity) -> Self {
+        Self {
+            connectivity,
+            extents: HashMap::new(),
+            fragments: HashMap::new(),
+            maps: HashMap::new(),
+            chunk_counts: HashMap::new(),
+            water_chunks: HashMap::new(),
+        }
+    }
+
+    pub fn connectivity_label(&self) -> u8 {
+        match self.connectivity {
+            Connectivity::Four => 4,
+            Connectivity::Eight => 8,
+        }
+    }
+
+    pub fn add_extents(&mut self, grid_name: &str, chunk_grid: &ChunkGrid) {
+        for (area_id, extent) in grid::area_extents(chunk_grid) {
+            self.maps.entry(area_id).or_default().push(grid_name.to_string());
+            *self.chunk_counts.entry(area_id).or_default() += extent.chunk_count;
+            let larger = self.extents.get(&area_id).is_none_or(|(_, e)| extent.chunk_count > e.chunk_count);
+            if larger {
+                self.extents.insert(area_id, (grid_name.to_string(), extent));
+            }
+        }
+    }
+
+    /// The area's chunks on every map, not just its continent
+    pub fn chunk_count(&self, area_id: u32) -> u32 {
+        self.chunk_counts.get(&area_id).copied().unwrap_or(0)
+    }
+
+    /// Approximate area in square yards, across all maps
+    pub fn area_yards(&self, area_id: u32) -> f64 {
+        self.chunk_count(area_id) as f64 * CHUNK_SIZE * CHUNK_SIZE
+    }
+
+    pub fn add_liquids(&mut self, tiles: &TileGrid) {
+        for (area_id, (water, total)) in liquid::water_chunks(&tiles.tiles_raw, &tiles.liquids) {
+            let counts = self.water_chunks.entry(area_id).or_default();
+            counts.0 += water;
+            counts.1 += total;
+        }
+    }
+
+    /// Share of the area's chunks that are shallow or deep water
+    pub fn water_fraction(&self, area_id: u32) -> Option<f64> {
+        let &(water, total) = self.water_chunks.get(&area_id)?;
+        (total > 0).then(|| water as f64 / total as f64)
+    }
+
+    /// Record each fragment's size and label point, so both follow the same connectivity
+    /// and order. `fragments` come from `find_fragments`, largest first within each area.
+    pub fn add_fragments(&mut self, grid_name: &str, fragments: &[Fragment]) {
+        for fragment in fragments {
+            let yards = fragment.chunks as f64 * CHUNK_SIZE * CHUNK_SIZE;
+            let anchor = (outline::fragment_anchor(&fragment.cells), yards);
+            let per_map = self.fragments.entry(fragment.area).or_default();
+            per_map.entry(grid_name.to_string()).or_default().push((fragment.chunks, anchor));
+        }
+    }
+
+    /// `(chunk count, label anchor)` of every fragment on the area's continent (the map in
+    /// `extents`), largest first; empty for areas without chunks
+    pub fn continent_fragments(&self, area_id: u32) -> &[(u32, FragmentAnchor)] {
+        self.extents
+            .get(&area_id)
+            .and_then(|(continent, _)| self.fragments.get(&area_id)?.get(continent))
+            .map_or(&[], Vec::as_slice)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::tiles_from_rows;
+
+    #[test]
+    fn chunk_counts_cover_every_map() {
+        let mut geometry = AreaGeometry::new(Connectivity::Four);
+        let kalimdor = tiles_from_rows(&["111", "112"]);
+        let islands = tiles_from_rows(&["1.1", "22."]);
+        geometry.add_extents("Kalimdor", &ChunkGrid::new(&kalimdor));
+        geometry.add_extents("Islands", &ChunkGrid::new(&islands));
+
+        // The continent is the map with most of the area; the counts span both
+        assert_eq!(geometry.extents[&1].0, "Kalimdor");
+        assert_eq!(geometry.extents[&1].1.chunk_count, 5);
+        assert_eq!(geometry.chunk_count(1), 7);
+        assert_eq!(geometry.extents[&2].0, "Islands");
+        assert_eq!(geometry.chunk_count(2), 3);
+        assert_eq!(geometry.chunk_count(3), 0);
+        assert!((geometry.area_yards(1) - 7.0 * CHUNK_SIZE * CHUNK_SIZE).abs() < 1e-6);
+        assert_eq!(geometry.maps[&1], ["Kalimdor", "Islands"]);
+    }
+}
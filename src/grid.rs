@@ -0,0 +1,224 @@
+// ============================================================================
+// Chunk grid: a map's tiles flattened into one 1024x1024 grid of chunks
+// ============================================================================
+
+use std::collections::HashMap;
+
+use crate::tiles::tile_key;
+
+/// Width of one ADT tile in yards
+pub const TILE_SIZE: f64 = 1600.0 / 3.0;
+/// Distance from the map origin to its edge
+pub const HALF_SIZE: f64 = TILE_SIZE * 32.0;
+pub const CHUNK_SIZE: f64 = TILE_SIZE / 16.0;
+/// Chunks along one side of a map
+pub const GRID_SIZE: i32 = 64 * 16;
+
+/// `tiles_raw` addressed by global chunk coordinates: `x = tileX * 16 + chunkX`,
+/// `y = tileY * 16 + chunkY`
+pub struct ChunkGrid<'a> {
+    tiles: &'a HashMap<u32, Vec<u32>>,
+}
+
+impl<'a> ChunkGrid<'a> {
+    pub fn new(tiles: &'a HashMap<u32, Vec<u32>>) -> Self {
+        Self { tiles }
+    }
+
+    /// AreaID of a chunk; 0 off the map and in tiles without an ADT
+    pub fn area_at(&self, x: i32, y: i32) -> u32 {
+        if !(0..GRID_SIZE).contains(&x) || !(0..GRID_SIZE).contains(&y) {
+            return 0;
+        }
+        let key = (y / 16) as u32 * 64 + (x / 16) as u32;
+        self.tiles
+            .get(&key)
+            .and_then(|ids| ids.get(((y % 16) * 16 + x % 16) as usize))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// The chunk under a world point and its AreaID; `None` off the map
+    pub fn area_at_world(&self, x: f64, y: f64) -> Option<(ChunkLocation, u32)> {
+        let location = ChunkLocation::from_world(x, y)?;
+        let (gx, gy) = location.global();
+        Some((location, self.area_at(gx, gy)))
+    }
+
+    /// Every chunk with a nonzero AreaID as `(x, y, area)`, tile by tile in key order
+    pub fn chunks(&self) -> Vec<(i32, i32, u32)> {
+        let mut keys: Vec<u32> = self.tiles.keys().copied().collect();
+        keys.sort_unstable();
+
+        let mut out = Vec::new();
+        for key in keys {
+            let (tx, ty) = ((key % 64) as i32, (key / 64) as i32);
+            for (i, &area) in self.tiles[&key].iter().enumerate().filter(|(_, &a)| a != 0) {
+                let (cx, cy) = ((i % 16) as i32, (i / 16) as i32);
+                out.push((tx * 16 + cx, ty * 16 + cy, area));
+            }
+        }
+        out
+    }
+}
+
+/// World `(x, y)` of a point on the chunk lattice (chunk corners are whole numbers).
+/// World Y falls as grid x grows and world X falls as grid y grows.
+pub fn grid_to_world(x: f64, y: f64) -> (f64, f64) {
+    (HALF_SIZE - y * CHUNK_SIZE, HALF_SIZE - x * CHUNK_SIZE)
+}
+
+/// Inverse of `grid_to_world`
+pub fn world_to_grid(x: f64, y: f64) -> (f64, f64) {
+    ((HALF_SIZE - y) / CHUNK_SIZE, (HALF_SIZE - x) / CHUNK_SIZE)
+}
+
+/// The tile and chunk a world point falls in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChunkLocation {
+    pub tile_x: u32,
+    pub tile_y: u32,
+    /// Chunk within the tile, 0..16
+    pub chunk_x: u32,
+    pub chunk_y: u32,
+}
+
+impl ChunkLocation {
+    /// `None` outside the 64x64-tile map
+    pub fn from_world(x: f64, y: f64) -> Option<Self> {
+        let (gx, gy) = world_to_grid(x, y);
+        let (gx, gy) = (gx.floor(), gy.floor());
+        if !(0.0..GRID_SIZE as f64).contains(&gx) || !(0.0..GRID_SIZE as f64).contains(&gy) {
+            return None;
+        }
+        let (gx, gy) = (gx as u32, gy as u32);
+        Some(Self { tile_x: gx / 16, tile_y: gy / 16, chunk_x: gx % 16, chunk_y: gy % 16 })
+    }
+
+    pub fn tile_key(&self) -> u32 {
+        tile_key(self.tile_x, self.tile_y)
+    }
+
+    /// Global chunk coordinates, as used by `ChunkGrid`
+    pub fn global(&self) -> (i32, i32) {
+        ((self.tile_x * 16 + self.chunk_x) as i32, (self.tile_y * 16 + self.chunk_y) as i32)
+    }
+}
+
+/// Where an area sits on one map's chunk grid
+pub struct AreaExtent {
+    pub chunk_count: u32,
+    /// Inclusive chunk bounds `(min_x, min_y, max_x, max_y)`
+    chunks: (i32, i32, i32, i32),
+    pub min_tile_key: u32,
+    pub max_tile_key: u32,
+}
+
+impl AreaExtent {
+    /// World-coordinate bounding box `(min_x, min_y, max_x, max_y)`
+    pub fn world_bounds(&self) -> (f64, f64, f64, f64) {
+        let (min_x, min_y, max_x, max_y) = self.chunks;
+        let (wx0, wy0) = grid_to_world((max_x + 1) as f64, (max_y + 1) as f64);
+        let (wx1, wy1) = grid_to_world(min_x as f64, min_y as f64);
+        (wx0, wy0, wx1, wy1)
+    }
+
+    /// Approximate area in square yards
+    pub fn area_yards(&self) -> f64 {
+        self.chunk_count as f64 * CHUNK_SIZE * CHUNK_SIZE
+    }
+}
+
+/// Chunk count, bounds and tile-key range of every area on the grid
+pub fn area_extents(grid: &ChunkGrid) -> HashMap<u32, AreaExtent> {
+    let mut extents: HashMap<u32, AreaExtent> = HashMap::new();
+    for (x, y, area) in grid.chunks() {
+        let key = (y / 16) as u32 * 64 + (x / 16) as u32;
+        let extent = extents.entry(area).or_insert(AreaExtent {
+            chunk_count: 0,
+            chunks: (x, y, x, y),
+            min_tile_key: key,
+            max_tile_key: key,
+        });
+        extent.chunk_count += 1;
+        let (min_x, min_y, max_x, max_y) = extent.chunks;
+        extent.chunks = (min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y));
+        extent.min_tile_key = extent.min_tile_key.min(key);
+        extent.max_tile_key = extent.max_tile_key.max(key);
+    }
+    extents
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::tiles_from_rows;
+
+    fn location(tile_x: u32, tile_y: u32, chunk_x: u32, chunk_y: u32) -> Option<ChunkLocation> {
+        Some(ChunkLocation { tile_x, tile_y, chunk_x, chunk_y })
+    }
+
+    #[test]
+    fn grid_and_world_round_trip() {
+        // Tile corners across the map, the center, and points between chunk corners
+        let mut points: Vec<(f64, f64)> = Vec::new();
+        for ty in [0, 1, 31, 32, 33, 63, 64] {
+            for tx in [0, 1, 31, 32, 33, 63, 64] {
+                points.push(((tx * 16) as f64, (ty * 16) as f64));
+            }
+        }
+        points.extend([(512.0, 512.0), (0.5, 1023.5), (700.25, 3.75)]);
+        for (x, y) in points {
+            let (wx, wy) = grid_to_world(x, y);
+            let (gx, gy) = world_to_grid(wx, wy);
+            assert!((gx - x).abs() < 1e-9 && (gy - y).abs() < 1e-9, "({}, {}) came back as ({}, {})", x, y, gx, gy);
+        }
+
+        // The map center is the corner shared by tiles 31 and 32; the map's corners are at
+        // +-HALF_SIZE, with world X along grid y and world Y along grid x, both reversed
+        assert_eq!(grid_to_world(512.0, 512.0), (0.0, 0.0));
+        assert_eq!(grid_to_world(0.0, 0.0), (HALF_SIZE, HALF_SIZE));
+        assert_eq!(grid_to_world(1024.0, 0.0), (HALF_SIZE, -HALF_SIZE));
+        assert_eq!(grid_to_world(0.0, 1024.0), (-HALF_SIZE, HALF_SIZE));
+        assert!((grid_to_world(16.0, 0.0).1 - (HALF_SIZE - TILE_SIZE)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn chunk_locations() {
+        // Just inside each side of the center corner
+        let e = 0.01;
+        assert_eq!(ChunkLocation::from_world(e, e), location(31, 31, 15, 15));
+        assert_eq!(ChunkLocation::from_world(-e, -e), location(32, 32, 0, 0));
+        assert_eq!(ChunkLocation::from_world(e, -e), location(32, 31, 0, 15));
+        assert_eq!(ChunkLocation::from_world(-e, e), location(31, 32, 15, 0));
+
+        // Chunk centers inside a few tiles
+        for (tx, ty, cx, cy) in [(0, 0, 0, 0), (33, 31, 7, 12), (63, 63, 15, 15), (12, 50, 15, 0)] {
+            let (x, y) = grid_to_world((tx * 16 + cx) as f64 + 0.5, (ty * 16 + cy) as f64 + 0.5);
+            let found = ChunkLocation::from_world(x, y).unwrap();
+            assert_eq!(found, location(tx, ty, cx, cy).unwrap());
+            assert_eq!(found.tile_key(), ty * 64 + tx);
+            assert_eq!(found.global(), ((tx * 16 + cx) as i32, (ty * 16 + cy) as i32));
+        }
+
+        // The map is half-open: its top corner is on it, the far edges are not
+        assert_eq!(ChunkLocation::from_world(HALF_SIZE, HALF_SIZE), location(0, 0, 0, 0));
+        assert_eq!(ChunkLocation::from_world(-HALF_SIZE + e, -HALF_SIZE + e), location(63, 63, 15, 15));
+        assert_eq!(ChunkLocation::from_world(-HALF_SIZE, 0.0), None);
+        assert_eq!(ChunkLocation::from_world(0.0, HALF_SIZE + e), None);
+    }
+
+    #[test]
+    fn area_at_world_reads_the_chunk_under_a_point() {
+        let tiles = tiles_from_rows(&["12", "34"]);
+        let grid = ChunkGrid::new(&tiles);
+        for (x, y, area) in [(0, 0, 1), (1, 0, 2), (0, 1, 3), (1, 1, 4), (2, 0, 0)] {
+            let (wx, wy) = grid_to_world(x as f64 + 0.5, y as f64 + 0.5);
+            let (found, id) = grid.area_at_world(wx, wy).unwrap();
+            assert_eq!((found.global(), id), ((x, y), area));
+        }
+        assert!(grid.area_at_world(-HALF_SIZE - 1.0, 0.0).is_none());
+        assert_eq!(grid.area_at(-1, 0), 0);
+    }
+}
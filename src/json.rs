@@ -0,0 +1,501 @@
+// ============================================================================
+// JSON output: the tile grids, AreaInfo, AreaHierarchy and MapToArea for tools
+// that cannot read Lua. The layout is documented in the README ("JSON output").
+// ============================================================================
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::area::{find_root_parent, AreaHierarchy, AreaTable, MapToAreaEntry};
+use crate::drawlist::DrawList;
+use crate::geometry::AreaGeometry;
+use crate::neighbors::NeighborGraph;
+use crate::rules::AreaMarks;
+use crate::tiles::TileGrid;
+
+/// Bumped whenever a field changes meaning or disappears; new fields keep the version
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// A JSON string literal
+pub fn string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn join<T: ToString>(values: impl IntoIterator<Item = T>) -> String {
+    values.into_iter().map(|v| v.to_string()).collect::<Vec<_>>().join(", ")
+}
+
+/// Opens the document and writes the `schema`/`schemaVersion` pair every file starts with
+fn begin(out_path: &Path, schema: &str) -> std::io::Result<BufWriter<File>> {
+    let mut f = BufWriter::new(File::create(out_path)?);
+    writeln!(f, "{{")?;
+    writeln!(f, "  \"schema\": \"zonemap.{}\",", schema)?;
+    writeln!(f, "  \"schemaVersion\": {},", SCHEMA_VERSION)?;
+    Ok(f)
+}
+
+/// Writes `"key": {` followed by one `"id": value` line per entry and the closing brace
+fn write_object<W: Write>(
+    f: &mut W,
+    indent: &str,
+    key: &str,
+    entries: impl IntoIterator<Item = (String, String)>,
+    last: bool,
+) -> std::io::Result<()> {
+    writeln!(f, "{}{}: {{", indent, string(key))?;
+    let entries: Vec<(String, String)> = entries.into_iter().collect();
+    for (i, (k, v)) in entries.iter().enumerate() {
+        let comma = if i + 1 < entries.len() { "," } else { "" };
+        writeln!(f, "{}  {}: {}{}", indent, string(k), v, comma)?;
+    }
+    writeln!(f, "{}}}{}", indent, if last { "" } else { "," })
+}
+
+/// `<Grid>_tiles.json`: every tile as its 256 raw AreaIDs, row by row
+pub fn export_tiles(export: &TileGrid, out_path: &Path) -> std::io::Result<()> {
+    let mut f = begin(out_path, "tiles")?;
+    let optional = |v: Option<u32>| v.map(|v| v.to_string()).unwrap_or_else(|| "null".to_string());
+
+    writeln!(f, "  \"name\": {},", string(&export.continent_name))?;
+    writeln!(f, "  \"uiMapId\": {},", optional(export.ui_map_id))?;
+    writeln!(f, "  \"tileSize\": 16,")?;
+    writeln!(f, "  \"tilesPerSide\": 64,")?;
+    match &export.existing_tiles {
+        Some(inventory) => writeln!(f, "  \"existingTiles\": [{}],", join(inventory.keys()))?,
+        None => writeln!(f, "  \"existingTiles\": null,")?,
+    }
+
+    let tiles: BTreeMap<u32, &Vec<u32>> = export.tiles_raw.iter().map(|(&k, v)| (k, v)).collect();
+    write_object(&mut f, "  ", "tiles", tiles.iter().map(|(k, ids)| (k.to_string(), format!("[{}]", join(ids.iter())))), false)?;
+    let liquid = export.liquid_tiles().into_iter().map(|(k, chunks)| {
+        (k.to_string(), format!("[{}]", join(chunks.iter().map(|l| l.code()))))
+    });
+    write_object(&mut f, "  ", "liquid", liquid, true)?;
+    writeln!(f, "}}")?;
+    f.flush()
+}
+
+/// `AreaInfo.json`: the same fields as `AreaInfo.lua`
+pub fn export_area_info(
+    found_areas: &BTreeSet<u32>,
+    areas: &AreaTable,
+    colors: &HashMap<u32, (f32, f32, f32)>,
+    neighbors: &NeighborGraph,
+    geometry: &AreaGeometry,
+    marks: &HashMap<u32, AreaMarks>,
+    out_path: &Path,
+) -> std::io::Result<()> {
+    let mut f = begin(out_path, "areaInfo")?;
+    writeln!(f, "  \"connectivity\": {},", geometry.connectivity_label())?;
+
+    let mut entries = Vec::new();
+    for &area_id in found_areas.iter().filter(|&&id| id != 0) {
+        let (name, parent_id, level) = match areas.get(&area_id) {
+            Some(area) => (area.name.clone(), area.parent_id, area.exploration_level),
+            None => (format!("Unknown_{}", area_id), 0, 0),
+        };
+        let (r, g, b) = colors.get(&area_id).copied().unwrap_or((0.5, 0.5, 0.5));
+        let mut fields = vec![
+            format!("\"name\": {}", string(&name)),
+            format!("\"parentId\": {}", parent_id),
+            format!("\"rootParentId\": {}", find_root_parent(area_id, areas)),
+            format!("\"explorationLevel\": {}", level),
+            format!("\"color\": [{:.3}, {:.3}, {:.3}]", r, g, b),
+            format!("\"neighborCount\": {}", neighbors.get(&area_id).map(|n| n.len()).unwrap_or(0)),
+        ];
+        let area_marks = marks.get(&area_id).copied().unwrap_or_default();
+        fields.push(format!("\"sea\": {}", area_marks.sea));
+        fields.push(format!("\"hidden\": {}", area_marks.hidden));
+        fields.push(format!("\"mergeIntoParent\": {}", area_marks.merge_into_parent));
+        if let Some((continent, extent)) = geometry.extents.get(&area_id) {
+            let (min_x, min_y, max_x, max_y) = extent.world_bounds();
+            fields.push(format!("\"continent\": {}", string(continent)));
+            fields.push(format!("\"bounds\": [{:.1}, {:.1}, {:.1}, {:.1}]", min_x, min_y, max_x, max_y));
+            fields.push(format!("\"tileKeyRange\": [{}, {}]", extent.min_tile_key, extent.max_tile_key));
+            fields.push(format!("\"chunkCount\": {}", geometry.chunk_count(area_id)));
+            fields.push(format!("\"areaYards\": {:.0}", geometry.area_yards(area_id)));
+        }
+        if let Some(maps) = geometry.maps.get(&area_id) {
+            fields.push(format!("\"maps\": [{}]", join(maps.iter().map(|m| string(m)))));
+        }
+        if let Some(fraction) = geometry.water_fraction(area_id) {
+            fields.push(format!("\"waterFraction\": {:.3}", fraction));
+        }
+        let fragments = geometry.continent_fragments(area_id);
+        if let Some((_, ((x, y), _))) = fragments.first() {
+            fields.push(format!("\"fragmentCount\": {}", fragments.len()));
+            fields.push(format!("\"fragmentSizes\": [{}]", join(fragments.iter().map(|(chunks, _)| chunks))));
+            fields.push(format!("\"labelAnchor\": [{:.1}, {:.1}]", x, y));
+            let points = fragments.iter().map(|(_, ((x, y), _))| format!("[{:.1}, {:.1}]", x, y));
+            fields.push(format!("\"fragmentAnchors\": [{}]", join(points)));
+        }
+        entries.push((area_id.to_string(), format!("{{{}}}", fields.join(", "))));
+    }
+
+    write_object(&mut f, "  ", "areas", entries, true)?;
+    writeln!(f, "}}")?;
+    f.flush()
+}
+
+/// `AreaHierarchy.json`: every root zone with the found areas under it
+pub fn export_area_hierarchy(
+    hierarchy: &AreaHierarchy,
+    areas: &AreaTable,
+    out_path: &Path,
+) -> std::io::Result<()> {
+    let mut f = begin(out_path, "areaHierarchy")?;
+    let zones = hierarchy.iter().map(|(root_id, children)| {
+        let name = areas.get(root_id).map(|a| a.name.clone()).unwrap_or_else(|| format!("Unknown_{}", root_id));
+        let children = join(children.iter().map(|(id, name)| format!("{}: {}", string(&id.to_string()), string(name))));
+        (root_id.to_string(), format!("{{\"name\": {}, \"children\": {{{}}}}}", string(&name), children))
+    });
+    write_object(&mut f, "  ", "zones", zones, true)?;
+    writeln!(f, "}}")?;
+    f.flush()
+}
+
+/// `MapToArea.json`: UiMap ID -> area and the reverse lookup. Later CSV rows win, as in Lua.
+pub fn export_map_to_area(entries: &[MapToAreaEntry], out_path: &Path) -> std::io::Result<()> {
+    let mut map_to_area: BTreeMap<u32, &MapToAreaEntry> = BTreeMap::new();
+    let mut area_to_map: BTreeMap<u32, u32> = BTreeMap::new();
+    for entry in entries {
+        map_to_area.insert(entry.map_id, entry);
+        area_to_map.insert(entry.area_id, entry.map_id);
+    }
+
+    let mut f = begin(out_path, "mapToArea")?;
+    let maps = map_to_area.iter().map(|(map_id, e)| {
+        (map_id.to_string(), format!("{{\"areaId\": {}, \"name\": {}}}", e.area_id, string(&e.zone_name)))
+    });
+    write_object(&mut f, "  ", "mapToArea", maps, false)?;
+    write_object(&mut f, "  ", "areaToMap", area_to_map.iter().map(|(a, m)| (a.to_string(), m.to_string())), true)?;
+    writeln!(f, "}}")?;
+    f.flush()
+}
+
+/// `DrawLists.json`: every UiMap's rectangles per grid, as in `DrawLists.lua`
+pub fn export_draw_lists(lists: &[DrawList], out_path: &Path) -> std::io::Result<()> {
+    let mut f = begin(out_path, "drawLists")?;
+    let entries = lists.iter().map(|list| {
+        let grids = join(list.grids.iter().map(|(grid, rects)| {
+            let rects = join(rects.iter().map(|r| format!("[{}, {}, {}, {}, {}]", r.x, r.y, r.w, r.h, r.area)));
+            format!("{}: [{}]", string(grid), rects)
+        }));
+        let value = format!(
+            "{{\"areaId\": {}, \"rootAreaId\": {}, \"grids\": {{{}}}}}",
+            list.area_id, list.root_area_id, grids
+        );
+        (list.ui_map_id.to_string(), value)
+    });
+    write_object(&mut f, "  ", "drawLists", entries, true)?;
+    writeln!(f, "}}")?;
+    f.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::component::Connectivity;
+    use crate::grid::ChunkGrid;
+    use crate::liquid::Liquid;
+    use crate::testutil::{area_table, tiles_from_rows, TempDir};
+
+    /// Just enough of a strict JSON reader to read our files back
+    #[derive(Debug, PartialEq)]
+    enum Value {
+        Null,
+        Bool(bool),
+        Number(f64),
+        String(String),
+        Array(Vec<Value>),
+        Object(Vec<(String, Value)>),
+    }
+
+    impl Value {
+        fn get(&self, key: &str) -> &Value {
+            match self {
+                Value::Object(fields) => {
+                    &fields.iter().find(|(k, _)| k == key).unwrap_or_else(|| panic!("no key {}", key)).1
+                }
+                other => panic!("{:?} is not an object", other),
+            }
+        }
+
+        fn keys(&self) -> Vec<&str> {
+            match self {
+                Value::Object(fields) => fields.iter().map(|(k, _)| k.as_str()).collect(),
+                other => panic!("{:?} is not an object", other),
+            }
+        }
+
+        fn str(&self) -> &str {
+            match self {
+                Value::String(s) => s,
+                other => panic!("{:?} is not a string", other),
+            }
+        }
+
+        fn num(&self) -> f64 {
+            match self {
+                Value::Number(n) => *n,
+                other => panic!("{:?} is not a number", other),
+            }
+        }
+
+        fn numbers(&self) -> Vec<f64> {
+            match self {
+                Value::Array(items) => items.iter().map(Value::num).collect(),
+                other => panic!("{:?} is not an array", other),
+            }
+        }
+    }
+
+    struct Reader<'a> {
+        text: &'a [u8],
+        pos: usize,
+    }
+
+    impl Reader<'_> {
+        fn parse(text: &str) -> Value {
+            let mut reader = Reader { text: text.as_bytes(), pos: 0 };
+            let value = reader.value();
+            reader.skip_space();
+            assert_eq!(reader.pos, text.len(), "trailing text after the document");
+            value
+        }
+
+        fn skip_space(&mut self) {
+            while self.text.get(self.pos).is_some_and(|c| b" \t\r\n".contains(c)) {
+                self.pos += 1;
+            }
+        }
+
+        fn expect(&mut self, c: u8) {
+            self.skip_space();
+            assert_eq!(self.text.get(self.pos), Some(&c), "expected '{}' at byte {}", c as char, self.pos);
+            self.pos += 1;
+        }
+
+        fn value(&mut self) -> Value {
+            self.skip_space();
+            match self.text[self.pos] {
+                b'{' => {
+                    self.pos += 1;
+                    let mut fields: Vec<(String, Value)> = Vec::new();
+                    self.skip_space();
+                    if self.text[self.pos] == b'}' {
+                        self.pos += 1;
+                        return Value::Object(fields);
+                    }
+                    loop {
+                        self.skip_space();
+                        let key = self.string();
+                        assert!(fields.iter().all(|(k, _)| *k != key), "duplicate key {}", key);
+                        self.expect(b':');
+                        fields.push((key, self.value()));
+                        self.skip_space();
+                        self.pos += 1;
+                        match self.text[self.pos - 1] {
+                            b',' => continue,
+                            b'}' => return Value::Object(fields),
+                            c => panic!("unexpected '{}' at byte {}", c as char, self.pos - 1),
+                        }
+                    }
+                }
+                b'[' => {
+                    self.pos += 1;
+                    let mut items = Vec::new();
+                    self.skip_space();
+                    if self.text[self.pos] == b']' {
+                        self.pos += 1;
+                        return Value::Array(items);
+                    }
+                    loop {
+                        items.push(self.value());
+                        self.skip_space();
+                        self.pos += 1;
+                        match self.text[self.pos - 1] {
+                            b',' => continue,
+                            b']' => return Value::Array(items),
+                            c => panic!("unexpected '{}' at byte {}", c as char, self.pos - 1),
+                        }
+                    }
+                }
+                b'"' => Value::String(self.string()),
+                _ => {
+                    let words = [("null", Value::Null), ("true", Value::Bool(true)), ("false", Value::Bool(false))];
+                    for (word, value) in words {
+                        if self.text[self.pos..].starts_with(word.as_bytes()) {
+                            self.pos += word.len();
+                            return value;
+                        }
+                    }
+                    let start = self.pos;
+                    while self.text.get(self.pos).is_some_and(|c| b"+-.eE0123456789".contains(c)) {
+                        self.pos += 1;
+                    }
+                    let number = std::str::from_utf8(&self.text[start..self.pos]).unwrap();
+                    let parsed = number.parse().unwrap_or_else(|_| panic!("bad number '{}' at byte {}", number, start));
+                    Value::Number(parsed)
+                }
+            }
+        }
+
+        fn string(&mut self) -> String {
+            assert_eq!(self.text[self.pos], b'"', "expected a string at byte {}", self.pos);
+            self.pos += 1;
+            let mut out = Vec::new();
+            loop {
+                let c = self.text[self.pos];
+                self.pos += 1;
+                match c {
+                    b'"' => return String::from_utf8(out).unwrap(),
+                    b'\\' => {
+                        let escaped = self.text[self.pos];
+                        self.pos += 1;
+                        let c = match escaped {
+                            b'"' => '"',
+                            b'\\' => '\\',
+                            b'/' => '/',
+                            b'n' => '\n',
+                            b'r' => '\r',
+                            b't' => '\t',
+                            b'b' => '\u{8}',
+                            b'f' => '\u{c}',
+                            b'u' => {
+                                let hex = std::str::from_utf8(&self.text[self.pos..self.pos + 4]).unwrap();
+                                self.pos += 4;
+                                char::from_u32(u32::from_str_radix(hex, 16).unwrap()).unwrap()
+                            }
+                            other => panic!("bad escape '\\{}'", other as char),
+                        };
+                        out.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                    }
+                    c if c < 0x20 => panic!("raw control character {:#04x} in a string", c),
+                    c => out.push(c),
+                }
+            }
+        }
+    }
+
+    const AWKWARD: &str = "Zul'\"Drak\" \\ Ö\ttab\nline\r\u{1}\u{1f}";
+
+    fn read(path: &Path) -> Value {
+        Reader::parse(&std::fs::read_to_string(path).unwrap())
+    }
+
+    #[test]
+    fn strings_escape_quotes_and_control_characters() {
+        assert_eq!(string("plain"), "\"plain\"");
+        assert_eq!(string(AWKWARD), "\"Zul'\\\"Drak\\\" \\\\ Ö\\ttab\\nline\\r\\u0001\\u001f\"");
+        assert_eq!(Reader::parse(&string(AWKWARD)), Value::String(AWKWARD.to_string()));
+    }
+
+    #[test]
+    fn tiles_round_trip() {
+        let dir = TempDir::new("json-tiles");
+        let path = dir.0.join("Kalimdor_tiles.json");
+        let mut grid = TileGrid::new(AWKWARD);
+        grid.ui_map_id = Some(1414);
+        grid.tiles_raw.insert(5, (0..256).map(|i| i * 1000).collect());
+        grid.tiles_raw.insert(70, vec![14; 256]);
+        let mut liquids = vec![Liquid::Land; 256];
+        liquids[3] = Liquid::Deep;
+        liquids[4] = Liquid::Slime;
+        grid.liquids.insert(70, liquids);
+        grid.liquids.insert(5, vec![Liquid::Land; 256]);
+        export_tiles(&grid, &path).unwrap();
+
+        let doc = read(&path);
+        assert_eq!(doc.get("schema").str(), "zonemap.tiles");
+        assert_eq!(doc.get("schemaVersion").num(), SCHEMA_VERSION as f64);
+        assert_eq!(doc.get("name").str(), AWKWARD);
+        assert_eq!(doc.get("uiMapId").num(), 1414.0);
+        assert_eq!(doc.get("existingTiles"), &Value::Null);
+        let tiles = doc.get("tiles");
+        assert_eq!(tiles.keys(), ["5", "70"]);
+        for (key, ids) in &grid.tiles_raw {
+            let read: Vec<u32> = tiles.get(&key.to_string()).numbers().iter().map(|&n| n as u32).collect();
+            assert_eq!(&read, ids);
+        }
+        // Land-only tiles are left out
+        let liquid = doc.get("liquid");
+        assert_eq!(liquid.keys(), ["70"]);
+        assert_eq!(liquid.get("70").numbers()[..6], [0.0, 0.0, 0.0, 2.0, 4.0, 0.0]);
+    }
+
+    #[test]
+    fn area_files_round_trip() {
+        let dir = TempDir::new("json-areas");
+        let mut areas = area_table(&[(1, AWKWARD, 0), (2, "Razor \"Hill\"", 1)]);
+        areas.get_mut(&2).unwrap().exploration_level = 12;
+        let found: BTreeSet<u32> = [0, 1, 2, 3].into_iter().collect();
+        let colors: HashMap<u32, (f32, f32, f32)> = [(1, (0.25, 0.5, 1.0))].into_iter().collect();
+        let mut neighbors = NeighborGraph::new();
+        neighbors.entry(1).or_default().insert(2);
+        neighbors.entry(2).or_default().insert(1);
+        let marks: HashMap<u32, AreaMarks> = [(2, AreaMarks { sea: true, ..Default::default() })].into_iter().collect();
+        let mut geometry = AreaGeometry::new(Connectivity::Four);
+        let tiles = tiles_from_rows(&["112", "112"]);
+        geometry.add_extents("Kal\"imdor", &ChunkGrid::new(&tiles));
+
+        let path = dir.0.join("AreaInfo.json");
+        export_area_info(&found, &areas, &colors, &neighbors, &geometry, &marks, &path).unwrap();
+        let doc = read(&path);
+        assert_eq!(doc.get("connectivity").num(), 4.0);
+        let entries = doc.get("areas");
+        // Area 0 is left out; 3 is not in the AreaTable
+        assert_eq!(entries.keys(), ["1", "2", "3"]);
+        let first = entries.get("1");
+        assert_eq!(first.get("name").str(), AWKWARD);
+        assert_eq!(first.get("color").numbers(), [0.25, 0.5, 1.0]);
+        assert_eq!(first.get("neighborCount").num(), 1.0);
+        assert_eq!(first.get("continent").str(), "Kal\"imdor");
+        assert_eq!(first.get("chunkCount").num(), 4.0);
+        assert_eq!(first.get("sea"), &Value::Bool(false));
+        let second = entries.get("2");
+        assert_eq!((second.get("name").str(), second.get("parentId").num()), ("Razor \"Hill\"", 1.0));
+        assert_eq!((second.get("rootParentId").num(), second.get("explorationLevel").num()), (1.0, 12.0));
+        assert_eq!(second.get("sea"), &Value::Bool(true));
+        assert_eq!(entries.get("3").get("name").str(), "Unknown_3");
+
+        let path = dir.0.join("AreaHierarchy.json");
+        let hierarchy = crate::area::group_by_root(&found, &areas);
+        export_area_hierarchy(&hierarchy, &areas, &path).unwrap();
+        let zones = read(&path);
+        let zone = zones.get("zones").get("1");
+        assert_eq!(zone.get("name").str(), AWKWARD);
+        assert_eq!(zone.get("children").get("2").str(), "Razor \"Hill\"");
+
+        let path = dir.0.join("MapToArea.json");
+        let entries = [
+            MapToAreaEntry { zone_name: AWKWARD.to_string(), map_id: 1411, area_id: 1 },
+            MapToAreaEntry { zone_name: "Later \\ wins".to_string(), map_id: 1411, area_id: 2 },
+        ];
+        export_map_to_area(&entries, &path).unwrap();
+        let doc = read(&path);
+        assert_eq!(doc.get("mapToArea").get("1411").get("name").str(), "Later \\ wins");
+        assert_eq!(doc.get("areaToMap").keys(), ["1", "2"]);
+
+        // Empty inputs still give well-formed documents
+        let path = dir.0.join("DrawLists.json");
+        export_draw_lists(&[], &path).unwrap();
+        assert_eq!(read(&path).get("drawLists").keys(), Vec::<&str>::new());
+    }
+}
@@ -0,0 +1,46 @@
+//! Reads WoW root ADTs (loose, from MPQs or from CASC) and the AreaTable, and builds the
+//! per-chunk area grids, neighbor graph, colors and geometry that the ZoneMap addon draws.
+//!
+//! The `zone-map` binary is a thin command-line front end over this crate. Other tools can
+//! run the whole pipeline with [`generate::generate`], or load a [`World`] from a
+//! [`config::Config`], look up areas with [`grid::ChunkGrid::area_at_world`] and write any of
+//! the Lua, JSON, GeoJSON, SVG or PNG outputs. Nothing in the library prints; progress
+//! comes back as data (plan logs, map stats, validation messages) for the caller to show.
+//!
+//! With the `serde` feature, the data types (`AreaInfo`, `TileGrid`, `World`, ...) derive
+//! `Serialize` and `Deserialize`.
+
+pub mod area;
+pub mod border;
+pub mod casc;
+pub mod component;
+pub mod config;
+pub mod dataset;
+pub mod diff;
+pub mod drawlist;
+pub mod export;
+pub mod generate;
+pub mod geojson;
+pub mod geometry;
+pub mod grid;
+pub mod json;
+pub mod liquid;
+pub mod mpq;
+pub mod neighbors;
+pub mod outline;
+pub mod png;
+pub mod render;
+pub mod rules;
+pub mod sources;
+pub mod tiles;
+pub mod validate;
+pub mod wdt;
+pub mod world;
+
+#[cfg(test)]
+mod testutil;
+
+pub use area::{AreaInfo, AreaTable};
+pub use neighbors::NeighborGraph;
+pub use tiles::TileGrid;
+pub use world::{MapGrid, MapKey, World};
@@ -0,0 +1,371 @@
+// ============================================================================
+// Liquid classification: what covers each chunk, from MCNK flags, MCLQ and MH2O
+// ============================================================================
+
+use std::collections::HashMap;
+
+use crate::wdt::chunks;
+
+/// What covers most of a chunk
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Liquid {
+    #[default]
+    Land,
+    Shallow,
+    /// Water that causes fatigue (the MCLQ fatigue flag or the MH2O deep bit)
+    Deep,
+    Lava,
+    Slime,
+}
+
+impl Liquid {
+    const ALL: [Liquid; 5] = [Liquid::Land, Liquid::Shallow, Liquid::Deep, Liquid::Lava, Liquid::Slime];
+
+    /// Digit written to the liquid masks
+    pub fn code(self) -> u8 {
+        match self {
+            Liquid::Land => 0,
+            Liquid::Shallow => 1,
+            Liquid::Deep => 2,
+            Liquid::Lava => 3,
+            Liquid::Slime => 4,
+        }
+    }
+
+    pub fn is_water(self) -> bool {
+        matches!(self, Liquid::Shallow | Liquid::Deep)
+    }
+
+    /// Kind of a LiquidType.db2 ID; IDs this table does not know count as water
+    fn from_liquid_type(id: u16) -> Self {
+        match id {
+            3 | 7 | 11 | 15 | 19 => Liquid::Lava,
+            4 | 8 | 12 | 20 | 21 => Liquid::Slime,
+            _ => Liquid::Shallow,
+        }
+    }
+}
+
+/// MCNK flags naming the MCLQ instances present, in the order they are stored
+const MCNK_LIQUID_FLAGS: [(u32, Liquid); 4] =
+    [(0x04, Liquid::Shallow), (0x08, Liquid::Shallow), (0x10, Liquid::Lava), (0x20, Liquid::Slime)];
+const MCNK_HEADER_LEN: usize = 128;
+/// Height range, 9x9 vertices, 8x8 cell flags, flow count and two flow vectors
+const MCLQ_INSTANCE_LEN: usize = 8 + 81 * 8 + 64 + 4 + 80;
+/// MCLQ cell flags: low nibble 0xF means no liquid in the cell
+const MCLQ_NO_LIQUID: u8 = 0x0F;
+const MCLQ_FATIGUE: u8 = 0x80;
+
+fn u16_at(data: &[u8], pos: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(pos..pos + 2)?.try_into().ok()?))
+}
+
+fn u32_at(data: &[u8], pos: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(pos..pos + 4)?.try_into().ok()?))
+}
+
+fn u64_at(data: &[u8], pos: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(data.get(pos..pos + 8)?.try_into().ok()?))
+}
+
+/// The liquid of every chunk in a root ADT, in MCNK order, padded with land to 256.
+/// MH2O wins over MCLQ for chunks that have both. Data cut short reads as land.
+pub fn parse_adt_liquids(data: &[u8]) -> Vec<Liquid> {
+    let mut mcnk_bodies = Vec::new();
+    let mut mh2o = None;
+    for (magic, body) in chunks(data) {
+        match magic {
+            b"KNCM" => mcnk_bodies.push(body),
+            b"O2HM" => mh2o = Some(body),
+            _ => {}
+        }
+    }
+
+    let mut cells: Vec<[Liquid; 64]> =
+        mcnk_bodies.iter().map(|body| mclq_cells(body).unwrap_or([Liquid::Land; 64])).collect();
+    cells.resize(256, [Liquid::Land; 64]);
+    if let Some(mh2o) = mh2o {
+        for (i, chunk_cells) in cells.iter_mut().enumerate() {
+            if let Some(layers) = mh2o_cells(mh2o, i) {
+                *chunk_cells = layers;
+            }
+        }
+    }
+    cells.iter().map(majority).collect()
+}
+
+/// The kind covering most of the chunk's 8x8 cells; ties go to the drier kind
+fn majority(cells: &[Liquid; 64]) -> Liquid {
+    let count = |kind: Liquid| cells.iter().filter(|&&c| c == kind).count();
+    let mut best = Liquid::Land;
+    for kind in Liquid::ALL {
+        if count(kind) > count(best) {
+            best = kind;
+        }
+    }
+    best
+}
+
+/// Pre-WotLK liquid: one MCLQ instance per liquid flag set in the MCNK header
+fn mclq_cells(mcnk: &[u8]) -> Option<[Liquid; 64]> {
+    let flags = u32_at(mcnk, 0)?;
+    // The offset counts from the start of the MCNK chunk, its 8-byte header included
+    let offset = u32_at(mcnk, 0x60)? as usize;
+    let size = u32_at(mcnk, 0x64)? as usize;
+    if offset < 8 + MCNK_HEADER_LEN || size <= 8 {
+        return None;
+    }
+    let mut pos = offset - 8;
+    if mcnk.get(pos..pos + 4)? == b"QLCM" {
+        pos += 8;
+    }
+
+    let mut cells = [Liquid::Land; 64];
+    for (flag, kind) in MCNK_LIQUID_FLAGS {
+        if flags & flag == 0 {
+            continue;
+        }
+        let tiles = mcnk.get(pos + 8 + 81 * 8..pos + 8 + 81 * 8 + 64)?;
+        for (cell, &tile) in cells.iter_mut().zip(tiles) {
+            if tile & MCLQ_NO_LIQUID == MCLQ_NO_LIQUID {
+                continue;
+            }
+            *cell = if kind == Liquid::Shallow && tile & MCLQ_FATIGUE != 0 { Liquid::Deep } else { kind };
+        }
+        pos += MCLQ_INSTANCE_LEN;
+    }
+    Some(cells)
+}
+
+/// WotLK+ liquid: chunk `index`'s MH2O layers, later layers on top. `None` without layers.
+fn mh2o_cells(mh2o: &[u8], index: usize) -> Option<[Liquid; 64]> {
+    let header = index * 12;
+    let instances = u32_at(mh2o, header)? as usize;
+    let layer_count = u32_at(mh2o, header + 4)? as usize;
+    let attributes = u32_at(mh2o, header + 8)? as usize;
+    if instances == 0 || layer_count == 0 {
+        return None;
+    }
+    let deep = if attributes != 0 { u64_at(mh2o, attributes + 8)? } else { 0 };
+
+    let mut cells = [Liquid::Land; 64];
+    for layer in 0..layer_count {
+        let at = instances + layer * 24;
+        let kind = Liquid::from_liquid_type(u16_at(mh2o, at)?);
+        let layer_bytes = mh2o.get(at + 12..at + 16)?;
+        let (x0, y0, width, height) =
+            (layer_bytes[0] as usize, layer_bytes[1] as usize, layer_bytes[2] as usize, layer_bytes[3] as usize);
+        let exists_offset = u32_at(mh2o, at + 16)? as usize;
+
+        for dy in 0..height {
+            for dx in 0..width {
+                let (x, y) = (x0 + dx, y0 + dy);
+                if x >= 8 || y >= 8 {
+                    continue;
+                }
+                // Without a bitmap every cell of the layer's rectangle has liquid
+                let bit = dy * width + dx;
+                if exists_offset != 0 && mh2o.get(exists_offset + bit / 8)? & (1 << (bit % 8)) == 0 {
+                    continue;
+                }
+                let cell = y * 8 + x;
+                cells[cell] = if kind == Liquid::Shallow && deep & (1 << cell) != 0 { Liquid::Deep } else { kind };
+            }
+        }
+    }
+    Some(cells)
+}
+
+/// Chunks with water, per area, against all of the area's chunks
+pub fn water_chunks(
+    tiles_raw: &HashMap<u32, Vec<u32>>,
+    liquids: &HashMap<u32, Vec<Liquid>>,
+) -> HashMap<u32, (u32, u32)> {
+    let mut counts: HashMap<u32, (u32, u32)> = HashMap::new();
+    for (key, area_ids) in tiles_raw {
+        let tile_liquids = liquids.get(key);
+        for (i, &area_id) in area_ids.iter().enumerate().filter(|(_, &id)| id != 0) {
+            let (water, total) = counts.entry(area_id).or_default();
+            *total += 1;
+            if tile_liquids.and_then(|l| l.get(i)).is_some_and(|l| l.is_water()) {
+                *water += 1;
+            }
+        }
+    }
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OCEAN: u32 = 0x08;
+    const MAGMA: u32 = 0x10;
+    /// MCLQ cell flag bits for liquid present (any low nibble but 0xF)
+    const WET: u8 = 0x04;
+
+    fn chunk(magic: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut out = magic.to_vec();
+        out.extend((body.len() as u32).to_le_bytes());
+        out.extend(body);
+        out
+    }
+
+    /// An MCNK body whose MCLQ holds one instance per entry, with these 8x8 cell flags
+    fn mcnk(flags: u32, instances: &[[u8; 64]]) -> Vec<u8> {
+        let mut body = vec![0u8; MCNK_HEADER_LEN];
+        body[..4].copy_from_slice(&flags.to_le_bytes());
+        if !instances.is_empty() {
+            let mut mclq = Vec::new();
+            for tiles in instances {
+                let mut instance = vec![0u8; MCLQ_INSTANCE_LEN];
+                instance[8 + 81 * 8..8 + 81 * 8 + 64].copy_from_slice(tiles);
+                mclq.extend(instance);
+            }
+            let mclq = chunk(b"QLCM", &mclq);
+            body[0x60..0x64].copy_from_slice(&(8 + MCNK_HEADER_LEN as u32).to_le_bytes());
+            body[0x64..0x68].copy_from_slice(&(mclq.len() as u32).to_le_bytes());
+            body.extend(mclq);
+        }
+        body
+    }
+
+    struct Layer {
+        liquid_type: u16,
+        rect: [u8; 4],
+        exists: Option<Vec<u8>>,
+    }
+
+    /// An MH2O body with layers (and an optional deep bitmap) for some chunks
+    fn mh2o(entries: &[(usize, Vec<Layer>, Option<u64>)]) -> Vec<u8> {
+        let mut body = vec![0u8; 256 * 12];
+        for (index, layers, deep) in entries {
+            let instances = body.len();
+            body.resize(instances + layers.len() * 24, 0);
+            for (i, layer) in layers.iter().enumerate() {
+                let at = instances + i * 24;
+                body[at..at + 2].copy_from_slice(&layer.liquid_type.to_le_bytes());
+                body[at + 12..at + 16].copy_from_slice(&layer.rect);
+                if let Some(bits) = &layer.exists {
+                    let offset = body.len() as u32;
+                    body.extend(bits);
+                    body[at + 16..at + 20].copy_from_slice(&offset.to_le_bytes());
+                }
+            }
+            let mut attributes = 0;
+            if let Some(deep) = deep {
+                attributes = body.len() as u32;
+                body.extend(0u64.to_le_bytes());
+                body.extend(deep.to_le_bytes());
+            }
+            let header = index * 12;
+            body[header..header + 4].copy_from_slice(&(instances as u32).to_le_bytes());
+            body[header + 4..header + 8].copy_from_slice(&(layers.len() as u32).to_le_bytes());
+            body[header + 8..header + 12].copy_from_slice(&attributes.to_le_bytes());
+        }
+        body
+    }
+
+    fn count(cells: &[Liquid; 64], kind: Liquid) -> usize {
+        cells.iter().filter(|&&c| c == kind).count()
+    }
+
+    #[test]
+    fn mclq_ocean_splits_on_fatigue() {
+        let mut tiles = [MCLQ_NO_LIQUID; 64];
+        tiles[..40].fill(WET | MCLQ_FATIGUE);
+        tiles[40..60].fill(WET);
+        let cells = mclq_cells(&mcnk(OCEAN, &[tiles])).unwrap();
+        assert_eq!(cells[0], Liquid::Deep);
+        assert_eq!(cells[40], Liquid::Shallow);
+        assert_eq!(cells[63], Liquid::Land);
+        assert_eq!((count(&cells, Liquid::Deep), count(&cells, Liquid::Shallow)), (40, 20));
+        assert_eq!(majority(&cells), Liquid::Deep);
+    }
+
+    #[test]
+    fn mclq_instances_follow_the_flag_order() {
+        // Ocean first, magma stored after it and drawn on top where it has cells
+        let ocean = [WET; 64];
+        let mut magma = [MCLQ_NO_LIQUID; 64];
+        magma[..10].fill(WET | MCLQ_FATIGUE);
+        let cells = mclq_cells(&mcnk(OCEAN | MAGMA, &[ocean, magma])).unwrap();
+        assert_eq!(count(&cells, Liquid::Lava), 10);
+        assert_eq!(count(&cells, Liquid::Shallow), 54);
+        // No liquid flags: nothing is read even if MCLQ is there
+        assert_eq!(mclq_cells(&mcnk(0, &[ocean])).unwrap(), [Liquid::Land; 64]);
+        assert_eq!(mclq_cells(&mcnk(0, &[])), None);
+    }
+
+    #[test]
+    fn mh2o_honours_exists_bitmap_and_deep_bits() {
+        // A 4x2 rectangle at (2, 3) with every other cell present, and the deep bit on (2, 3)
+        let layer = Layer { liquid_type: 2, rect: [2, 3, 4, 2], exists: Some(vec![0b0101_0101]) };
+        let data = mh2o(&[(5, vec![layer], Some(1 << (3 * 8 + 2)))]);
+        let cells = mh2o_cells(&data, 5).unwrap();
+        let wet: Vec<usize> = (0..64).filter(|&i| cells[i] != Liquid::Land).collect();
+        assert_eq!(wet, [3 * 8 + 2, 3 * 8 + 4, 4 * 8 + 2, 4 * 8 + 4]);
+        assert_eq!(cells[3 * 8 + 2], Liquid::Deep);
+        assert_eq!(cells[3 * 8 + 4], Liquid::Shallow);
+        assert_eq!(mh2o_cells(&data, 4), None);
+    }
+
+    #[test]
+    fn mh2o_layers_stack_and_clip() {
+        let water = Layer { liquid_type: 1, rect: [0, 0, 8, 8], exists: None };
+        // Slime past the chunk edge is clipped to the 8x8 cells
+        let slime = Layer { liquid_type: 4, rect: [6, 6, 4, 4], exists: None };
+        let data = mh2o(&[(0, vec![water, slime], None)]);
+        let cells = mh2o_cells(&data, 0).unwrap();
+        assert_eq!(count(&cells, Liquid::Slime), 4);
+        assert_eq!(count(&cells, Liquid::Shallow), 60);
+        assert_eq!(Liquid::from_liquid_type(3), Liquid::Lava);
+    }
+
+    #[test]
+    fn majority_ties_go_to_the_drier_kind() {
+        let mut cells = [Liquid::Land; 64];
+        cells[..32].fill(Liquid::Shallow);
+        assert_eq!(majority(&cells), Liquid::Land);
+        cells[32..].fill(Liquid::Deep);
+        assert_eq!(majority(&cells), Liquid::Shallow);
+        cells[..20].fill(Liquid::Lava);
+        cells[20..40].fill(Liquid::Slime);
+        cells[40..].fill(Liquid::Deep);
+        assert_eq!(majority(&cells), Liquid::Deep);
+    }
+
+    #[test]
+    fn adt_liquids_prefer_mh2o_and_pad_to_256() {
+        let deep = [WET | MCLQ_FATIGUE; 64];
+        let mut adt = chunk(b"KNCM", &mcnk(OCEAN, &[deep]));
+        adt.extend(chunk(b"KNCM", &mcnk(OCEAN, &[deep])));
+        let lava = Layer { liquid_type: 3, rect: [0, 0, 8, 8], exists: None };
+        adt.extend(chunk(b"O2HM", &mh2o(&[(1, vec![lava], None)])));
+
+        let liquids = parse_adt_liquids(&adt);
+        assert_eq!(liquids.len(), 256);
+        assert_eq!(&liquids[..3], [Liquid::Deep, Liquid::Lava, Liquid::Land]);
+        assert_eq!(parse_adt_liquids(&[]), vec![Liquid::Land; 256]);
+    }
+
+    #[test]
+    fn truncated_input_reads_as_land() {
+        let body = mcnk(OCEAN, &[[WET; 64]]);
+        for cut in [0, 3, 0x62, MCNK_HEADER_LEN + 8, MCNK_HEADER_LEN + 8 + 8 + 81 * 8 + 63] {
+            assert_eq!(mclq_cells(&body[..cut]), None, "MCLQ cut at {}", cut);
+        }
+        let layer = Layer { liquid_type: 2, rect: [0, 0, 8, 8], exists: Some(vec![0xFF; 8]) };
+        let data = mh2o(&[(0, vec![layer], Some(u64::MAX))]);
+        for cut in [0, 8, 256 * 12, 256 * 12 + 20, data.len() - 1] {
+            assert_eq!(mh2o_cells(&data[..cut], 0), None, "MH2O cut at {}", cut);
+        }
+
+        let mut adt = chunk(b"KNCM", &body);
+        adt.extend(chunk(b"O2HM", &data));
+        for cut in (0..adt.len()).step_by(29) {
+            assert_eq!(parse_adt_liquids(&adt[..cut]).len(), 256);
+        }
+    }
+}
@@ -1,730 +1,510 @@
-use wow_adt::Adt;
-
-use base64::{engine::general_purpose, Engine as _};
-
-use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
-use std::fs::{self, File};
-use std::io::{BufRead, BufReader, Cursor, Write};
-use std::path::{Path, PathBuf};
-
-// ============================================================================
-// Area Table parsing
-// ============================================================================
-
-#[derive(Debug, Clone)]
-struct AreaInfo {
-    id: u32,
-    name: String,
-    parent_id: u32,
-    exploration_level: i32,
+mod cli;
+
+use cli::{CascExtractArgs, Command, DiffArgs, GenerateArgs, LookupArgs, QueryArgs, RenderArgs};
+use zone_map::area::{db2_format, find_root_parent, parent_chain, parse_area_table};
+use zone_map::config::{self, Config, TileEncoding};
+use zone_map::dataset::{self, Dataset};
+use zone_map::generate::generate;
+use zone_map::grid::ChunkLocation;
+use zone_map::rules::AreaMarks;
+use zone_map::sources::{open_casc, plan_maps};
+use zone_map::validate::Validator;
+use zone_map::{diff, render, AreaInfo, World};
+
+use std::fs;
+use std::path::Path;
+use std::process::ExitCode;
+
+/// Use `--config` if given, else `zonemap.toml` in the working directory, else the built-in defaults.
+fn resolve_config(path: Option<&Path>) -> Result<Config, Box<dyn std::error::Error>> {
+    let default_path = Path::new(config::DEFAULT_CONFIG);
+    let path = match path {
+        Some(p) => p,
+        None if default_path.exists() => default_path,
+        None => return Ok(Config::default()),
+    };
+    let config = Config::load(path)
+        .map_err(|e| format!("failed to load config {}: {}", path.display(), e))?;
+    println!("Using config {}", path.display());
+    Ok(config)
 }
 
-fn parse_area_table(csv_path: &Path) -> Result<HashMap<u32, AreaInfo>, Box<dyn std::error::Error>> {
-    let file = File::open(csv_path)?;
-    let reader = BufReader::new(file);
-    let mut areas = HashMap::new();
-    
-    let mut lines = reader.lines();
-    let header = lines.next().ok_or("Empty CSV")??;
-    
-    let columns: Vec<&str> = header.split(',').collect();
-    let id_idx = columns.iter().position(|&c| c == "ID").ok_or("No ID column")?;
-    let name_idx = columns.iter().position(|&c| c == "AreaName_lang").ok_or("No AreaName_lang column")?;
-    let parent_idx = columns.iter().position(|&c| c == "ParentAreaID").ok_or("No ParentAreaID column")?;
-    let level_idx = columns.iter().position(|&c| c == "ExplorationLevel").ok_or("No ExplorationLevel column")?;
-    
-    for line in lines {
-        let line = line?;
-        let fields: Vec<&str> = parse_csv_line(&line);
-        
-        if fields.len() <= id_idx.max(name_idx).max(parent_idx).max(level_idx) {
-            continue;
+/// The config file with the command-line overrides applied
+fn generate_config(args: &GenerateArgs) -> Result<Config, Box<dyn std::error::Error>> {
+    let mut config = resolve_config(args.config.as_deref())?;
+    if let Some(p) = &args.area_table {
+        config.area_table = p.clone();
+    }
+    if let Some(p) = &args.map_to_area {
+        config.map_to_area = p.clone();
+    }
+    if let Some(p) = &args.rules {
+        config.rules = Some(p.clone());
+    }
+    if let Some(p) = &args.out_dir {
+        config.out_dir = p.clone();
+    }
+    if let Some(e) = args.tile_encoding {
+        config.tile_encoding = e;
+    }
+    if let Some(f) = args.format {
+        config.format = f;
+    }
+    if args.outlines {
+        config.outlines = true;
+    }
+    if args.borders {
+        config.borders = true;
+    }
+    if args.geojson {
+        config.geojson = true;
+    }
+    if let Some(c) = args.connectivity {
+        config.connectivity = c;
+    }
+    if let Some(p) = &args.fragment_report {
+        config.fragment_report = Some(p.clone());
+    }
+    if let Some(yards) = args.simplify {
+        config.outlines = true;
+        config.simplify = yards;
+    }
+    if args.adt_root.is_some() || args.mpq_dir.is_some() || args.casc_dir.is_some() {
+        // Configured maps now only describe how to export what discovery finds
+        config.adt_root = args.adt_root.clone();
+        config.mpq_dir = args.mpq_dir.clone();
+        config.casc_dir = args.casc_dir.clone();
+        for map in &mut config.maps {
+            map.adt_dir = None;
         }
-        
-        let id: u32 = match fields[id_idx].parse() {
-            Ok(v) => v,
-            Err(_) => continue,
-        };
-        
-        let name = fields[name_idx].trim_matches('"').to_string();
-        let parent_id: u32 = fields[parent_idx].parse().unwrap_or(0);
-        let exploration_level: i32 = fields[level_idx].parse().unwrap_or(0);
-        
-        areas.insert(id, AreaInfo { id, name, parent_id, exploration_level });
     }
-    
-    Ok(areas)
+    if let Some(p) = &args.casc_product {
+        config.casc_product = Some(p.clone());
+    }
+    if let Some(locale) = &args.casc_locale {
+        config.casc_locale = Some(locale.clone());
+    }
+    if let Some(p) = &args.listfile {
+        config.listfile = Some(p.clone());
+    }
+    if args.strict {
+        config.strict = true;
+    }
+    for (grid, dir) in &args.adt_dirs {
+        config.set_adt_dir(grid, dir.clone());
+    }
+    Ok(config)
 }
 
-fn parse_csv_line(line: &str) -> Vec<&str> {
-    let mut fields = Vec::new();
-    let mut start = 0;
-    let mut in_quotes = false;
-    let bytes = line.as_bytes();
-    
-    for i in 0..bytes.len() {
-        match bytes[i] {
-            b'"' => in_quotes = !in_quotes,
-            b',' if !in_quotes => {
-                fields.push(&line[start..i]);
-                start = i + 1;
+/// Plan and read every map, then print what was found on each
+fn load_world(config: &Config) -> Result<World, Box<dyn std::error::Error>> {
+    let plan = plan_maps(config)?;
+    let world = World::load(config, plan.maps)?;
+    print_world(&plan.log, &world, config);
+    Ok(world)
+}
+
+fn print_world(plan_log: &[String], world: &World, config: &Config) {
+    for line in plan_log {
+        println!("{}", line);
+    }
+    println!("Loaded {} areas from {}", world.areas.len(), config.area_table.display());
+
+    for grid in world.maps.values() {
+        let stats = grid.stats();
+        println!("\nRead {} from {} ADTs", grid.name(), grid.adt_prefix);
+        println!(
+            "  {} tiles, {} chunks ({} without area, {} under liquid), {} areas, {} neighbor pairs",
+            stats.tiles,
+            stats.chunks,
+            stats.unassigned_chunks,
+            stats.liquid_chunks,
+            stats.areas,
+            stats.neighbor_pairs
+        );
+        match &grid.tile_check {
+            Some(check) => {
+                println!(
+                    "  WDT lists {} tiles: {} missing, {} not in WDT, {} without chunks",
+                    check.listed,
+                    check.missing.len(),
+                    check.not_in_wdt.len(),
+                    check.without_chunks.len()
+                );
+                print_tile_list("Missing ADTs", &check.missing);
+                print_tile_list("ADTs not in WDT", &check.not_in_wdt);
+                print_tile_list("ADTs without chunks", &check.without_chunks);
             }
-            _ => {}
+            None => println!("  No {}.wdt found, skipping missing-tile check", grid.adt_prefix),
         }
     }
-    fields.push(&line[start..]);
-    fields
 }
 
-fn find_root_parent(area_id: u32, areas: &HashMap<u32, AreaInfo>) -> u32 {
-    let mut current = area_id;
-    let mut visited = BTreeSet::new();
-    
-    while let Some(area) = areas.get(&current) {
-        if area.parent_id == 0 || visited.contains(&current) {
-            return current;
-        }
-        visited.insert(current);
-        current = area.parent_id;
+fn print_tile_list(label: &str, keys: &[u32]) {
+    const SHOWN: usize = 16;
+    if keys.is_empty() {
+        return;
     }
-    area_id
+    let coords: Vec<String> = keys
+        .iter()
+        .take(SHOWN)
+        .map(|k| format!("{}_{}", k % 64, k / 64))
+        .collect();
+    let more = if keys.len() > SHOWN {
+        format!(" ... and {} more", keys.len() - SHOWN)
+    } else {
+        String::new()
+    };
+    println!("    {}: {}{}", label, coords.join(", "), more);
 }
 
-// ============================================================================
-// Map ID to Area ID mapping
-// ============================================================================
-
-#[derive(Debug)]
-struct MapToAreaEntry {
-    zone_name: String,
-    map_id: u32,
-    area_id: u32,
-}
+/// Build every map's tile grid and run the validation pass without writing anything
+fn run_validate(args: &GenerateArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let config = generate_config(args)?;
+    let world = load_world(&config)?;
 
-fn parse_map_to_area_csv(csv_path: &Path) -> Result<Vec<MapToAreaEntry>, Box<dyn std::error::Error>> {
-    let file = File::open(csv_path)?;
-    let reader = BufReader::new(file);
-    let mut entries = Vec::new();
-    
-    let mut lines = reader.lines();
-    let header = lines.next().ok_or("Empty CSV")??;
-    
-    // Parse header to find column indices
-    let columns: Vec<&str> = header.split(',').collect();
-    let zone_idx = columns.iter().position(|&c| c.trim() == "Zone").ok_or("No Zone column")?;
-    let map_id_idx = columns.iter().position(|&c| c.trim() == "mapId").ok_or("No mapId column")?;
-    let area_id_idx = columns.iter().position(|&c| c.trim() == "AreaId").ok_or("No AreaId column")?;
-    
-    for line in lines {
-        let line = line?;
-        let fields: Vec<&str> = parse_csv_line(&line);
-        
-        if fields.len() <= zone_idx.max(map_id_idx).max(area_id_idx) {
-            continue;
-        }
-        
-        let zone_name = fields[zone_idx].trim_matches('"').to_string();
-        let map_id: u32 = match fields[map_id_idx].trim().parse() {
-            Ok(v) => v,
-            Err(_) => continue,
-        };
-        let area_id: u32 = match fields[area_id_idx].trim().parse() {
-            Ok(v) => v,
-            Err(_) => continue,
-        };
-        
-        entries.push(MapToAreaEntry { zone_name, map_id, area_id });
+    let mut validator = Validator::default();
+    validator.check_area_table(&world.areas);
+    for grid in world.maps.values() {
+        validator.check_map(grid.name(), grid.config.map_id, &grid.tiles.tiles_raw, &world.areas);
     }
-    
-    Ok(entries)
-}
 
-fn export_map_to_area(entries: &[MapToAreaEntry], out_path: &Path) -> std::io::Result<()> {
-    let mut f = File::create(out_path)?;
-    
-    writeln!(f, "-- Auto-generated Map ID to Area ID mapping")?;
-    writeln!(f, "-- Maps WoW UI map IDs to parent area IDs")?;
-    writeln!(f)?;
-    writeln!(f, "local _, addon = ...")?;
-    writeln!(f)?;
-    writeln!(f, "addon.MapToArea = {{")?;
-    
-    for entry in entries {
-        let escaped_name = entry.zone_name.replace("\"", "\\\"");
-        writeln!(f, "  [{}] = {{ areaId = {}, name = \"{}\" }},", 
-            entry.map_id, entry.area_id, escaped_name)?;
-    }
-    
-    writeln!(f, "}}")?;
-    
-    // Also create reverse lookup (areaId -> mapId)
-    writeln!(f)?;
-    writeln!(f, "addon.AreaToMap = {{")?;
-    
-    for entry in entries {
-        writeln!(f, "  [{}] = {},", entry.area_id, entry.map_id)?;
+    println!("\nValidation:");
+    println!("{}", validator);
+    if !validator.errors.is_empty() {
+        return Err(format!("validation found {} errors", validator.errors.len()).into());
     }
-    
-    writeln!(f, "}}")?;
-    
     Ok(())
 }
 
-// ============================================================================
-// Neighbor detection and graph coloring
-// ============================================================================
-
-type NeighborGraph = HashMap<u32, HashSet<u32>>;
-
-/// Add a neighbor relationship (bidirectional)
-fn add_neighbor(graph: &mut NeighborGraph, a: u32, b: u32) {
-    if a != 0 && b != 0 && a != b {
-        graph.entry(a).or_default().insert(b);
-        graph.entry(b).or_default().insert(a);
+/// A generated Data directory, or a config whose ADTs and AreaTable are read
+fn load_dataset(path: &Path) -> Result<Dataset, Box<dyn std::error::Error>> {
+    if path.is_dir() {
+        println!("Reading generated data from {}", path.display());
+        return dataset::load_data_dir(path);
     }
+
+    println!("Reading inputs from {}", path.display());
+    let config = Config::load(path).map_err(|e| format!("failed to load {}: {}", path.display(), e))?;
+    Ok(Dataset::from_world(load_world(&config)?))
 }
 
-/// Find neighbors within a single tile (adjacent chunks with different area IDs)
-fn find_tile_neighbors(area_ids: &[u32], graph: &mut NeighborGraph) {
-    // area_ids is 256 elements, 16x16 grid
-    // Check horizontal neighbors (left-right)
-    for y in 0..16 {
-        for x in 0..15 {
-            let idx1 = y * 16 + x;
-            let idx2 = y * 16 + x + 1;
-            add_neighbor(graph, area_ids[idx1], area_ids[idx2]);
+fn run_diff(args: &DiffArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let old = load_dataset(&args.old)?;
+    let new = load_dataset(&args.new)?;
+
+    let report = diff::report(&old, &new);
+    match &args.report {
+        Some(path) => {
+            fs::write(path, &report).map_err(|e| format!("failed to write {}: {}", path.display(), e))?;
+            println!("Wrote: {}", path.display());
         }
+        None => print!("\n{}", report),
     }
-    
-    // Check vertical neighbors (up-down)
-    for y in 0..15 {
-        for x in 0..16 {
-            let idx1 = y * 16 + x;
-            let idx2 = (y + 1) * 16 + x;
-            add_neighbor(graph, area_ids[idx1], area_ids[idx2]);
+
+    if let Some(dir) = &args.mask_dir {
+        for path in diff::write_masks(&old, &new, dir)? {
+            println!("Wrote: {}", path);
         }
     }
+    Ok(())
 }
 
-/// Find neighbors between adjacent tiles
-fn find_inter_tile_neighbors(
-    tiles: &HashMap<u32, Vec<u32>>,
-    graph: &mut NeighborGraph,
-) {
-    for (&key, area_ids) in tiles {
-        let tile_x = key % 64;
-        let tile_y = key / 64;
-        
-        // Check right neighbor tile
-        if tile_x < 63 {
-            let right_key = tile_y * 64 + tile_x + 1;
-            if let Some(right_ids) = tiles.get(&right_key) {
-                // Compare rightmost column of current tile with leftmost column of right tile
-                for y in 0..16 {
-                    let idx_current = y * 16 + 15;  // Rightmost column
-                    let idx_right = y * 16 + 0;     // Leftmost column
-                    add_neighbor(graph, area_ids[idx_current], right_ids[idx_right]);
-                }
-            }
-        }
-        
-        // Check bottom neighbor tile
-        if tile_y < 63 {
-            let bottom_key = (tile_y + 1) * 64 + tile_x;
-            if let Some(bottom_ids) = tiles.get(&bottom_key) {
-                // Compare bottom row of current tile with top row of bottom tile
-                for x in 0..16 {
-                    let idx_current = 15 * 16 + x;  // Bottom row
-                    let idx_bottom = 0 * 16 + x;    // Top row
-                    add_neighbor(graph, area_ids[idx_current], bottom_ids[idx_bottom]);
+/// `--zone` as an area ID or name, resolved to its root zone
+fn resolve_zone(query: &str, dataset: &Dataset) -> Result<u32, Box<dyn std::error::Error>> {
+    let id = match query.parse::<u32>() {
+        Ok(id) if dataset.areas.contains_key(&id) => id,
+        Ok(id) => return Err(format!("area {} is not in the generated AreaInfo", id).into()),
+        Err(_) => {
+            let needle = query.to_lowercase();
+            let exact: Vec<u32> =
+                dataset.areas.iter().filter(|(_, a)| a.name.to_lowercase() == needle).map(|(&id, _)| id).collect();
+            let partial: Vec<u32> = dataset
+                .areas
+                .iter()
+                .filter(|(_, a)| a.name.to_lowercase().contains(&needle))
+                .map(|(&id, _)| id)
+                .collect();
+            match (exact.as_slice(), partial.as_slice()) {
+                ([id], _) | ([], [id]) => *id,
+                ([], []) => return Err(format!("no area matches '{}'", query).into()),
+                (_, candidates) => {
+                    let names: Vec<String> =
+                        candidates.iter().map(|id| format!("{} ({})", dataset.areas[id].name, id)).collect();
+                    return Err(format!("'{}' matches several areas: {}", query, names.join(", ")).into());
                 }
             }
         }
-    }
+    };
+    Ok(match dataset.areas[&id].root_parent_id {
+        0 => id,
+        root => root,
+    })
 }
 
-/// Generate distinct colors using graph coloring
-/// Returns a map of area_id -> (r, g, b)
-fn generate_colors_with_graph(
-    found_areas: &BTreeSet<u32>,
-    neighbors: &NeighborGraph,
-    areas: &HashMap<u32, AreaInfo>,
-) -> HashMap<u32, (f32, f32, f32)> {
-    let mut colors: HashMap<u32, (f32, f32, f32)> = HashMap::new();
-    
-    // Predefined palette of visually distinct colors
-    let palette: Vec<(f32, f32, f32)> = vec![
-        (0.90, 0.30, 0.30),  // Red
-        (0.30, 0.70, 0.30),  // Green
-        (0.30, 0.50, 0.90),  // Blue
-        (0.90, 0.80, 0.20),  // Yellow
-        (0.80, 0.40, 0.80),  // Purple
-        (0.20, 0.80, 0.80),  // Cyan
-        (0.95, 0.60, 0.30),  // Orange
-        (0.60, 0.80, 0.40),  // Lime
-        (0.80, 0.50, 0.60),  // Pink
-        (0.50, 0.70, 0.80),  // Sky blue
-        (0.70, 0.60, 0.40),  // Tan
-        (0.60, 0.40, 0.70),  // Violet
-        (0.40, 0.60, 0.50),  // Teal
-        (0.85, 0.70, 0.70),  // Light pink
-        (0.70, 0.85, 0.70),  // Light green
-        (0.70, 0.70, 0.85),  // Light blue
-    ];
-    
-    // Sort areas by number of neighbors (descending) for better coloring
-    let mut area_list: Vec<u32> = found_areas.iter().copied().filter(|&a| a != 0).collect();
-    area_list.sort_by_key(|&a| std::cmp::Reverse(neighbors.get(&a).map(|n| n.len()).unwrap_or(0)));
-    
-    for area_id in area_list {
-        // Find colors used by neighbors
-        let neighbor_colors: HashSet<usize> = neighbors
-            .get(&area_id)
-            .map(|ns| {
-                ns.iter()
-                    .filter_map(|&n| {
-                        colors.get(&n).and_then(|c| {
-                            palette.iter().position(|p| {
-                                (p.0 - c.0).abs() < 0.01 && 
-                                (p.1 - c.1).abs() < 0.01 && 
-                                (p.2 - c.2).abs() < 0.01
-                            })
-                        })
-                    })
-                    .collect()
-            })
-            .unwrap_or_default();
-        
-        // Also avoid parent color
-        let parent_id = areas.get(&area_id).map(|a| a.parent_id).unwrap_or(0);
-        let parent_color_idx: Option<usize> = colors.get(&parent_id).and_then(|c| {
-            palette.iter().position(|p| {
-                (p.0 - c.0).abs() < 0.01 && 
-                (p.1 - c.1).abs() < 0.01 && 
-                (p.2 - c.2).abs() < 0.01
-            })
-        });
-        
-        // Find first available color
-        let mut chosen_idx = 0;
-        for i in 0..palette.len() {
-            if !neighbor_colors.contains(&i) && parent_color_idx != Some(i) {
-                chosen_idx = i;
-                break;
-            }
-        }
-        
-        // If all colors used, generate a unique one based on area_id
-        let color = if chosen_idx < palette.len() && !neighbor_colors.contains(&chosen_idx) {
-            palette[chosen_idx]
-        } else {
-            // Fallback: generate unique color
-            let golden_ratio = 0.618033988749895_f64;
-            let hue = ((area_id as f64) * golden_ratio) % 1.0;
-            let s = 0.7_f64;
-            let v = 0.9_f64;
-            let c = v * s;
-            let x = c * (1.0 - ((hue * 6.0) % 2.0 - 1.0).abs());
-            let m = v - c;
-            
-            let (r, g, b) = match (hue * 6.0) as i32 {
-                0 => (c, x, 0.0),
-                1 => (x, c, 0.0),
-                2 => (0.0, c, x),
-                3 => (0.0, x, c),
-                4 => (x, 0.0, c),
-                _ => (c, 0.0, x),
-            };
-            ((r + m) as f32, (g + m) as f32, (b + m) as f32)
-        };
-        
-        colors.insert(area_id, color);
+fn run_render(args: &RenderArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let data = match &args.data {
+        Some(dir) => dir.clone(),
+        None => resolve_config(args.config.as_deref())?.out_dir,
+    };
+    if !data.is_dir() {
+        return Err(format!("{} is not a generated Data directory", data.display()).into());
     }
-    
-    colors
-}
-
-/// Export area info to Lua
-fn export_area_info(
-    found_areas: &BTreeSet<u32>,
-    areas: &HashMap<u32, AreaInfo>,
-    colors: &HashMap<u32, (f32, f32, f32)>,
-    neighbors: &NeighborGraph,
-    out_path: &Path,
-) -> std::io::Result<()> {
-    let mut f = File::create(out_path)?;
-    
-    writeln!(f, "-- Auto-generated Area Info")?;
-    writeln!(f, "-- Contains name, parent, level, color, and neighbors for each area")?;
-    writeln!(f)?;
-    writeln!(f, "local _, addon = ...")?;
-    writeln!(f)?;
-    writeln!(f, "addon.AreaInfo = {{")?;
-    
-    for &area_id in found_areas {
-        if area_id == 0 {
-            continue;
-        }
-        
-        let (name, parent_id, root_parent, level) = if let Some(area) = areas.get(&area_id) {
-            let root = find_root_parent(area_id, areas);
-            (area.name.clone(), area.parent_id, root, area.exploration_level)
-        } else {
-            (format!("Unknown_{}", area_id), 0, area_id, 0)
-        };
-        
-        let (r, g, b) = colors.get(&area_id).copied().unwrap_or((0.5, 0.5, 0.5));
-        let escaped_name = name.replace("\"", "\\\"");
-        
-        // Get neighbor count for info
-        let neighbor_count = neighbors.get(&area_id).map(|n| n.len()).unwrap_or(0);
-        
-        writeln!(f, "  [{}] = {{", area_id)?;
-        writeln!(f, "    name = \"{}\",", escaped_name)?;
-        writeln!(f, "    parentId = {},", parent_id)?;
-        writeln!(f, "    rootParentId = {},", root_parent)?;
-        writeln!(f, "    explorationLevel = {},", level)?;
-        writeln!(f, "    color = {{{:.3}, {:.3}, {:.3}}},", r, g, b)?;
-        writeln!(f, "    neighborCount = {},", neighbor_count)?;
-        writeln!(f, "  }},")?;
+    let dataset = load_dataset(&data)?;
+    let zone = args.zone.as_deref().map(|z| resolve_zone(z, &dataset)).transpose()?;
+    if let Some(root) = zone {
+        let name = dataset.areas.get(&root).map(|a| a.name.as_str()).unwrap_or("?");
+        println!("Rendering zone {} ({})", name, root);
     }
-    
-    writeln!(f, "}}")?;
-    Ok(())
-}
 
-fn export_area_hierarchy(
-    found_areas: &BTreeSet<u32>,
-    areas: &HashMap<u32, AreaInfo>,
-    out_path: &Path,
-) -> std::io::Result<()> {
-    // Group areas by root parent
-    let mut hierarchy: BTreeMap<u32, BTreeMap<u32, String>> = BTreeMap::new();
-    
-    for &area_id in found_areas {
-        if area_id == 0 {
+    let options = render::RenderOptions { px_per_chunk: args.scale, borders: args.borders, labels: args.labels, zone };
+    fs::create_dir_all(&args.out_dir)?;
+    let mut written = 0;
+    for (name, tiles) in &dataset.grids {
+        if args.grid.as_ref().is_some_and(|g| !g.eq_ignore_ascii_case(name)) {
             continue;
         }
-        
-        let root_parent = find_root_parent(area_id, areas);
-        let name = if let Some(area) = areas.get(&area_id) {
-            area.name.clone()
-        } else {
-            format!("Unknown_{}", area_id)
+        let extension = if args.svg { "svg" } else { "png" };
+        let path = match zone {
+            Some(root) => args.out_dir.join(format!("{}_{}.{}", name, root, extension)),
+            None => args.out_dir.join(format!("{}.{}", name, extension)),
         };
-        
-        hierarchy
-            .entry(root_parent)
-            .or_insert_with(BTreeMap::new)
-            .insert(area_id, name);
-    }
-    
-    let mut f = File::create(out_path)?;
-    
-    writeln!(f, "-- Auto-generated Area Hierarchy")?;
-    writeln!(f, "-- Groups areas by their root parent zone")?;
-    writeln!(f)?;
-    writeln!(f, "local _, addon = ...")?;
-    writeln!(f)?;
-    writeln!(f, "addon.AreaHierarchy = {{")?;
-    
-    for (root_id, children) in &hierarchy {
-        let root_name = if let Some(area) = areas.get(root_id) {
-            area.name.replace("\"", "\\\"")
+        if args.svg {
+            let Some(svg) = render::render_svg(tiles, &dataset.areas, &options) else {
+                continue;
+            };
+            fs::write(&path, svg).map_err(|e| format!("failed to write {}: {}", path.display(), e))?;
+            println!("Wrote: {}", path.display());
         } else {
-            format!("Unknown_{}", root_id)
-        };
-        
-        writeln!(f, "  [{}] = {{  -- {}", root_id, root_name)?;
-        writeln!(f, "    name = \"{}\",", root_name)?;
-        writeln!(f, "    children = {{")?;
-        
-        for (child_id, child_name) in children {
-            let escaped = child_name.replace("\"", "\\\"");
-            writeln!(f, "      [{}] = \"{}\",", child_id, escaped)?;
+            let Some(image) = render::render_grid(tiles, &dataset.areas, &options) else {
+                continue;
+            };
+            image.write(&path).map_err(|e| format!("failed to write {}: {}", path.display(), e))?;
+            println!("Wrote: {} ({}x{})", path.display(), image.width, image.height);
         }
-        
-        writeln!(f, "    }},")?;
-        writeln!(f, "  }},")?;
+        written += 1;
     }
-    
-    writeln!(f, "}}")?;
-    
-    println!("  {} root zones, {} total areas", hierarchy.len(), found_areas.len());
-    Ok(())
-}
 
-// ============================================================================
-// ADT / Tile parsing
-// ============================================================================
-
-fn parse_root_adt_filename(path: &Path) -> Option<(String, u32, u32)> {
-    if path.extension()?.to_str()?.to_ascii_lowercase() != "adt" {
-        return None;
-    }
-    let stem = path.file_stem()?.to_str()?.to_string();
-    let parts: Vec<&str> = stem.split('_').collect();
-    if parts.len() != 3 {
-        return None;
+    if written == 0 {
+        return Err(match (&args.grid, zone) {
+            (Some(grid), _) if !dataset.grids.keys().any(|g| g.eq_ignore_ascii_case(grid)) => {
+                format!("no grid named {} in {}", grid, data.display())
+            }
+            (_, Some(root)) => format!("zone {} has no chunks on the rendered grids", root),
+            _ => format!("nothing to render in {}", data.display()),
+        }
+        .into());
     }
-    let map = parts[0].to_string();
-    let x: u32 = parts[1].parse().ok()?;
-    let y: u32 = parts[2].parse().ok()?;
-    Some((map, x, y))
+    Ok(())
 }
 
-fn tile_key(tile_x: u32, tile_y: u32) -> u32 {
-    tile_y * 64 + tile_x
-}
+fn run_generate(args: &GenerateArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let config = generate_config(args)?;
+    let outputs = generate(&config)?;
 
-fn encode_tile_b64(area_ids_256: &[u32]) -> Result<String, Box<dyn std::error::Error>> {
-    if area_ids_256.len() != 256 {
-        return Err(format!("expected 256 area IDs, got {}", area_ids_256.len()).into());
+    if outputs.created_out_dir {
+        println!("Created {}/ directory", config.out_dir.display());
     }
-
-    let mut raw = Vec::with_capacity(256 * 4);
-    for &v in area_ids_256 {
-        raw.extend_from_slice(&v.to_le_bytes());
+    if let Some(path) = &config.rules {
+        println!("Loaded {} area rules from {}", outputs.rule_count, path.display());
     }
+    print_world(&outputs.plan_log, &outputs.world, &config);
 
-    Ok(general_purpose::STANDARD.encode(&raw))
-}
+    for map in &outputs.maps {
+        println!("\nExporting {} ({})", map.name, map.key);
+        if map.encoding != config.tile_encoding {
+            println!("  Area IDs above 65535 found, writing {} tiles instead", map.encoding.format_tag());
+        }
+        if let Some(dedup) = &map.dedup {
+            println!(
+                "  {} uniform tiles, {} tiles sharing {} blobs",
+                dedup.uniform, dedup.shared_tiles, dedup.shared_blobs
+            );
+        }
+        let counts = map.fragments;
+        println!(
+            "  {} fragments: {} exclaves, {} enclaves, {} single-chunk specks",
+            counts.fragments, counts.exclaves, counts.enclaves, counts.specks
+        );
+        if let Some(count) = map.outline_polygons {
+            println!("  {} outline polygons", count);
+        }
+        if let Some(count) = map.border_segments {
+            println!("  {} border segments", count);
+        }
+        if let Some((packed_len, raw_len)) = map.payload_len {
+            println!(
+                "  Tile data: {} bytes as {} vs {} bytes as {} ({:.0}% smaller)",
+                packed_len,
+                map.encoding.format_tag(),
+                raw_len,
+                TileEncoding::U32.format_tag(),
+                100.0 * (1.0 - packed_len as f64 / raw_len.max(1) as f64)
+            );
+        }
+    }
 
-fn parse_adt_areaids(path: &Path) -> Result<Option<Vec<u32>>, Box<dyn std::error::Error>> {
-    let data = fs::read(path)?;
-    let adt = Adt::from_reader(Cursor::new(data))?;
+    println!("\nValidation:");
+    println!("{}", outputs.validator);
 
-    let mut area_ids: Vec<u32> = adt
-        .mcnk_chunks
-        .iter()
-        .map(|chunk| chunk.area_id)
-        .collect();
-
-    if area_ids.is_empty() {
-        return Ok(None);
-    }
-    
-    if area_ids.len() != 256 {
-        area_ids.resize(256, 0);
+    println!("\nBuilding neighbor graph...");
+    println!("  Found {} areas with neighbor relationships", outputs.neighbor_areas);
+    if outputs.shared_areas > 0 {
+        println!("  {} areas have chunks on more than one map", outputs.shared_areas);
     }
 
-    Ok(Some(area_ids))
-}
+    println!("\nApplying area rules...");
+    let count = |f: fn(&AreaMarks) -> bool| outputs.marks.values().filter(|m| f(m)).count();
+    println!(
+        "  {} sea, {} hidden, {} merged into their parent",
+        count(|m| m.sea),
+        count(|m| m.hidden),
+        count(|m| m.merge_into_parent)
+    );
+    for rule in &outputs.unused_rules {
+        println!("  warning: {} matches no area", rule);
+    }
 
-struct TileGridExport {
-    continent_name: String,
-    tiles_b64: BTreeMap<u32, String>,
-    tiles_raw: HashMap<u32, Vec<u32>>,
-    found_areas: BTreeSet<u32>,
+    println!("\nGenerating area hierarchy...");
+    println!("  {} root zones, {} total areas", outputs.root_zones, outputs.found_areas.len());
+    println!("\nGenerating map to area mapping...");
+    println!("  Loaded {} map-to-area entries", outputs.map_to_area_entries);
+    println!("\nGenerating draw lists...");
+    println!(
+        "  {} UiMaps, {} rectangles covering {} chunks",
+        outputs.draw_lists.len(),
+        outputs.draw_lists.iter().map(|l| l.rect_count()).sum::<usize>(),
+        outputs.draw_lists.iter().map(|l| l.chunk_count()).sum::<usize>()
+    );
+
+    println!();
+    for path in &outputs.written {
+        println!("Wrote: {}", path.display());
+    }
+    println!("\nDone!");
+    Ok(())
 }
 
-impl TileGridExport {
-    fn new(continent_name: &str) -> Self {
-        Self {
-            continent_name: continent_name.to_string(),
-            tiles_b64: BTreeMap::new(),
-            tiles_raw: HashMap::new(),
-            found_areas: BTreeSet::new(),
+fn run_query(args: &QueryArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let area_table = match &args.area_table {
+        Some(p) => p.clone(),
+        None => resolve_config(args.config.as_deref())?.area_table,
+    };
+    let areas = parse_area_table(&area_table)
+        .map_err(|e| format!("failed to load area table {}: {}", area_table.display(), e))?;
+    
+    let mut matches: Vec<&AreaInfo> = match args.area.parse::<u32>() {
+        Ok(id) => areas.get(&id).into_iter().collect(),
+        Err(_) => {
+            let needle = args.area.to_lowercase();
+            areas.values().filter(|a| a.name.to_lowercase().contains(&needle)).collect()
         }
+    };
+    if matches.is_empty() {
+        return Err(format!("no area matches '{}'", args.area).into());
     }
-
-    fn export_lua(&self, out_path: &Path) -> std::io::Result<()> {
-        let mut f = File::create(out_path)?;
-
-        writeln!(f, "-- Auto-generated AreaID grid for {}", self.continent_name)?;
-        writeln!(f, "-- Each tile is 16x16 chunks (256 u32 AreaIDs), base64 encoded.")?;
-        writeln!(f)?;
-        writeln!(f, "local _, addon = ...")?;
-        writeln!(f)?;
-        writeln!(f, "local tiles = {{")?;
-
-        for (k, v) in &self.tiles_b64 {
-            writeln!(f, "  [{}] = [[{}]],", k, v)?;
-        }
-
-        writeln!(f, "}}")?;
-        writeln!(f)?;
-        writeln!(f, "addon:RegisterTileGrid(\"{}\", {{", self.continent_name)?;
-        writeln!(f, "  name = \"{}\",", self.continent_name)?;
-        writeln!(f, "  tileSize = 16,")?;
-        writeln!(f, "  tilesPerSide = 64,")?;
-        writeln!(f, "  tiles = tiles,")?;
-        writeln!(f, "}})")?;
-        Ok(())
+    matches.sort_by_key(|a| a.id);
+    
+    for area in matches {
+        println!("[{}] {}", area.id, area.name);
+        println!("  parent:            {}", area.parent_id);
+        println!("  root parent:       {}", find_root_parent(area.id, &areas));
+        println!("  exploration level: {}", area.exploration_level);
+        println!("  chain:             {}", parent_chain(area.id, &areas));
     }
+    Ok(())
 }
 
-fn build_tile_export(adt_dir: &Path, continent_name: &str) -> Result<TileGridExport, Box<dyn std::error::Error>> {
-    let mut export = TileGridExport::new(continent_name);
-
-    if !adt_dir.exists() {
-        return Err(format!("Directory not found: {}", adt_dir.display()).into());
+fn run_lookup(args: &LookupArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let mut config = resolve_config(args.config.as_deref())?;
+    if let Some(p) = &args.area_table {
+        config.area_table = p.clone();
     }
-
-    println!("Scanning: {}", adt_dir.display());
-
-    let mut parsed = 0usize;
-
-    for entry in fs::read_dir(adt_dir)? {
-        let entry = entry?;
-        let path = entry.path();
-        if !path.is_file() {
-            continue;
+    let location = ChunkLocation::from_world(args.x, args.y)
+        .ok_or_else(|| format!("({}, {}) is outside the 64x64-tile map", args.x, args.y))?;
+
+    let world = World::load_tile(&config, &args.map, &location)?;
+    let grid = world.grid(&args.map).ok_or("the looked-up map was not loaded")?;
+    let key = location.tile_key();
+
+    let (gx, gy) = location.global();
+    println!("\n{} ({:.1}, {:.1})", grid.name(), args.x, args.y);
+    println!("  tile:  {}_{} (key {})", location.tile_x, location.tile_y, key);
+    println!("  chunk: {}, {} (global {}, {})", location.chunk_x, location.chunk_y, gx, gy);
+    if !grid.tiles.tiles_raw.contains_key(&key) {
+        println!("  area:  none (no ADT for this tile)");
+        return Ok(());
+    }
+    match grid.chunk_grid().area_at_world(args.x, args.y).map_or(0, |(_, id)| id) {
+        0 => println!("  area:  0 (no area)"),
+        id => {
+            let name = world.areas.get(&id).map(|a| a.name.as_str()).unwrap_or("not in the AreaTable");
+            println!("  area:  {} {}", id, name);
+            println!("  chain: {}", parent_chain(id, &world.areas));
         }
+    }
+    Ok(())
+}
 
-        let Some((_, tx, ty)) = parse_root_adt_filename(&path) else {
-            continue;
-        };
-
-        match parse_adt_areaids(&path) {
-            Ok(Some(area_ids)) => {
-                for &aid in &area_ids {
-                    if aid != 0 {
-                        export.found_areas.insert(aid);
-                    }
-                }
-                
-                let b64 = encode_tile_b64(&area_ids)?;
-                let key = tile_key(tx, ty);
-                export.tiles_b64.insert(key, b64);
-                export.tiles_raw.insert(key, area_ids);
-                parsed += 1;
-            }
-            Ok(None) => {}
-            Err(e) => {
-                eprintln!("  ERROR parsing {}: {}", path.display(), e);
+fn run_casc_extract(args: &CascExtractArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let config = resolve_config(args.config.as_deref())?;
+    let dir = args.casc_dir.as_ref().or(config.casc_dir.as_ref()).ok_or("casc-extract needs --casc-dir")?;
+    let product = args.casc_product.as_deref().or(config.casc_product.as_deref());
+    let locale = args.casc_locale.as_deref().or(config.casc_locale.as_deref());
+    let listfile = args.listfile.as_deref().or(config.listfile.as_deref());
+    let storage = open_casc(dir, product, locale, listfile)?;
+
+    fs::create_dir_all(&args.out_dir)?;
+    for file in &args.files {
+        let (data, out_name) = match file.parse::<u32>() {
+            Ok(fdid) => (storage.read_fdid(fdid)?, format!("{}.bin", fdid)),
+            Err(_) => {
+                let base = file.rsplit(['/', '\\']).next().unwrap_or(file).to_string();
+                (storage.read_name(file)?, base)
             }
+        };
+        let out_path = args.out_dir.join(out_name);
+        fs::write(&out_path, &data).map_err(|e| format!("failed to write {}: {}", out_path.display(), e))?;
+        println!("  Wrote: {} ({} bytes)", out_path.display(), data.len());
+        if let Some(format) = db2_format(&data) {
+            println!("    {} table: convert it to CSV before using it as area_table or map_to_area", format);
         }
     }
-
-    println!("  Parsed {} tiles, found {} unique areas", parsed, export.found_areas.len());
-    Ok(export)
+    Ok(())
 }
 
-fn main() {
-    println!("ZoneMap Tile Generator\n");
-    
-    // Load area table
-    let csv_path = Path::new("AreaTable.1.15.8.64907.csv");
-    let areas = if csv_path.exists() {
-        match parse_area_table(csv_path) {
-            Ok(a) => {
-                println!("Loaded {} areas from CSV\n", a.len());
-                a
-            }
-            Err(e) => {
-                eprintln!("Warning: Failed to parse area table: {}", e);
-                HashMap::new()
-            }
+fn main() -> ExitCode {
+    let command = match cli::parse_args(std::env::args().skip(1)) {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("Error: {}\n\n{}", e, cli::USAGE);
+            return ExitCode::from(2);
         }
-    } else {
-        eprintln!("Warning: AreaTable CSV not found\n");
-        HashMap::new()
     };
     
-    // Create Data directory
-    let out_dir = Path::new("Data");
-    if !out_dir.exists() {
-        if let Err(e) = fs::create_dir(out_dir) {
-            eprintln!("Failed to create Data directory: {}", e);
-            return;
-        }
-        println!("Created Data/ directory");
-    }
-    
-    // Track all data across continents
-    let mut all_found_areas = BTreeSet::new();
-    let mut all_tiles_raw: HashMap<u32, Vec<u32>> = HashMap::new();
-    let mut neighbor_graph: NeighborGraph = HashMap::new();
-    
-    // Process Kalimdor
-    if let Ok(export) = build_tile_export(Path::new("kalimdor_adts"), "Kalimdor") {
-        all_found_areas.extend(&export.found_areas);
-        
-        // Find neighbors within tiles
-        for area_ids in export.tiles_raw.values() {
-            find_tile_neighbors(area_ids, &mut neighbor_graph);
-        }
-        
-        // Find neighbors between tiles
-        find_inter_tile_neighbors(&export.tiles_raw, &mut neighbor_graph);
-        
-        // Export before moving tiles_raw
-        let out_path = out_dir.join("Kalimdor_tiles.lua");
-        if let Err(e) = export.export_lua(&out_path) {
-            eprintln!("Failed to write: {}", e);
-        } else {
-            println!("  Wrote: {}", out_path.display());
-        }
-        
-        all_tiles_raw.extend(export.tiles_raw);
-    }
-    
-    // Process Azeroth
-    if let Ok(export) = build_tile_export(Path::new("azeroth_adts"), "Azeroth") {
-        all_found_areas.extend(&export.found_areas);
-        
-        for area_ids in export.tiles_raw.values() {
-            find_tile_neighbors(area_ids, &mut neighbor_graph);
+    let result = match command {
+        Command::Generate(args) => {
+            println!("ZoneMap Tile Generator\n");
+            run_generate(&args)
         }
-        find_inter_tile_neighbors(&export.tiles_raw, &mut neighbor_graph);
-        
-        // Export before moving tiles_raw
-        let out_path = out_dir.join("Azeroth_tiles.lua");
-        if let Err(e) = export.export_lua(&out_path) {
-            eprintln!("Failed to write: {}", e);
-        } else {
-            println!("  Wrote: {}", out_path.display());
+        Command::Validate(args) => run_validate(&args),
+        Command::Diff(args) => run_diff(&args),
+        Command::Render(args) => run_render(&args),
+        Command::Query(args) => run_query(&args),
+        Command::Lookup(args) => run_lookup(&args),
+        Command::CascExtract(args) => run_casc_extract(&args),
+        Command::Help => {
+            print!("{}", cli::USAGE);
+            Ok(())
         }
-        
-        all_tiles_raw.extend(export.tiles_raw);
-    }
-    
-    // Generate colors using neighbor graph
-    println!("\nBuilding neighbor graph...");
-    println!("  Found {} areas with neighbor relationships", neighbor_graph.len());
-    
-    let colors = generate_colors_with_graph(&all_found_areas, &neighbor_graph, &areas);
-    
-    // Export area info with graph-colored colors
-    println!("\nGenerating area info...");
-    let area_info_path = out_dir.join("AreaInfo.lua");
-    if let Err(e) = export_area_info(&all_found_areas, &areas, &colors, &neighbor_graph, &area_info_path) {
-        eprintln!("Failed to write area info: {}", e);
-    } else {
-        println!("  Wrote: {}", area_info_path.display());
-    }
-    
-    // Export area hierarchy grouped by root parent
-    println!("\nGenerating area hierarchy...");
-    let hierarchy_path = out_dir.join("AreaHierarchy.lua");
-    if let Err(e) = export_area_hierarchy(&all_found_areas, &areas, &hierarchy_path) {
-        eprintln!("Failed to write area hierarchy: {}", e);
-    } else {
-        println!("  Wrote: {}", hierarchy_path.display());
-    }
+    };
     
-    // Export map ID to area ID mapping
-    let map_csv_path = Path::new("mapIdToArea.csv");
-    if map_csv_path.exists() {
-        println!("\nGenerating map to area mapping...");
-        match parse_map_to_area_csv(map_csv_path) {
-            Ok(entries) => {
-                println!("  Loaded {} map-to-area entries", entries.len());
-                let map_path = out_dir.join("MapToArea.lua");
-                if let Err(e) = export_map_to_area(&entries, &map_path) {
-                    eprintln!("Failed to write map to area: {}", e);
-                } else {
-                    println!("  Wrote: {}", map_path.display());
-                }
-            }
-            Err(e) => {
-                eprintln!("Failed to parse mapIdToArea.csv: {}", e);
-            }
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
         }
-    } else {
-        println!("\nSkipping map-to-area (mapIdToArea.csv not found)");
     }
-    
-    println!("\nDone!");
 }
+
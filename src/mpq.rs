@@ -0,0 +1,892 @@
+// ============================================================================
+// MPQ archive reading (pre-CASC clients and private-server data)
+// ============================================================================
+
+use bzip2::read::BzDecoder;
+use flate2::read::ZlibDecoder;
+
+use std::cell::RefCell;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+const HEADER_MAGIC: &[u8; 4] = b"MPQ\x1A";
+const USER_DATA_MAGIC: &[u8; 4] = b"MPQ\x1B";
+
+const FILE_IMPLODE: u32 = 0x0000_0100;
+const FILE_COMPRESS: u32 = 0x0000_0200;
+const FILE_ENCRYPTED: u32 = 0x0001_0000;
+const FILE_FIX_KEY: u32 = 0x0002_0000;
+const FILE_SINGLE_UNIT: u32 = 0x0100_0000;
+const FILE_DELETE_MARKER: u32 = 0x0200_0000;
+const FILE_EXISTS: u32 = 0x8000_0000;
+
+const HASH_ENTRY_EMPTY: u32 = 0xFFFF_FFFF;
+const HASH_ENTRY_DELETED: u32 = 0xFFFF_FFFE;
+
+// Sector compression mask bits, undone in this order
+const COMPRESSION_BZIP2: u8 = 0x10;
+const COMPRESSION_IMPLODE: u8 = 0x08;
+const COMPRESSION_ZLIB: u8 = 0x02;
+
+// Hash types used by `hash_string`
+const HASH_TABLE_OFFSET: u32 = 0;
+const HASH_NAME_A: u32 = 1;
+const HASH_NAME_B: u32 = 2;
+const HASH_FILE_KEY: u32 = 3;
+
+fn crypt_table() -> &'static [u32; 0x500] {
+    static TABLE: OnceLock<[u32; 0x500]> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let mut table = [0u32; 0x500];
+        let mut seed: u32 = 0x0010_0001;
+        for index1 in 0..0x100 {
+            let mut index2 = index1;
+            for _ in 0..5 {
+                seed = (seed * 125 + 3) % 0x2A_AAAB;
+                let temp1 = (seed & 0xFFFF) << 0x10;
+                seed = (seed * 125 + 3) % 0x2A_AAAB;
+                let temp2 = seed & 0xFFFF;
+                table[index2] = temp1 | temp2;
+                index2 += 0x100;
+            }
+        }
+        table
+    })
+}
+
+/// The classic MPQ string hash. Names are case-insensitive and `/` is treated as `\`.
+fn hash_string(name: &str, hash_type: u32) -> u32 {
+    let table = crypt_table();
+    let mut seed1: u32 = 0x7FED_7FED;
+    let mut seed2: u32 = 0xEEEE_EEEE;
+    for &b in name.as_bytes() {
+        let ch = match b {
+            b'/' => b'\\',
+            _ => b.to_ascii_uppercase(),
+        } as u32;
+        seed1 = table[(hash_type * 0x100 + ch) as usize] ^ seed1.wrapping_add(seed2);
+        seed2 = ch
+            .wrapping_add(seed1)
+            .wrapping_add(seed2)
+            .wrapping_add(seed2 << 5)
+            .wrapping_add(3);
+    }
+    seed1
+}
+
+fn decrypt_block(data: &mut [u32], mut key: u32) {
+    let table = crypt_table();
+    let mut seed: u32 = 0xEEEE_EEEE;
+    for value in data.iter_mut() {
+        seed = seed.wrapping_add(table[(0x400 + (key & 0xFF)) as usize]);
+        let ch = *value ^ key.wrapping_add(seed);
+        key = ((!key << 0x15).wrapping_add(0x1111_1111)) | (key >> 0x0B);
+        seed = ch.wrapping_add(seed).wrapping_add(seed << 5).wrapping_add(3);
+        *value = ch;
+    }
+}
+
+fn decrypt_bytes(data: &mut [u8], key: u32) {
+    let mut words: Vec<u32> = data
+        .chunks_exact(4)
+        .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+        .collect();
+    decrypt_block(&mut words, key);
+    for (chunk, word) in data.chunks_exact_mut(4).zip(words) {
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+}
+
+fn read_u32s(bytes: &[u8]) -> Vec<u32> {
+    bytes
+        .chunks_exact(4)
+        .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy)]
+struct HashEntry {
+    name_a: u32,
+    name_b: u32,
+    locale: u16,
+    block_index: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct BlockEntry {
+    offset: u64,
+    packed_size: u32,
+    unpacked_size: u32,
+    flags: u32,
+}
+
+/// Outcome of looking a name up in one archive
+enum Lookup {
+    Found(BlockEntry),
+    /// A patch archive marked the file as removed
+    Deleted,
+    Missing,
+}
+
+/// Anything an archive can be read from: a file on disk, or bytes in memory
+trait ArchiveSource: Read + Seek {}
+
+impl<T: Read + Seek> ArchiveSource for T {}
+
+/// A single opened `.MPQ` file (format versions 0 and 1)
+pub struct MpqArchive {
+    path: PathBuf,
+    file: RefCell<Box<dyn ArchiveSource>>,
+    archive_offset: u64,
+    sector_size: usize,
+    hash_table: Vec<HashEntry>,
+    block_table: Vec<BlockEntry>,
+}
+
+impl MpqArchive {
+    pub fn open(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::from_reader(path, File::open(path)?)
+    }
+
+    /// Read an archive from any seekable source; `path` is only used in messages
+    fn from_reader(path: &Path, file: impl Read + Seek + 'static) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut file: Box<dyn ArchiveSource> = Box::new(file);
+        let len = file.seek(SeekFrom::End(0))?;
+
+        // The header sits on a 512-byte boundary, optionally behind a user-data block
+        let mut archive_offset = 0u64;
+        let header = loop {
+            if archive_offset + 32 > len {
+                return Err("no MPQ header found".into());
+            }
+            let mut head = [0u8; 44];
+            file.seek(SeekFrom::Start(archive_offset))?;
+            let n = file.read(&mut head)?;
+            if n >= 32 && &head[0..4] == HEADER_MAGIC {
+                break head;
+            }
+            if n >= 16 && &head[0..4] == USER_DATA_MAGIC {
+                archive_offset += u32::from_le_bytes([head[8], head[9], head[10], head[11]]) as u64;
+                continue;
+            }
+            archive_offset += 0x200;
+        };
+
+        let u16_at = |o: usize| u16::from_le_bytes([header[o], header[o + 1]]);
+        let u32_at = |o: usize| u32::from_le_bytes([header[o], header[o + 1], header[o + 2], header[o + 3]]);
+
+        let format_version = u16_at(0x0C);
+        if format_version > 1 {
+            return Err(format!("unsupported MPQ format version {}", format_version).into());
+        }
+        let sector_size = 512usize << u16_at(0x0E);
+        let mut hash_table_pos = u32_at(0x10) as u64;
+        let mut block_table_pos = u32_at(0x14) as u64;
+        let hash_table_size = u32_at(0x18) as usize;
+        let block_table_size = u32_at(0x1C) as usize;
+        if format_version == 1 {
+            hash_table_pos |= (u16_at(0x28) as u64) << 32;
+            block_table_pos |= (u16_at(0x2A) as u64) << 32;
+        }
+
+        let mut read_table = |pos: u64, entries: usize, key: &str| -> std::io::Result<Vec<u32>> {
+            let mut raw = vec![0u8; entries * 16];
+            file.seek(SeekFrom::Start(archive_offset + pos))?;
+            file.read_exact(&mut raw)?;
+            let mut words = read_u32s(&raw);
+            decrypt_block(&mut words, hash_string(key, HASH_FILE_KEY));
+            Ok(words)
+        };
+
+        let hash_words = read_table(hash_table_pos, hash_table_size, "(hash table)")?;
+        let hash_table = hash_words
+            .chunks_exact(4)
+            .map(|w| HashEntry {
+                name_a: w[0],
+                name_b: w[1],
+                locale: (w[2] & 0xFFFF) as u16,
+                block_index: w[3],
+            })
+            .collect();
+
+        let block_words = read_table(block_table_pos, block_table_size, "(block table)")?;
+        let block_table = block_words
+            .chunks_exact(4)
+            .map(|w| BlockEntry {
+                offset: archive_offset + w[0] as u64,
+                packed_size: w[1],
+                unpacked_size: w[2],
+                flags: w[3],
+            })
+            .collect();
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            file: RefCell::new(file),
+            archive_offset,
+            sector_size,
+            hash_table,
+            block_table,
+        })
+    }
+
+    fn lookup(&self, name: &str) -> Lookup {
+        if self.hash_table.is_empty() {
+            return Lookup::Missing;
+        }
+        let mask = self.hash_table.len() - 1;
+        let start = hash_string(name, HASH_TABLE_OFFSET) as usize & mask;
+        let name_a = hash_string(name, HASH_NAME_A);
+        let name_b = hash_string(name, HASH_NAME_B);
+
+        // Prefer the neutral locale, but take any locale if that is all there is
+        let mut fallback = None;
+        for i in 0..self.hash_table.len() {
+            let entry = self.hash_table[(start + i) & mask];
+            if entry.block_index == HASH_ENTRY_EMPTY {
+                break;
+            }
+            if entry.block_index == HASH_ENTRY_DELETED || entry.name_a != name_a || entry.name_b != name_b {
+                continue;
+            }
+            let Some(&block) = self.block_table.get(entry.block_index as usize) else {
+                continue;
+            };
+            if entry.locale == 0 {
+                fallback = Some(block);
+                break;
+            }
+            fallback.get_or_insert(block);
+        }
+
+        match fallback {
+            Some(block) if block.flags & FILE_DELETE_MARKER != 0 => Lookup::Deleted,
+            Some(block) if block.flags & FILE_EXISTS != 0 => Lookup::Found(block),
+            _ => Lookup::Missing,
+        }
+    }
+
+    /// Read and decompress a file by its full archive path, e.g. `World\Maps\Azeroth\Azeroth.wdt`.
+    pub fn read_file(&self, name: &str) -> Result<Option<Vec<u8>>, Box<dyn std::error::Error>> {
+        match self.lookup(name) {
+            Lookup::Found(block) => self.read_block(name, &block).map(Some),
+            Lookup::Deleted | Lookup::Missing => Ok(None),
+        }
+    }
+
+    fn read_block(&self, name: &str, block: &BlockEntry) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let mut packed = vec![0u8; block.packed_size as usize];
+        let mut file = self.file.borrow_mut();
+        file.seek(SeekFrom::Start(block.offset))?;
+        file.read_exact(&mut packed)?;
+        drop(file);
+
+        let key = if block.flags & FILE_ENCRYPTED != 0 {
+            let base = name.rsplit(['\\', '/']).next().unwrap_or(name);
+            let mut key = hash_string(base, HASH_FILE_KEY);
+            if block.flags & FILE_FIX_KEY != 0 {
+                key = key.wrapping_add((block.offset - self.archive_offset) as u32) ^ block.unpacked_size;
+            }
+            Some(key)
+        } else {
+            None
+        };
+
+        let unpacked_size = block.unpacked_size as usize;
+        // Older archives implode whole sectors instead of tagging each with a mask byte
+        let imploded = block.flags & FILE_IMPLODE != 0;
+        let compressed = imploded || block.flags & FILE_COMPRESS != 0;
+
+        if block.flags & FILE_SINGLE_UNIT != 0 {
+            if let Some(key) = key {
+                decrypt_bytes(&mut packed, key);
+            }
+            return if compressed && packed.len() < unpacked_size {
+                decompress_sector(name, &packed, unpacked_size, imploded)
+            } else {
+                Ok(packed)
+            };
+        }
+
+        // Sectored file: a table of sector offsets followed by the sectors themselves
+        let sector_count = unpacked_size.div_ceil(self.sector_size);
+        let offsets: Vec<usize> = if compressed {
+            let table_len = (sector_count + 1) * 4;
+            if packed.len() < table_len {
+                return Err(format!("{}: truncated sector table", name).into());
+            }
+            let mut table = packed[..table_len].to_vec();
+            if let Some(key) = key {
+                decrypt_bytes(&mut table, key.wrapping_sub(1));
+            }
+            read_u32s(&table).into_iter().map(|o| o as usize).collect()
+        } else {
+            (0..=sector_count)
+                .map(|i| (i * self.sector_size).min(unpacked_size))
+                .collect()
+        };
+
+        let mut out = Vec::with_capacity(unpacked_size);
+        for (i, pair) in offsets.windows(2).enumerate() {
+            let (start, end) = (pair[0], pair[1]);
+            if start > end || end > packed.len() {
+                return Err(format!("{}: sector {} out of bounds", name, i).into());
+            }
+            let mut sector = packed[start..end].to_vec();
+            if let Some(key) = key {
+                decrypt_bytes(&mut sector, key.wrapping_add(i as u32));
+            }
+
+            let expected = self.sector_size.min(unpacked_size - out.len());
+            if compressed && sector.len() < expected {
+                out.extend(decompress_sector(name, &sector, expected, imploded)?);
+            } else {
+                out.extend(sector);
+            }
+        }
+
+        Ok(out)
+    }
+
+    /// Names from the archive's `(listfile)`, if it has one.
+    pub fn list_files(&self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let Some(data) = self.read_file("(listfile)")? else {
+            return Ok(Vec::new());
+        };
+        Ok(String::from_utf8_lossy(&data)
+            .split(['\r', '\n', ';'])
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+}
+
+/// Undo a sector's compression. `FILE_COMPRESS` sectors start with a mask byte naming the
+/// methods applied; `FILE_IMPLODE` sectors are PKWARE-imploded with no mask.
+fn decompress_sector(
+    name: &str,
+    data: &[u8],
+    expected: usize,
+    imploded: bool,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    if imploded {
+        return explode(data).map_err(|e| format!("{}: {}", name, e).into());
+    }
+
+    let (&mask, payload) = data.split_first().ok_or_else(|| format!("{}: empty sector", name))?;
+    let supported = COMPRESSION_BZIP2 | COMPRESSION_IMPLODE | COMPRESSION_ZLIB;
+    if mask == 0 || mask & !supported != 0 {
+        return Err(format!(
+            "{}: unsupported MPQ compression 0x{:02X} (only zlib, PKWARE implode and bzip2 are read)",
+            name, mask
+        )
+        .into());
+    }
+
+    let mut out = payload.to_vec();
+    if mask & COMPRESSION_BZIP2 != 0 {
+        let mut next = Vec::with_capacity(expected);
+        BzDecoder::new(out.as_slice()).read_to_end(&mut next).map_err(|e| format!("{}: bzip2: {}", name, e))?;
+        out = next;
+    }
+    if mask & COMPRESSION_IMPLODE != 0 {
+        out = explode(&out).map_err(|e| format!("{}: {}", name, e))?;
+    }
+    if mask & COMPRESSION_ZLIB != 0 {
+        let mut next = Vec::with_capacity(expected);
+        ZlibDecoder::new(out.as_slice()).read_to_end(&mut next).map_err(|e| format!("{}: zlib: {}", name, e))?;
+        out = next;
+    }
+    Ok(out)
+}
+
+// ============================================================================
+// PKWARE Data Compression Library "explode", after zlib's contrib/blast
+// ============================================================================
+
+const EXPLODE_MAX_BITS: usize = 13;
+
+/// Code lengths as (repeat - 1) << 4 | length bytes
+const LITERAL_LENGTHS: [u8; 98] = [
+    11, 124, 8, 7, 28, 7, 188, 13, 76, 4, 10, 8, 12, 10, 12, 10, 8, 23, 8, 9, 7, 6, 7, 8, 7, 6, 55, 8, 23, 24,
+    12, 11, 7, 9, 11, 12, 6, 7, 22, 5, 7, 24, 6, 11, 9, 6, 7, 22, 7, 11, 38, 7, 9, 8, 25, 11, 8, 11, 9, 12, 8,
+    12, 5, 38, 5, 38, 5, 11, 7, 5, 6, 21, 6, 10, 53, 8, 7, 24, 10, 27, 44, 253, 253, 253, 252, 252, 252, 13,
+    12, 45, 12, 45, 12, 61, 12, 45, 44, 173,
+];
+const LENGTH_LENGTHS: [u8; 6] = [2, 35, 36, 53, 38, 23];
+const DISTANCE_LENGTHS: [u8; 7] = [2, 20, 53, 230, 247, 151, 248];
+const LENGTH_BASE: [usize; 16] = [3, 2, 4, 5, 6, 7, 8, 9, 10, 12, 16, 24, 40, 72, 136, 264];
+const LENGTH_EXTRA: [u32; 16] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 2, 3, 4, 5, 6, 7, 8];
+const END_OF_STREAM: usize = 519;
+
+/// Canonical Huffman code: how many codes have each length, and the symbols in code order
+struct Huffman {
+    count: [u16; EXPLODE_MAX_BITS + 1],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(packed_lengths: &[u8]) -> Self {
+        let lengths: Vec<usize> = packed_lengths
+            .iter()
+            .flat_map(|&b| std::iter::repeat_n((b & 15) as usize, (b >> 4) as usize + 1))
+            .collect();
+
+        let mut count = [0u16; EXPLODE_MAX_BITS + 1];
+        for &len in &lengths {
+            count[len] += 1;
+        }
+        let mut offsets = [0usize; EXPLODE_MAX_BITS + 1];
+        for len in 1..EXPLODE_MAX_BITS {
+            offsets[len + 1] = offsets[len] + count[len] as usize;
+        }
+        let mut symbols = vec![0u16; lengths.len()];
+        for (symbol, &len) in lengths.iter().enumerate().filter(|(_, &len)| len != 0) {
+            symbols[offsets[len]] = symbol as u16;
+            offsets[len] += 1;
+        }
+        Self { count, symbols }
+    }
+}
+
+/// Least-significant-bit-first reader over the imploded stream
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    buf: u32,
+    len: u32,
+}
+
+impl BitReader<'_> {
+    fn bits(&mut self, need: u32) -> Result<u32, String> {
+        while self.len < need {
+            let byte = *self.data.get(self.pos).ok_or("imploded data ends early")?;
+            self.buf |= (byte as u32) << self.len;
+            self.pos += 1;
+            self.len += 8;
+        }
+        let value = self.buf & ((1u32 << need) - 1);
+        self.buf >>= need;
+        self.len -= need;
+        Ok(value)
+    }
+
+    /// Codes are stored bit-reversed and inverted, so build them one bit at a time
+    fn decode(&mut self, code: &Huffman) -> Result<usize, String> {
+        let (mut value, mut first, mut index) = (0usize, 0usize, 0usize);
+        for len in 1..=EXPLODE_MAX_BITS {
+            value |= (self.bits(1)? ^ 1) as usize;
+            let count = code.count[len] as usize;
+            if value < first + count {
+                return Ok(code.symbols[index + value - first] as usize);
+            }
+            index += count;
+            first = (first + count) << 1;
+            value <<= 1;
+        }
+        Err("invalid code in imploded data".to_string())
+    }
+}
+
+/// Decompress one PKWARE-imploded buffer
+fn explode(data: &[u8]) -> Result<Vec<u8>, String> {
+    static CODES: OnceLock<(Huffman, Huffman, Huffman)> = OnceLock::new();
+    let (literals, lengths, distances) = CODES.get_or_init(|| {
+        (Huffman::new(&LITERAL_LENGTHS), Huffman::new(&LENGTH_LENGTHS), Huffman::new(&DISTANCE_LENGTHS))
+    });
+
+    let mut reader = BitReader { data, pos: 0, buf: 0, len: 0 };
+    let coded_literals = match reader.bits(8)? {
+        0 => false,
+        1 => true,
+        other => return Err(format!("bad implode literal mode {}", other)),
+    };
+    let dict_bits = reader.bits(8)?;
+    if !(4..=6).contains(&dict_bits) {
+        return Err(format!("bad implode dictionary size {}", dict_bits));
+    }
+
+    let mut out = Vec::new();
+    loop {
+        if reader.bits(1)? == 0 {
+            let literal = if coded_literals { reader.decode(literals)? as u8 } else { reader.bits(8)? as u8 };
+            out.push(literal);
+            continue;
+        }
+
+        let symbol = reader.decode(lengths)?;
+        let len = LENGTH_BASE[symbol] + reader.bits(LENGTH_EXTRA[symbol])? as usize;
+        if len == END_OF_STREAM {
+            return Ok(out);
+        }
+        let low_bits = if len == 2 { 2 } else { dict_bits };
+        let dist = ((reader.decode(distances)? << low_bits) | reader.bits(low_bits)? as usize) + 1;
+        if dist > out.len() {
+            return Err("imploded data refers back past its start".to_string());
+        }
+        // Overlapping copies repeat the bytes just written
+        let start = out.len() - dist;
+        for i in 0..len {
+            out.push(out[start + i]);
+        }
+    }
+}
+
+/// Load order of an archive: base archives first, then `patch.MPQ`, `patch-2.MPQ`, ...
+/// `patch-9.MPQ`, then lettered patches (`patch-A.MPQ`, ...) as private servers ship them.
+fn archive_priority(path: &Path) -> (u32, String) {
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+
+    let rank = match stem.strip_prefix("patch") {
+        None => 0,
+        Some("") => 1,
+        Some(suffix) => match suffix.strip_prefix('-') {
+            Some(n) if n.chars().all(|c| c.is_ascii_digit()) => 1 + n.parse::<u32>().unwrap_or(0),
+            Some(n) if n.len() == 1 && n.as_bytes()[0].is_ascii_alphabetic() => {
+                100 + (n.as_bytes()[0] - b'a') as u32
+            }
+            _ => 1,
+        },
+    };
+    (rank, stem)
+}
+
+/// All archives of a client `Data` directory, searched highest priority first.
+pub struct MpqSet {
+    /// Lowest priority first
+    archives: Vec<MpqArchive>,
+}
+
+impl MpqSet {
+    pub fn open_dir(dir: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        if !dir.is_dir() {
+            return Err(format!("Directory not found: {}", dir.display()).into());
+        }
+
+        let mut paths: Vec<PathBuf> = fs::read_dir(dir)?
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| {
+                p.is_file()
+                    && p.extension()
+                        .and_then(|e| e.to_str())
+                        .is_some_and(|e| e.eq_ignore_ascii_case("mpq"))
+            })
+            .collect();
+        paths.sort_by_key(|p| archive_priority(p));
+
+        let mut archives = Vec::with_capacity(paths.len());
+        for path in paths {
+            let archive = MpqArchive::open(&path)
+                .map_err(|e| format!("failed to open {}: {}", path.display(), e))?;
+            archives.push(archive);
+        }
+        if archives.is_empty() {
+            return Err(format!("no .MPQ files in {}", dir.display()).into());
+        }
+
+        Ok(Self { archives })
+    }
+
+    pub fn archives(&self) -> &[MpqArchive] {
+        &self.archives
+    }
+
+    /// Read a file from the highest-priority archive that has it. A delete marker in a
+    /// patch hides the file in every archive below it.
+    pub fn read_file(&self, name: &str) -> Result<Option<Vec<u8>>, Box<dyn std::error::Error>> {
+        for archive in self.archives.iter().rev() {
+            match archive.lookup(name) {
+                Lookup::Found(block) => {
+                    return archive
+                        .read_block(name, &block)
+                        .map(Some)
+                        .map_err(|e| format!("{}: {}", archive.path.display(), e).into());
+                }
+                Lookup::Deleted => return Ok(None),
+                Lookup::Missing => {}
+            }
+        }
+        Ok(None)
+    }
+
+    /// Whether the highest-priority archive mentioning `name` still has it.
+    pub fn contains(&self, name: &str) -> bool {
+        self.archives.iter().rev().find_map(|a| match a.lookup(name) {
+            Lookup::Found(_) => Some(true),
+            Lookup::Deleted => Some(false),
+            Lookup::Missing => None,
+        }) == Some(true)
+    }
+
+    /// Every listfile name across all archives that still resolves to a file.
+    pub fn list_files(&self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let mut seen = std::collections::BTreeMap::new();
+        for archive in &self.archives {
+            for name in archive.list_files()? {
+                seen.entry(name.to_ascii_lowercase()).or_insert(name);
+            }
+        }
+        Ok(seen.into_values().filter(|name| self.contains(name)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Write};
+
+    /// Inverse of `decrypt_block`
+    fn encrypt_block(data: &mut [u32], mut key: u32) {
+        let table = crypt_table();
+        let mut seed: u32 = 0xEEEE_EEEE;
+        for value in data.iter_mut() {
+            seed = seed.wrapping_add(table[(0x400 + (key & 0xFF)) as usize]);
+            let plain = *value;
+            *value = plain ^ key.wrapping_add(seed);
+            key = ((!key << 0x15).wrapping_add(0x1111_1111)) | (key >> 0x0B);
+            seed = plain.wrapping_add(seed).wrapping_add(seed << 5).wrapping_add(3);
+        }
+    }
+
+    fn encrypt_bytes(data: &mut [u8], key: u32) {
+        let mut words = read_u32s(data);
+        encrypt_block(&mut words, key);
+        for (chunk, word) in data.chunks_exact_mut(4).zip(words) {
+            chunk.copy_from_slice(&word.to_le_bytes());
+        }
+    }
+
+    fn zlib(data: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn bzip2(data: &[u8]) -> Vec<u8> {
+        let mut encoder = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    /// zlib's contrib/blast test file, with uncoded literals: "AIAIAIAIAIAIA"
+    const IMPLODED_BLAST: [u8; 8] = [0x00, 0x04, 0x82, 0x24, 0x25, 0x8f, 0x80, 0x7f];
+    /// Coded literals and one overlapping copy: "hello, hello, hello world"
+    const IMPLODED_CODED: [u8; 16] =
+        [0x01, 0x04, 0x14, 0xdb, 0x34, 0x75, 0x7c, 0x1f, 0xb3, 0x79, 0x78, 0x5d, 0x33, 0xe9, 0x01, 0xff];
+
+    /// 512-byte sectors behind an offset table, each sector packed as `mask` + `pack(raw)`
+    /// when that is smaller, as MPQ writers do
+    fn sectored(content: &[u8], mask: u8, pack: fn(&[u8]) -> Vec<u8>, key: Option<u32>) -> Vec<u8> {
+        let sectors: Vec<Vec<u8>> = content
+            .chunks(512)
+            .map(|raw| {
+                let mut packed = vec![mask];
+                packed.extend(pack(raw));
+                if packed.len() < raw.len() { packed } else { raw.to_vec() }
+            })
+            .collect();
+        let mut offsets = vec![(sectors.len() as u32 + 1) * 4];
+        for sector in &sectors {
+            offsets.push(offsets[offsets.len() - 1] + sector.len() as u32);
+        }
+
+        let mut out: Vec<u8> = offsets.iter().flat_map(|o| o.to_le_bytes()).collect();
+        if let Some(key) = key {
+            encrypt_bytes(&mut out, key.wrapping_sub(1));
+        }
+        for (i, mut sector) in sectors.into_iter().enumerate() {
+            if let Some(key) = key {
+                encrypt_bytes(&mut sector, key.wrapping_add(i as u32));
+            }
+            out.extend(sector);
+        }
+        out
+    }
+
+    struct Entry {
+        name: &'static str,
+        locale: u16,
+        flags: u32,
+        stored: Vec<u8>,
+        unpacked_size: u32,
+    }
+
+    impl Entry {
+        fn new(name: &'static str, flags: u32, stored: Vec<u8>, unpacked_size: usize) -> Self {
+            Self { name, locale: 0, flags, stored, unpacked_size: unpacked_size as u32 }
+        }
+    }
+
+    /// A format 0 archive with 512-byte sectors and a 16-slot hash table. File data
+    /// starts right after the 32-byte header, so the first entry sits at offset 32.
+    fn archive(entries: &[Entry]) -> MpqArchive {
+        const HASH_SLOTS: usize = 16;
+        let mut data: Vec<u8> = Vec::new();
+        let mut blocks = Vec::new();
+        for entry in entries {
+            blocks.push([32 + data.len() as u32, entry.stored.len() as u32, entry.unpacked_size, entry.flags]);
+            data.extend(&entry.stored);
+        }
+
+        let mut hash = vec![[HASH_ENTRY_EMPTY; 4]; HASH_SLOTS];
+        for (i, entry) in entries.iter().enumerate() {
+            let mut slot = hash_string(entry.name, HASH_TABLE_OFFSET) as usize % HASH_SLOTS;
+            while hash[slot][3] != HASH_ENTRY_EMPTY {
+                slot = (slot + 1) % HASH_SLOTS;
+            }
+            hash[slot] = [
+                hash_string(entry.name, HASH_NAME_A),
+                hash_string(entry.name, HASH_NAME_B),
+                entry.locale as u32,
+                i as u32,
+            ];
+        }
+        let mut hash_words: Vec<u32> = hash.concat();
+        encrypt_block(&mut hash_words, hash_string("(hash table)", HASH_FILE_KEY));
+        let mut block_words: Vec<u32> = blocks.concat();
+        encrypt_block(&mut block_words, hash_string("(block table)", HASH_FILE_KEY));
+
+        let hash_pos = 32 + data.len() as u32;
+        let block_pos = hash_pos + HASH_SLOTS as u32 * 16;
+        let mut bytes = HEADER_MAGIC.to_vec();
+        bytes.extend(32u32.to_le_bytes());
+        bytes.extend((block_pos + blocks.len() as u32 * 16).to_le_bytes());
+        bytes.extend(0u16.to_le_bytes());
+        bytes.extend(0u16.to_le_bytes());
+        for word in [hash_pos, block_pos, HASH_SLOTS as u32, blocks.len() as u32] {
+            bytes.extend(word.to_le_bytes());
+        }
+        bytes.extend(data);
+        for word in hash_words.iter().chain(&block_words) {
+            bytes.extend(word.to_le_bytes());
+        }
+        MpqArchive::from_reader(Path::new("test.mpq"), Cursor::new(bytes)).unwrap()
+    }
+
+    /// Compressible for two sectors, then noise that stays raw
+    fn sample(len: usize) -> Vec<u8> {
+        let mut state = 1u32;
+        (0..len)
+            .map(|i| {
+                if i < 1024 {
+                    (i * 7 % 13) as u8
+                } else {
+                    state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                    (state >> 16) as u8
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn crypt_table_matches_storm() {
+        let table = crypt_table();
+        assert_eq!(table[0], 0x55C6_36E2);
+        assert_eq!(table[1], 0x02BE_0170);
+        assert_eq!(table[0x4FF], 0x7303_286C);
+    }
+
+    #[test]
+    fn hash_string_known_values() {
+        assert_eq!(hash_string("(hash table)", HASH_FILE_KEY), 0xC3AF_3770);
+        assert_eq!(hash_string("(block table)", HASH_FILE_KEY), 0xEC83_B3A3);
+        assert_eq!(hash_string("World\\Maps\\Azeroth\\Azeroth.wdt", HASH_NAME_A), 0xAEBD_30DD);
+        assert_eq!(hash_string("world/maps/azeroth/azeroth.wdt", HASH_NAME_A), 0xAEBD_30DD);
+    }
+
+    #[test]
+    fn decrypt_block_known_values() {
+        let mut words = [0x9408_99B4, 0x7A01_7D50, 0x46B0_4E22, 0x59F8_EEC8];
+        decrypt_block(&mut words, 0xC3AF_3770);
+        assert_eq!(words, [0x1234_5678, 0, 0xFFFF_FFFF, 0xDEAD_BEEF]);
+
+        // A trailing partial word is stored in the clear
+        let mut bytes = vec![0xB4, 0x99, 0x08, 0x94, 0xAA, 0xBB];
+        decrypt_bytes(&mut bytes, 0xC3AF_3770);
+        assert_eq!(bytes, [0x78, 0x56, 0x34, 0x12, 0xAA, 0xBB]);
+    }
+
+    #[test]
+    fn lookup_prefers_neutral_locale() {
+        let mut german = Entry::new("Both.txt", FILE_EXISTS | FILE_SINGLE_UNIT, b"deDE".to_vec(), 4);
+        german.locale = 0x407;
+        let neutral = Entry::new("Both.txt", FILE_EXISTS | FILE_SINGLE_UNIT, b"neutral".to_vec(), 7);
+        let mut only_french = Entry::new("French.txt", FILE_EXISTS | FILE_SINGLE_UNIT, b"frFR".to_vec(), 4);
+        only_french.locale = 0x40C;
+        let deleted = Entry::new("Gone.txt", FILE_DELETE_MARKER | FILE_EXISTS, Vec::new(), 0);
+        let mpq = archive(&[german, neutral, only_french, deleted]);
+
+        assert_eq!(mpq.read_file("Both.txt").unwrap().as_deref(), Some(&b"neutral"[..]));
+        assert_eq!(mpq.read_file("french.TXT").unwrap().as_deref(), Some(&b"frFR"[..]));
+        assert!(matches!(mpq.lookup("Gone.txt"), Lookup::Deleted));
+        assert!(matches!(mpq.lookup("Missing.txt"), Lookup::Missing));
+    }
+
+    #[test]
+    fn archive_priority_orders_patches() {
+        let mut names = vec![
+            "patch-A.MPQ", "patch-10.mpq", "patch.MPQ", "dbc.MPQ", "patch-2.MPQ", "terrain.MPQ", "patch-3.MPQ",
+        ];
+        names.sort_by_key(|name| archive_priority(Path::new(name)));
+        assert_eq!(
+            names,
+            ["dbc.MPQ", "terrain.MPQ", "patch.MPQ", "patch-2.MPQ", "patch-3.MPQ", "patch-10.mpq", "patch-A.MPQ"]
+        );
+    }
+
+    #[test]
+    fn read_block_sectored_and_single_unit() {
+        let content = sample(1300);
+        let name = "World\\Maps\\Test\\Test_1_2.adt";
+        // FILE_FIX_KEY mixes in the block's offset, 32 for the first entry
+        let key = hash_string("Test_1_2.adt", HASH_FILE_KEY).wrapping_add(32) ^ content.len() as u32;
+        let flags = FILE_EXISTS | FILE_COMPRESS;
+        let mut single = vec![COMPRESSION_ZLIB];
+        single.extend(zlib(&content));
+        let mpq = archive(&[
+            Entry::new(name, flags | FILE_ENCRYPTED | FILE_FIX_KEY, sectored(&content, COMPRESSION_ZLIB, zlib, Some(key)), 1300),
+            Entry::new("zlib", flags, sectored(&content, COMPRESSION_ZLIB, zlib, None), 1300),
+            Entry::new("bzip2", flags, sectored(&content, COMPRESSION_BZIP2, bzip2, None), 1300),
+            Entry::new("plain", FILE_EXISTS, content.clone(), 1300),
+            Entry::new("single", flags | FILE_SINGLE_UNIT, single, 1300),
+        ]);
+
+        for file in [name, "zlib", "bzip2", "plain", "single"] {
+            assert_eq!(mpq.read_file(file).unwrap().as_ref(), Some(&content), "{}", file);
+        }
+    }
+
+    #[test]
+    fn read_block_imploded() {
+        let mut masked = vec![COMPRESSION_IMPLODE];
+        masked.extend(IMPLODED_CODED);
+        let mpq = archive(&[
+            Entry::new("masked", FILE_EXISTS | FILE_COMPRESS | FILE_SINGLE_UNIT, masked, 25),
+            Entry::new("whole", FILE_EXISTS | FILE_IMPLODE | FILE_SINGLE_UNIT, IMPLODED_BLAST.to_vec(), 13),
+        ]);
+        assert_eq!(mpq.read_file("masked").unwrap().unwrap(), b"hello, hello, hello world");
+        assert_eq!(mpq.read_file("whole").unwrap().unwrap(), b"AIAIAIAIAIAIA");
+    }
+
+    #[test]
+    fn read_block_rejects_unknown_compression() {
+        let huffman = vec![0x01, 0xAA, 0xBB];
+        let mpq = archive(&[Entry::new("wave", FILE_EXISTS | FILE_COMPRESS | FILE_SINGLE_UNIT, huffman, 100)]);
+        let err = mpq.read_file("wave").unwrap_err().to_string();
+        assert!(err.contains("unsupported MPQ compression 0x01"), "{}", err);
+    }
+
+    #[test]
+    fn explode_rejects_bad_streams() {
+        assert!(explode(&[0x02, 0x04]).is_err());
+        assert!(explode(&[0x00, 0x07]).is_err());
+        assert!(explode(&IMPLODED_BLAST[..5]).is_err());
+    }
+}
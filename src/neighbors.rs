@@ -0,0 +1,177 @@
+// ============================================================================
+// Neighbor detection and graph coloring
+// ============================================================================
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use crate::area::AreaTable;
+
+pub type NeighborGraph = HashMap<u32, HashSet<u32>>;
+
+/// Add a neighbor relationship (bidirectional)
+fn add_neighbor(graph: &mut NeighborGraph, a: u32, b: u32) {
+    if a != 0 && b != 0 && a != b {
+        graph.entry(a).or_default().insert(b);
+        graph.entry(b).or_default().insert(a);
+    }
+}
+
+/// Find neighbors within a single tile (adjacent chunks with different area IDs)
+pub fn find_tile_neighbors(area_ids: &[u32], graph: &mut NeighborGraph) {
+    // area_ids is 256 elements, 16x16 grid
+    // Check horizontal neighbors (left-right)
+    for y in 0..16 {
+        for x in 0..15 {
+            let idx1 = y * 16 + x;
+            let idx2 = y * 16 + x + 1;
+            add_neighbor(graph, area_ids[idx1], area_ids[idx2]);
+        }
+    }
+    
+    // Check vertical neighbors (up-down)
+    for y in 0..15 {
+        for x in 0..16 {
+            let idx1 = y * 16 + x;
+            let idx2 = (y + 1) * 16 + x;
+            add_neighbor(graph, area_ids[idx1], area_ids[idx2]);
+        }
+    }
+}
+
+/// Find neighbors between adjacent tiles
+pub fn find_inter_tile_neighbors(
+    tiles: &HashMap<u32, Vec<u32>>,
+    graph: &mut NeighborGraph,
+) {
+    for (&key, area_ids) in tiles {
+        let tile_x = key % 64;
+        let tile_y = key / 64;
+        
+        // Check right neighbor tile
+        if tile_x < 63 {
+            let right_key = tile_y * 64 + tile_x + 1;
+            if let Some(right_ids) = tiles.get(&right_key) {
+                // Compare rightmost column of current tile with leftmost column of right tile
+                for y in 0..16 {
+                    let idx_current = y * 16 + 15;  // Rightmost column
+                    let idx_right = y * 16;          // Leftmost column
+                    add_neighbor(graph, area_ids[idx_current], right_ids[idx_right]);
+                }
+            }
+        }
+        
+        // Check bottom neighbor tile
+        if tile_y < 63 {
+            let bottom_key = (tile_y + 1) * 64 + tile_x;
+            if let Some(bottom_ids) = tiles.get(&bottom_key) {
+                // Compare bottom row of current tile with top row of bottom tile
+                for x in 0..16 {
+                    let idx_current = 15 * 16 + x;  // Bottom row
+                    let idx_bottom = x;              // Top row
+                    add_neighbor(graph, area_ids[idx_current], bottom_ids[idx_bottom]);
+                }
+            }
+        }
+    }
+}
+
+/// Generate distinct colors using graph coloring
+/// Returns a map of area_id -> (r, g, b)
+pub fn generate_colors_with_graph(
+    found_areas: &BTreeSet<u32>,
+    neighbors: &NeighborGraph,
+    areas: &AreaTable,
+) -> HashMap<u32, (f32, f32, f32)> {
+    let mut colors: HashMap<u32, (f32, f32, f32)> = HashMap::new();
+    
+    // Predefined palette of visually distinct colors
+    let palette: Vec<(f32, f32, f32)> = vec![
+        (0.90, 0.30, 0.30),  // Red
+        (0.30, 0.70, 0.30),  // Green
+        (0.30, 0.50, 0.90),  // Blue
+        (0.90, 0.80, 0.20),  // Yellow
+        (0.80, 0.40, 0.80),  // Purple
+        (0.20, 0.80, 0.80),  // Cyan
+        (0.95, 0.60, 0.30),  // Orange
+        (0.60, 0.80, 0.40),  // Lime
+        (0.80, 0.50, 0.60),  // Pink
+        (0.50, 0.70, 0.80),  // Sky blue
+        (0.70, 0.60, 0.40),  // Tan
+        (0.60, 0.40, 0.70),  // Violet
+        (0.40, 0.60, 0.50),  // Teal
+        (0.85, 0.70, 0.70),  // Light pink
+        (0.70, 0.85, 0.70),  // Light green
+        (0.70, 0.70, 0.85),  // Light blue
+    ];
+    
+    // Sort areas by number of neighbors (descending) for better coloring
+    let mut area_list: Vec<u32> = found_areas.iter().copied().filter(|&a| a != 0).collect();
+    area_list.sort_by_key(|&a| std::cmp::Reverse(neighbors.get(&a).map(|n| n.len()).unwrap_or(0)));
+    
+    for area_id in area_list {
+        // Find colors used by neighbors
+        let neighbor_colors: HashSet<usize> = neighbors
+            .get(&area_id)
+            .map(|ns| {
+                ns.iter()
+                    .filter_map(|&n| {
+                        colors.get(&n).and_then(|c| {
+                            palette.iter().position(|p| {
+                                (p.0 - c.0).abs() < 0.01 && 
+                                (p.1 - c.1).abs() < 0.01 && 
+                                (p.2 - c.2).abs() < 0.01
+                            })
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        
+        // Also avoid parent color
+        let parent_id = areas.get(&area_id).map(|a| a.parent_id).unwrap_or(0);
+        let parent_color_idx: Option<usize> = colors.get(&parent_id).and_then(|c| {
+            palette.iter().position(|p| {
+                (p.0 - c.0).abs() < 0.01 && 
+                (p.1 - c.1).abs() < 0.01 && 
+                (p.2 - c.2).abs() < 0.01
+            })
+        });
+        
+        // Find first available color
+        let mut chosen_idx = 0;
+        for i in 0..palette.len() {
+            if !neighbor_colors.contains(&i) && parent_color_idx != Some(i) {
+                chosen_idx = i;
+                break;
+            }
+        }
+        
+        // If all colors used, generate a unique one based on area_id
+        let color = if chosen_idx < palette.len() && !neighbor_colors.contains(&chosen_idx) {
+            palette[chosen_idx]
+        } else {
+            // Fallback: generate unique color
+            let golden_ratio = 0.618033988749895_f64;
+            let hue = ((area_id as f64) * golden_ratio) % 1.0;
+            let s = 0.7_f64;
+            let v = 0.9_f64;
+            let c = v * s;
+            let x = c * (1.0 - ((hue * 6.0) % 2.0 - 1.0).abs());
+            let m = v - c;
+            
+            let (r, g, b) = match (hue * 6.0) as i32 {
+                0 => (c, x, 0.0),
+                1 => (x, c, 0.0),
+                2 => (0.0, c, x),
+                3 => (0.0, x, c),
+                4 => (x, 0.0, c),
+                _ => (c, 0.0, x),
+            };
+            ((r + m) as f32, (g + m) as f32, (b + m) as f32)
+        };
+        
+        colors.insert(area_id, color);
+    }
+    
+    colors
+}
@@ -0,0 +1,552 @@
+// ============================================================================
+// Area outlines: chunk regions traced into polygons with holes
+// ============================================================================
+
+use std::collections::{BTreeMap, BinaryHeap, HashMap};
+
+use crate::grid::{grid_to_world, ChunkGrid, CHUNK_SIZE};
+
+type Point = (i32, i32);
+
+/// A closed ring of world `(x, y)` points; the last point is not repeated
+pub type Ring = Vec<(f64, f64)>;
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Polygon {
+    pub outer: Ring,
+    pub holes: Vec<Ring>,
+}
+
+/// Trace every area's chunks into polygons. Chunks that only touch diagonally end up in
+/// separate polygons. `simplify` is the Douglas-Peucker tolerance in yards (0 keeps every
+/// corner of the staircase). Simplification runs once per border chain between junctions,
+/// so two areas still share the same simplified edge; a ring that would collapse below
+/// three points stays unsimplified.
+pub fn trace_areas(grid: &ChunkGrid, simplify: f64) -> BTreeMap<u32, Vec<Polygon>> {
+    // Directed boundary edges with the area on the left
+    let mut edges: HashMap<u32, Vec<(Point, Point)>> = HashMap::new();
+    for (x, y, area) in grid.chunks() {
+        let list = edges.entry(area).or_default();
+        if grid.area_at(x, y - 1) != area {
+            list.push(((x, y), (x + 1, y)));
+        }
+        if grid.area_at(x + 1, y) != area {
+            list.push(((x + 1, y), (x + 1, y + 1)));
+        }
+        if grid.area_at(x, y + 1) != area {
+            list.push(((x + 1, y + 1), (x, y + 1)));
+        }
+        if grid.area_at(x - 1, y) != area {
+            list.push(((x, y + 1), (x, y)));
+        }
+    }
+
+    let tolerance = simplify / CHUNK_SIZE;
+    edges
+        .into_iter()
+        .map(|(area, edges)| {
+            let polygons = assemble_polygons(&edges)
+                .into_iter()
+                .map(|(outer, holes)| Polygon {
+                    outer: to_world(&outer, tolerance, grid),
+                    holes: holes.iter().map(|h| to_world(h, tolerance, grid)).collect(),
+                })
+                .collect();
+            (area, polygons)
+        })
+        .collect()
+}
+
+/// Link edges into rings, then hand each hole to the smallest outer ring around it.
+fn assemble_polygons(edges: &[(Point, Point)]) -> Vec<(Vec<Point>, Vec<Vec<Point>>)> {
+    let mut outgoing: HashMap<Point, Vec<usize>> = HashMap::new();
+    for (i, (from, _)) in edges.iter().enumerate() {
+        outgoing.entry(*from).or_default().push(i);
+    }
+
+    let mut used = vec![false; edges.len()];
+    let mut outers: Vec<(Vec<Point>, i64)> = Vec::new();
+    let mut holes: Vec<(Vec<Point>, (f64, f64))> = Vec::new();
+
+    for start in 0..edges.len() {
+        if used[start] {
+            continue;
+        }
+        let mut ring = Vec::new();
+        let mut current = start;
+        while !used[current] {
+            used[current] = true;
+            let (from, to) = edges[current];
+            ring.push(from);
+            current = next_edge(edges, &outgoing[&to], (to.0 - from.0, to.1 - from.1));
+        }
+
+        let area2 = twice_area(&ring);
+        if area2 > 0 {
+            outers.push((ring, area2));
+        } else {
+            // Center of the chunk on the area's side of the hole's first edge
+            let (from, to) = edges[start];
+            let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+            let inside = (
+                (from.0 + to.0) as f64 / 2.0 - dy as f64 / 2.0,
+                (from.1 + to.1) as f64 / 2.0 + dx as f64 / 2.0,
+            );
+            holes.push((ring, inside));
+        }
+    }
+
+    let mut polygons: Vec<(Vec<Point>, Vec<Vec<Point>>)> =
+        outers.iter().map(|(ring, _)| (ring.clone(), Vec::new())).collect();
+    for (hole, inside) in holes {
+        let owner = outers
+            .iter()
+            .enumerate()
+            .filter(|(_, (ring, _))| contains(ring, inside))
+            .min_by_key(|(_, (_, area2))| *area2)
+            .map(|(i, _)| i);
+        if let Some(i) = owner {
+            polygons[i].1.push(hole);
+        }
+    }
+    polygons
+}
+
+/// At a vertex shared by two rings (chunks touching at a corner), turning left keeps
+/// each ring around its own chunk.
+fn next_edge(edges: &[(Point, Point)], candidates: &[usize], (dx, dy): Point) -> usize {
+    *candidates
+        .iter()
+        .min_by_key(|&&i| {
+            let (from, to) = edges[i];
+            let (ox, oy) = (to.0 - from.0, to.1 - from.1);
+            match dx * oy - dy * ox {
+                c if c > 0 => 0,
+                0 => 1,
+                _ => 2,
+            }
+        })
+        .expect("boundary edges always continue")
+}
+
+fn twice_area(ring: &[Point]) -> i64 {
+    (0..ring.len())
+        .map(|i| {
+            let (a, b) = (ring[i], ring[(i + 1) % ring.len()]);
+            a.0 as i64 * b.1 as i64 - b.0 as i64 * a.1 as i64
+        })
+        .sum()
+}
+
+/// Ray cast; `p` is a chunk center, so it never lies on a lattice edge.
+fn contains(ring: &[Point], p: (f64, f64)) -> bool {
+    let mut inside = false;
+    for i in 0..ring.len() {
+        let (a, b) = (ring[i], ring[(i + 1) % ring.len()]);
+        let (ay, by) = (a.1 as f64, b.1 as f64);
+        if (ay > p.1) != (by > p.1) {
+            let x = a.0 as f64 + (p.1 - ay) / (by - ay) * (b.0 - a.0) as f64;
+            if p.0 < x {
+                inside = !inside;
+            }
+        }
+    }
+    inside
+}
+
+fn to_world(ring: &[Point], tolerance: f64, grid: &ChunkGrid) -> Ring {
+    let points = if tolerance > 0.0 {
+        let simplified = simplify_chains(ring, tolerance, grid);
+        if simplified.len() >= 3 { simplified } else { drop_collinear(ring) }
+    } else {
+        drop_collinear(ring)
+    };
+    points.into_iter().map(|(x, y)| grid_to_world(x as f64, y as f64)).collect()
+}
+
+/// Whether the lattice point `(x, y)` ends a border chain: three or more areas (0
+/// included) meet there, or two meet diagonally. Anywhere else the same two areas are on
+/// either side of the ring, so both of their rings pass through in opposite directions.
+fn is_junction(grid: &ChunkGrid, (x, y): Point) -> bool {
+    let (nw, ne, sw, se) =
+        (grid.area_at(x - 1, y - 1), grid.area_at(x, y - 1), grid.area_at(x - 1, y), grid.area_at(x, y));
+    let mut distinct = vec![nw, ne, sw, se];
+    distinct.sort_unstable();
+    distinct.dedup();
+    distinct.len() > 2 || (nw == se && ne == sw && nw != ne)
+}
+
+/// Split a ring at its junctions and simplify every chain between two of them on its
+/// own, in a direction that does not depend on which side's ring it came from. A ring
+/// without junctions (an island inside one other area) is simplified whole, from its
+/// lowest point and counterclockwise.
+fn simplify_chains(ring: &[Point], tolerance: f64, grid: &ChunkGrid) -> Vec<Point> {
+    let junctions: Vec<usize> = (0..ring.len()).filter(|&i| is_junction(grid, ring[i])).collect();
+    if junctions.is_empty() {
+        let start = (0..ring.len()).min_by_key(|&i| ring[i]).unwrap_or(0);
+        let mut points: Vec<Point> = ring[start..].iter().chain(&ring[..start]).copied().collect();
+        let clockwise = twice_area(&points) < 0;
+        if clockwise {
+            points[1..].reverse();
+        }
+        let mut simplified = simplify_ring(&to_f64(&points), tolerance);
+        if clockwise {
+            simplified[1..].reverse();
+        }
+        return simplified.into_iter().map(to_point).collect();
+    }
+
+    let mut out = Vec::new();
+    for (n, &from) in junctions.iter().enumerate() {
+        let to = junctions.get(n + 1).copied().unwrap_or(junctions[0] + ring.len());
+        let chain: Vec<Point> = (from..=to).map(|i| ring[i % ring.len()]).collect();
+        let last = chain.len() - 1;
+        let reversed = chain[last] < chain[0] || (chain[last] == chain[0] && chain[last - 1] < chain[1]);
+        let mut points = to_f64(&chain);
+        if reversed {
+            points.reverse();
+        }
+        let mut simplified = douglas_peucker(&points, tolerance);
+        if reversed {
+            simplified.reverse();
+        }
+        // The next chain starts where this one ends
+        simplified.pop();
+        out.extend(simplified.into_iter().map(to_point));
+    }
+    out
+}
+
+fn to_f64(points: &[Point]) -> Vec<(f64, f64)> {
+    points.iter().map(|&(x, y)| (x as f64, y as f64)).collect()
+}
+
+/// Douglas-Peucker only ever keeps input points, which are all on the lattice
+fn to_point((x, y): (f64, f64)) -> Point {
+    (x as i32, y as i32)
+}
+
+/// Keep only the corners of a lattice ring
+fn drop_collinear(ring: &[Point]) -> Vec<Point> {
+    let n = ring.len();
+    (0..n)
+        .filter(|&i| {
+            let (prev, p, next) = (ring[(i + n - 1) % n], ring[i], ring[(i + 1) % n]);
+            (p.0 - prev.0) * (next.1 - p.1) != (p.1 - prev.1) * (next.0 - p.0)
+        })
+        .map(|i| ring[i])
+        .collect()
+}
+
+/// Douglas-Peucker on a closed ring, split at the point farthest from the first one
+fn simplify_ring(ring: &[(f64, f64)], tolerance: f64) -> Vec<(f64, f64)> {
+    if ring.len() < 4 {
+        return ring.to_vec();
+    }
+    let far = (1..ring.len())
+        .max_by(|&a, &b| dist2(ring[0], ring[a]).total_cmp(&dist2(ring[0], ring[b])))
+        .unwrap_or(1);
+
+    let mut first = ring[..=far].to_vec();
+    let mut second = ring[far..].to_vec();
+    second.push(ring[0]);
+    first = douglas_peucker(&first, tolerance);
+    second = douglas_peucker(&second, tolerance);
+
+    // Both halves keep their endpoints; drop the duplicates
+    first.pop();
+    second.pop();
+    first.extend(second);
+    first
+}
+
+fn douglas_peucker(points: &[(f64, f64)], tolerance: f64) -> Vec<(f64, f64)> {
+    let (a, b) = (points[0], points[points.len() - 1]);
+    let farthest = (1..points.len() - 1)
+        .map(|i| (i, segment_distance(points[i], a, b)))
+        .max_by(|x, y| x.1.total_cmp(&y.1));
+
+    match farthest {
+        Some((i, d)) if d > tolerance => {
+            let mut left = douglas_peucker(&points[..=i], tolerance);
+            left.pop();
+            left.extend(douglas_peucker(&points[i..], tolerance));
+            left
+        }
+        _ => vec![a, b],
+    }
+}
+
+fn dist2(a: (f64, f64), b: (f64, f64)) -> f64 {
+    (a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)
+}
+
+fn segment_distance(p: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    let len2 = dist2(a, b);
+    if len2 == 0.0 {
+        return dist2(p, a).sqrt();
+    }
+    let t = (((p.0 - a.0) * (b.0 - a.0) + (p.1 - a.1) * (b.1 - a.1)) / len2).clamp(0.0, 1.0);
+    dist2(p, (a.0 + t * (b.0 - a.0), a.1 + t * (b.1 - a.1))).sqrt()
+}
+
+/// Area of a polygon (outer ring minus holes), in square yards
+pub fn polygon_area(polygon: &Polygon) -> f64 {
+    let ring_area = |ring: &Ring| {
+        (0..ring.len())
+            .map(|i| {
+                let (a, b) = (ring[i], ring[(i + 1) % ring.len()]);
+                a.0 * b.1 - b.0 * a.1
+            })
+            .sum::<f64>()
+            .abs()
+            / 2.0
+    };
+    ring_area(&polygon.outer) - polygon.holes.iter().map(ring_area).sum::<f64>()
+}
+
+/// Label point of one connected fragment given as global chunk coordinates: the pole of
+/// inaccessibility of its traced outline. Fragments joined only at corners trace into
+/// several polygons; the point deepest inside any of them wins.
+pub fn fragment_anchor(cells: &[(i32, i32)]) -> (f64, f64) {
+    let mut tiles: HashMap<u32, Vec<u32>> = HashMap::new();
+    for &(x, y) in cells {
+        let key = (y / 16) as u32 * 64 + (x / 16) as u32;
+        tiles.entry(key).or_insert_with(|| vec![0; 256])[((y % 16) * 16 + x % 16) as usize] = 1;
+    }
+    trace_areas(&ChunkGrid::new(&tiles), 0.0)
+        .remove(&1)
+        .unwrap_or_default()
+        .iter()
+        .map(|polygon| pole_of_inaccessibility(polygon, 1.0))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map_or((0.0, 0.0), |(point, _)| point)
+}
+
+/// Pole of inaccessibility: the point inside the polygon farthest from any of its rings,
+/// found by refining a grid of square cells until no cell can beat the best by `precision`
+/// yards. Returns the point and its distance to the border.
+pub fn pole_of_inaccessibility(polygon: &Polygon, precision: f64) -> ((f64, f64), f64) {
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (f64::MAX, f64::MAX, f64::MIN, f64::MIN);
+    for &(x, y) in &polygon.outer {
+        min_x = min_x.min(x);
+        min_y = min_y.min(y);
+        max_x = max_x.max(x);
+        max_y = max_y.max(y);
+    }
+    let cell_size = (max_x - min_x).min(max_y - min_y);
+    let center = ((min_x + max_x) / 2.0, (min_y + max_y) / 2.0);
+    if cell_size <= 0.0 {
+        return (center, 0.0);
+    }
+
+    let mut queue = BinaryHeap::new();
+    let half = cell_size / 2.0;
+    let mut x = min_x;
+    while x < max_x {
+        let mut y = min_y;
+        while y < max_y {
+            queue.push(Cell::new((x + half, y + half), half, polygon));
+            y += cell_size;
+        }
+        x += cell_size;
+    }
+
+    let mut best = Cell::new(center, 0.0, polygon);
+    while let Some(cell) = queue.pop() {
+        if cell.potential - best.distance.max(cell.distance) > precision {
+            let h = cell.half / 2.0;
+            for (dx, dy) in [(-h, -h), (h, -h), (-h, h), (h, h)] {
+                queue.push(Cell::new((cell.center.0 + dx, cell.center.1 + dy), h, polygon));
+            }
+        }
+        if cell.distance > best.distance {
+            best = cell;
+        }
+    }
+    (best.center, best.distance)
+}
+
+/// A square search cell, ordered by the best distance any point inside it could have
+struct Cell {
+    center: (f64, f64),
+    half: f64,
+    /// Signed distance from the center to the border (negative outside)
+    distance: f64,
+    potential: f64,
+}
+
+impl Cell {
+    fn new(center: (f64, f64), half: f64, polygon: &Polygon) -> Self {
+        let distance = signed_distance(center, polygon);
+        Self { center, half, distance, potential: distance + half * std::f64::consts::SQRT_2 }
+    }
+}
+
+impl PartialEq for Cell {
+    fn eq(&self, other: &Self) -> bool {
+        self.potential == other.potential
+    }
+}
+
+impl Eq for Cell {}
+
+impl PartialOrd for Cell {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Cell {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.potential.total_cmp(&other.potential)
+    }
+}
+
+fn signed_distance(p: (f64, f64), polygon: &Polygon) -> f64 {
+    let mut inside = false;
+    let mut nearest = f64::MAX;
+    for ring in std::iter::once(&polygon.outer).chain(&polygon.holes) {
+        for i in 0..ring.len() {
+            let (a, b) = (ring[i], ring[(i + 1) % ring.len()]);
+            if (a.1 > p.1) != (b.1 > p.1) && p.0 < a.0 + (p.1 - a.1) / (b.1 - a.1) * (b.0 - a.0) {
+                inside = !inside;
+            }
+            nearest = nearest.min(segment_distance(p, a, b));
+        }
+    }
+    if inside { nearest } else { -nearest }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::{world_to_grid, ChunkLocation};
+    use crate::testutil::tiles_from_rows;
+
+    /// A world ring back on the chunk lattice, rotated to start at its lowest point
+    fn lattice(ring: &Ring) -> Vec<Point> {
+        let mut points: Vec<Point> = ring
+            .iter()
+            .map(|&(x, y)| {
+                let (gx, gy) = world_to_grid(x, y);
+                (gx.round() as i32, gy.round() as i32)
+            })
+            .collect();
+        let start = (0..points.len()).min_by_key(|&i| points[i]).unwrap();
+        points.rotate_left(start);
+        points
+    }
+
+    fn chunks(polygon: &Polygon) -> f64 {
+        polygon_area(polygon) / (CHUNK_SIZE * CHUNK_SIZE)
+    }
+
+    fn trace(rows: &[&str], simplify: f64) -> BTreeMap<u32, Vec<Polygon>> {
+        trace_areas(&ChunkGrid::new(&tiles_from_rows(rows)), simplify)
+    }
+
+    #[test]
+    fn donut_has_one_hole() {
+        let areas = trace(&["111", "1.1", "111"], 0.0);
+        let polygons = &areas[&1];
+        assert_eq!(polygons.len(), 1);
+        // The area stays on the same side of both rings, so they wind opposite ways
+        assert_eq!(lattice(&polygons[0].outer), [(0, 0), (3, 0), (3, 3), (0, 3)]);
+        assert_eq!(polygons[0].holes.len(), 1);
+        assert_eq!(lattice(&polygons[0].holes[0]), [(1, 1), (1, 2), (2, 2), (2, 1)]);
+        assert!((chunks(&polygons[0]) - 8.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn diagonal_pinch_splits_into_polygons_without_holes() {
+        let areas = trace(&["12", "21"], 0.0);
+        for area in [1, 2] {
+            assert_eq!(areas[&area].len(), 2, "area {}", area);
+            assert!(areas[&area].iter().all(|p| p.holes.is_empty() && p.outer.len() == 4));
+        }
+
+        // Two L shapes meeting at two corners around an empty chunk: the left turn at each
+        // shared corner keeps them apart instead of closing a ring with a hole
+        let areas = trace(&["11.", "1.1", ".11"], 0.0);
+        let polygons = &areas[&1];
+        assert_eq!(polygons.len(), 2);
+        for polygon in polygons {
+            assert!(polygon.holes.is_empty());
+            assert_eq!(polygon.outer.len(), 6);
+            assert!((chunks(polygon) - 3.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn island_inside_a_hole_is_its_own_polygon() {
+        let areas = trace(&["11111", "1...1", "1.1.1", "1...1", "11111"], 0.0);
+        let mut polygons: Vec<&Polygon> = areas[&1].iter().collect();
+        polygons.sort_by(|a, b| chunks(b).total_cmp(&chunks(a)));
+        assert_eq!(polygons.len(), 2);
+
+        let (ring, island) = (polygons[0], polygons[1]);
+        assert_eq!(ring.holes.len(), 1);
+        assert_eq!(lattice(&ring.holes[0]), [(1, 1), (1, 4), (4, 4), (4, 1)]);
+        assert!((chunks(ring) - 16.0).abs() < 1e-9);
+        assert!(island.holes.is_empty());
+        assert_eq!(lattice(&island.outer), [(2, 2), (3, 2), (3, 3), (2, 3)]);
+    }
+
+    #[test]
+    fn simplified_areas_share_their_borders() {
+        // A staircase between 1 and 2 collapses to one diagonal in both outlines
+        let rows: Vec<String> = (0..8).map(|y| format!("{}{}", "1".repeat(y + 1), "2".repeat(7 - y))).collect();
+        let rows: Vec<&str> = rows.iter().map(String::as_str).collect();
+        let areas = trace(&rows, CHUNK_SIZE * 0.75);
+        let one = lattice(&areas[&1][0].outer);
+        let two = lattice(&areas[&2][0].outer);
+        assert_eq!(one, [(0, 0), (1, 0), (8, 7), (8, 8), (0, 8)]);
+        assert_eq!(two, [(1, 0), (8, 0), (8, 7)]);
+
+        // A wavy border simplifies to something, but the two sides still tile the square
+        let areas = trace(
+            &["11122222", "11112222", "11222222", "11122222", "11111222", "11112222", "11222222", "11122222"],
+            CHUNK_SIZE * 0.9,
+        );
+        let total: f64 = areas.values().flatten().map(chunks).sum();
+        assert!((total - 64.0).abs() < 1e-9, "{}", total);
+        assert!(areas.values().flatten().all(|p| p.outer.len() < 12));
+    }
+
+    /// `fragment_anchor` must land on one of the fragment's own chunks
+    fn assert_anchor_inside(rows: &[&str]) {
+        let cells: Vec<(i32, i32)> = rows
+            .iter()
+            .enumerate()
+            .flat_map(|(y, row)| row.char_indices().filter(|&(_, c)| c == '1').map(move |(x, _)| (x as i32, y as i32)))
+            .collect();
+        let (x, y) = fragment_anchor(&cells);
+        let location = ChunkLocation::from_world(x, y).unwrap();
+        assert!(cells.contains(&location.global()), "{:?} at {:?} is outside {:?}", (x, y), location.global(), rows);
+
+        // The same point on the full grid falls in area 1
+        let tiles = tiles_from_rows(rows);
+        assert_eq!(ChunkGrid::new(&tiles).area_at_world(x, y).map(|(_, area)| area), Some(1));
+    }
+
+    #[test]
+    fn anchors_land_inside_concave_fragments() {
+        // A C shape, whose bounding box center is in the empty bite
+        assert_anchor_inside(&["11111", "11111", "11...", "11...", "11...", "11111", "11111"]);
+        // An L shape
+        assert_anchor_inside(&["11....", "11....", "11....", "11....", "111111", "111111"]);
+        // A ring around a hole, whose center is the hole
+        assert_anchor_inside(&["1111111", "1111111", "11...11", "11...11", "11...11", "1111111", "1111111"]);
+    }
+
+    #[test]
+    fn pole_of_inaccessibility_avoids_holes() {
+        let areas = trace(&["1111111", "1111111", "11...11", "11...11", "11...11", "1111111", "1111111"], 0.0);
+        let ((x, y), distance) = pole_of_inaccessibility(&areas[&1][0], 1.0);
+        let (gx, gy) = world_to_grid(x, y);
+        // Two chunks thick, a little more in the corners; never deeper than the corner blocks allow
+        assert!(distance > CHUNK_SIZE - 1.0 && distance < CHUNK_SIZE * 1.25, "{}", distance);
+        assert!(!(2.0..5.0).contains(&gx) || !(2.0..5.0).contains(&gy), "{:?}", (gx, gy));
+    }
+}
@@ -0,0 +1,66 @@
+// ============================================================================
+// Minimal PNG writer (8-bit RGB, no filtering)
+// ============================================================================
+
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+
+use flate2::write::ZlibEncoder;
+use flate2::{Compression, Crc};
+
+/// An RGB image, row by row from the top left
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Image {
+    pub fn new(width: u32, height: u32, background: [u8; 3]) -> Self {
+        let pixels = background.repeat(width as usize * height as usize);
+        Self { width, height, pixels }
+    }
+
+    /// Out-of-bounds pixels are ignored, so callers can draw partly off the image
+    pub fn set(&mut self, x: i64, y: i64, color: [u8; 3]) {
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+            return;
+        }
+        let i = (y as usize * self.width as usize + x as usize) * 3;
+        self.pixels[i..i + 3].copy_from_slice(&color);
+    }
+
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let mut raw = Vec::with_capacity(self.pixels.len() + self.height as usize);
+        for row in self.pixels.chunks(self.width as usize * 3) {
+            raw.push(0); // filter: none
+            raw.extend_from_slice(row);
+        }
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&raw)?;
+        let idat = encoder.finish()?;
+
+        let mut ihdr = Vec::with_capacity(13);
+        ihdr.extend_from_slice(&self.width.to_be_bytes());
+        ihdr.extend_from_slice(&self.height.to_be_bytes());
+        ihdr.extend_from_slice(&[8, 2, 0, 0, 0]); // 8-bit, truecolor, deflate, no filter, no interlace
+
+        let mut f = File::create(path)?;
+        f.write_all(b"\x89PNG\r\n\x1a\n")?;
+        write_chunk(&mut f, b"IHDR", &ihdr)?;
+        write_chunk(&mut f, b"IDAT", &idat)?;
+        write_chunk(&mut f, b"IEND", &[])?;
+        Ok(())
+    }
+}
+
+fn write_chunk(f: &mut File, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    let mut crc = Crc::new();
+    crc.update(kind);
+    crc.update(data);
+    f.write_all(&(data.len() as u32).to_be_bytes())?;
+    f.write_all(kind)?;
+    f.write_all(data)?;
+    f.write_all(&crc.sum().to_be_bytes())
+}
@@ -0,0 +1,271 @@
+// ============================================================================
+// Rendering: PNG and SVG pictures of a grid (or one root zone) in the addon's colors
+// ============================================================================
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+
+use crate::dataset::{AreaRecord, GridTiles};
+use crate::grid::{world_to_grid, ChunkGrid};
+use crate::outline;
+use crate::png::Image;
+
+pub struct RenderOptions {
+    pub px_per_chunk: u32,
+    /// Draw lines where areas meet: black between root zones, darker fill inside one
+    pub borders: bool,
+    /// Draw each area's ID at its label anchor
+    pub labels: bool,
+    /// Only draw areas under this root zone, cropped to them
+    pub zone: Option<u32>,
+}
+
+const BACKGROUND: [u8; 3] = [0, 0, 0];
+const UNKNOWN: (f32, f32, f32) = (0.5, 0.5, 0.5);
+
+/// 3x5 digit glyphs, one bit per pixel from the top left, row by row
+const DIGITS: [u16; 10] = [
+    0b111_101_101_101_111,
+    0b010_110_010_010_111,
+    0b111_001_111_100_111,
+    0b111_001_111_001_111,
+    0b101_101_111_001_001,
+    0b111_100_111_001_111,
+    0b111_100_111_101_111,
+    0b111_001_001_010_010,
+    0b111_101_111_101_111,
+    0b111_101_111_001_111,
+];
+
+/// The chunks one render covers and their inclusive chunk bounds
+struct View<'a> {
+    grid: ChunkGrid<'a>,
+    chunks: Vec<(i32, i32, u32)>,
+    min: (i32, i32),
+    max: (i32, i32),
+}
+
+impl<'a> View<'a> {
+    /// `None` when nothing on the grid matches
+    fn new(tiles: &'a GridTiles, areas: &BTreeMap<u32, AreaRecord>, zone: Option<u32>) -> Option<Self> {
+        let grid = ChunkGrid::new(tiles);
+        let chunks: Vec<(i32, i32, u32)> = grid
+            .chunks()
+            .into_iter()
+            .filter(|&(_, _, a)| zone.is_none_or(|z| root_of(areas, a) == z))
+            .collect();
+        let min = (chunks.iter().map(|c| c.0).min()?, chunks.iter().map(|c| c.1).min()?);
+        let max = (chunks.iter().map(|c| c.0).max()?, chunks.iter().map(|c| c.1).max()?);
+        Some(Self { grid, chunks, min, max })
+    }
+
+    fn size(&self, scale: i64) -> (u32, u32) {
+        (((self.max.0 - self.min.0 + 1) as i64 * scale) as u32, ((self.max.1 - self.min.1 + 1) as i64 * scale) as u32)
+    }
+
+    /// Image position of a world point
+    fn project(&self, (x, y): (f64, f64), scale: i64) -> (f64, f64) {
+        let (gx, gy) = world_to_grid(x, y);
+        ((gx - self.min.0 as f64) * scale as f64, (gy - self.min.1 as f64) * scale as f64)
+    }
+
+    fn areas(&self) -> Vec<u32> {
+        let mut ids: Vec<u32> = self.chunks.iter().map(|c| c.2).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+}
+
+fn root_of(areas: &BTreeMap<u32, AreaRecord>, id: u32) -> u32 {
+    match areas.get(&id).map(|a| a.root_parent_id) {
+        Some(root) if root != 0 => root,
+        _ => id,
+    }
+}
+
+fn rgb(areas: &BTreeMap<u32, AreaRecord>, id: u32) -> [u8; 3] {
+    let (r, g, b) = areas.get(&id).and_then(|a| a.color).unwrap_or(UNKNOWN);
+    [(r * 255.0).round() as u8, (g * 255.0).round() as u8, (b * 255.0).round() as u8]
+}
+
+/// `None` when nothing on the grid matches
+pub fn render_grid(tiles: &GridTiles, areas: &BTreeMap<u32, AreaRecord>, options: &RenderOptions) -> Option<Image> {
+    let view = View::new(tiles, areas, options.zone)?;
+    let (grid, chunks, (min_x, min_y)) = (&view.grid, &view.chunks, view.min);
+    let s = options.px_per_chunk.max(1) as i64;
+    let (width, height) = view.size(s);
+    let mut image = Image::new(width, height, BACKGROUND);
+
+    let mut colors: HashMap<u32, [u8; 3]> = HashMap::new();
+    let mut color_of = |id: u32| *colors.entry(id).or_insert_with(|| rgb(areas, id));
+
+    for &(x, y, area) in chunks {
+        let color = color_of(area);
+        let (px, py) = ((x - min_x) as i64 * s, (y - min_y) as i64 * s);
+        for dy in 0..s {
+            for dx in 0..s {
+                image.set(px + dx, py + dy, color);
+            }
+        }
+    }
+
+    if options.borders {
+        let width = (s / 4).max(1);
+        for &(x, y, area) in chunks {
+            let (px, py) = ((x - min_x) as i64 * s, (y - min_y) as i64 * s);
+            // Right and bottom edges; the left/top ones belong to the neighbor's pass
+            for (nx, ny, right) in [(x + 1, y, true), (x, y + 1, false)] {
+                let other = grid.area_at(nx, ny);
+                if other == area || other == 0 {
+                    continue;
+                }
+                let color = if root_of(areas, other) != root_of(areas, area) {
+                    [0, 0, 0]
+                } else {
+                    let [r, g, b] = color_of(area);
+                    [r / 2, g / 2, b / 2]
+                };
+                for i in 0..s {
+                    for w in 0..width {
+                        if right {
+                            image.set(px + s - 1 - w, py + i, color);
+                        } else {
+                            image.set(px + i, py + s - 1 - w, color);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    if options.labels {
+        let scale = if s >= 8 { 2 } else { 1 };
+        for id in view.areas() {
+            let Some(anchor) = areas.get(&id).and_then(|a| a.label_anchor) else {
+                continue;
+            };
+            let (cx, cy) = view.project(anchor, s);
+            draw_number(&mut image, cx.round() as i64, cy.round() as i64, id, scale);
+        }
+    }
+
+    Some(image)
+}
+
+/// One `<path>` per area built from its traced outline, with the area's metadata as
+/// `data-*` attributes and a `<title>` for hover text. `px_per_chunk` sets the units.
+pub fn render_svg(tiles: &GridTiles, areas: &BTreeMap<u32, AreaRecord>, options: &RenderOptions) -> Option<String> {
+    let view = View::new(tiles, areas, options.zone)?;
+    let s = options.px_per_chunk.max(1) as i64;
+    let (width, height) = view.size(s);
+    let polygons = outline::trace_areas(&view.grid, 0.0);
+
+    let mut out = String::new();
+    // Writing to a String cannot fail
+    let _ = write_svg(&mut out, &view, &polygons, areas, options, (width, height));
+    Some(out)
+}
+
+fn write_svg(
+    out: &mut String,
+    view: &View,
+    polygons: &BTreeMap<u32, Vec<outline::Polygon>>,
+    areas: &BTreeMap<u32, AreaRecord>,
+    options: &RenderOptions,
+    (width, height): (u32, u32),
+) -> std::fmt::Result {
+    let s = options.px_per_chunk.max(1) as i64;
+    writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#,
+        w = width,
+        h = height
+    )?;
+    let stroke = if options.borders { r##" stroke="#000000" stroke-width="1""## } else { "" };
+    writeln!(out, r#"  <g class="areas" fill-rule="evenodd"{}>"#, stroke)?;
+    for id in view.areas() {
+        let Some(area_polygons) = polygons.get(&id) else { continue };
+        let mut d = String::new();
+        for ring in area_polygons.iter().flat_map(|p| std::iter::once(&p.outer).chain(&p.holes)) {
+            for (i, &point) in ring.iter().enumerate() {
+                // Outlines run along chunk edges, so every corner is a whole number of chunks
+                let (x, y) = view.project(point, 1);
+                write!(d, "{}{} {}", if i == 0 { "M" } else { "L" }, x.round() as i64 * s, y.round() as i64 * s)?;
+            }
+            d.push('Z');
+        }
+
+        let (name, parent, root, level) = match areas.get(&id) {
+            Some(a) => (a.name.clone(), a.parent_id, root_of(areas, id), a.exploration_level),
+            None => (format!("Unknown_{}", id), 0, id, 0),
+        };
+        let [r, g, b] = rgb(areas, id);
+        writeln!(
+            out,
+            r##"    <path data-area-id="{}" data-name="{}" data-parent-id="{}" data-root-parent-id="{}" data-exploration-level="{}" fill="#{:02x}{:02x}{:02x}" d="{}">"##,
+            id,
+            escape_xml(&name),
+            parent,
+            root,
+            level,
+            r,
+            g,
+            b,
+            d
+        )?;
+        writeln!(out, "      <title>{} ({})</title>", escape_xml(&name), id)?;
+        writeln!(out, "    </path>")?;
+    }
+    writeln!(out, "  </g>")?;
+
+    if options.labels {
+        let size = (s * 2).max(8);
+        writeln!(
+            out,
+            r##"  <g class="labels" font-family="sans-serif" font-size="{}" text-anchor="middle" dominant-baseline="central" fill="#ffffff" stroke="#000000" stroke-width="{}" paint-order="stroke">"##,
+            size,
+            (size as f64 / 6.0).max(1.0)
+        )?;
+        for id in view.areas() {
+            let Some(anchor) = areas.get(&id).and_then(|a| a.label_anchor) else {
+                continue;
+            };
+            let (x, y) = view.project(anchor, s);
+            writeln!(out, r#"    <text x="{:.1}" y="{:.1}" data-area-id="{}">{}</text>"#, x, y, id, id)?;
+        }
+        writeln!(out, "  </g>")?;
+    }
+    writeln!(out, "</svg>")
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// White digits with a black outline, centered on `(cx, cy)`
+fn draw_number(image: &mut Image, cx: i64, cy: i64, value: u32, scale: i64) {
+    let digits: Vec<usize> = value.to_string().bytes().map(|b| (b - b'0') as usize).collect();
+    let width = (digits.len() as i64 * 4 - 1) * scale;
+    let (left, top) = (cx - width / 2, cy - 5 * scale / 2);
+
+    for (pass, color) in [(true, [0, 0, 0]), (false, [255, 255, 255])] {
+        for (i, &d) in digits.iter().enumerate() {
+            for row in 0..5 {
+                for col in 0..3 {
+                    if DIGITS[d] >> (14 - (row * 3 + col)) & 1 == 0 {
+                        continue;
+                    }
+                    let x = left + (i as i64 * 4 + col) * scale;
+                    let y = top + row * scale;
+                    let grow = if pass { 1 } else { 0 };
+                    for py in y - grow..y + scale + grow {
+                        for px in x - grow..x + scale + grow {
+                            image.set(px, py, color);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,377 @@
+// ============================================================================
+// Area rules: which areas are sea, hidden or drawn as part of their parent
+// ============================================================================
+
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::Path;
+
+use crate::area::{find_root_parent, AreaTable};
+use crate::config::{string_at, u32_at};
+use crate::neighbors::NeighborGraph;
+
+/// What a rule says about the areas it matches
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mark {
+    /// Open water; the addon does not fill it
+    Sea,
+    /// Never drawn
+    Hidden,
+    /// Drawn and labelled as its parent
+    Merge,
+}
+
+impl Mark {
+    const ALL: [(&'static str, Mark); 3] = [("sea", Mark::Sea), ("hidden", Mark::Hidden), ("merge", Mark::Merge)];
+
+    fn label(self) -> &'static str {
+        match self {
+            Mark::Sea => "sea",
+            Mark::Hidden => "hidden",
+            Mark::Merge => "merge",
+        }
+    }
+}
+
+/// The marks an area ended up with; written to AreaInfo as booleans
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AreaMarks {
+    pub sea: bool,
+    pub hidden: bool,
+    pub merge_into_parent: bool,
+}
+
+/// One `[[sea]]`, `[[hidden]]` or `[[merge]]` table. Every condition given must hold.
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub mark: Mark,
+    pub ids: Vec<u32>,
+    /// Case-insensitive name pattern; `*` matches any run of characters, `?` one character
+    pub name: Option<String>,
+    pub root_parent: Option<u32>,
+    /// AreaTable flag bits that must all be set
+    pub flags: Option<u32>,
+}
+
+impl Rule {
+    pub fn matches(&self, area_id: u32, areas: &AreaTable) -> bool {
+        let area = areas.get(&area_id);
+        (self.ids.is_empty() || self.ids.contains(&area_id))
+            && self.name.as_ref().is_none_or(|pattern| area.is_some_and(|a| name_matches(pattern, &a.name)))
+            && self.root_parent.is_none_or(|root| find_root_parent(area_id, areas) == root)
+            && self.flags.is_none_or(|bits| area.is_some_and(|a| a.flags & bits == bits))
+    }
+
+    /// How the rule reads in messages, e.g. `sea rule 2 (name "South Sea*")`
+    pub fn describe(&self, index: usize) -> String {
+        let mut conditions = Vec::new();
+        if !self.ids.is_empty() {
+            let ids: Vec<String> = self.ids.iter().map(u32::to_string).collect();
+            conditions.push(format!("id {}", ids.join(", ")));
+        }
+        if let Some(name) = &self.name {
+            conditions.push(format!("name \"{}\"", name));
+        }
+        if let Some(root) = self.root_parent {
+            conditions.push(format!("root_parent {}", root));
+        }
+        if let Some(flags) = self.flags {
+            conditions.push(format!("flags 0x{:x}", flags));
+        }
+        format!("{} rule {} ({})", self.mark.label(), index + 1, conditions.join(", "))
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AreaRules {
+    pub rules: Vec<Rule>,
+}
+
+impl AreaRules {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let text = fs::read_to_string(path)?;
+        let table: toml::Table = text.parse()?;
+
+        if let Some(key) = table.keys().find(|k| !Mark::ALL.iter().any(|(label, _)| label == k)) {
+            return Err(format!("unknown rule type '{}' (expected [[sea]], [[hidden]] or [[merge]])", key).into());
+        }
+
+        let mut rules = Vec::new();
+        for (label, mark) in Mark::ALL {
+            let Some(entries) = table.get(label) else {
+                continue;
+            };
+            let entries = entries.as_array().ok_or_else(|| format!("'{}' must be an array of tables ([[{}]])", label, label))?;
+            for (i, entry) in entries.iter().enumerate() {
+                let ctx = format!("{}[{}]", label, i);
+                let entry = entry.as_table().ok_or_else(|| format!("{} must be a table", ctx))?;
+
+                let rule = Rule {
+                    mark,
+                    ids: ids_at(entry, &ctx)?,
+                    name: string_at(entry, &ctx, "name")?,
+                    root_parent: u32_at(entry, &ctx, "root_parent")?,
+                    flags: u32_at(entry, &ctx, "flags")?,
+                };
+                if rule.ids.is_empty() && rule.name.is_none() && rule.root_parent.is_none() && rule.flags.is_none() {
+                    return Err(format!("{} has no id, name, root_parent or flags", ctx).into());
+                }
+                rules.push(rule);
+            }
+        }
+        Ok(Self { rules })
+    }
+
+    /// Marks for every found area that any rule matches, and the rules that matched nothing
+    pub fn apply(&self, found_areas: &BTreeSet<u32>, areas: &AreaTable) -> (HashMap<u32, AreaMarks>, Vec<String>) {
+        let mut marks: HashMap<u32, AreaMarks> = HashMap::new();
+        let mut unused = Vec::new();
+        for (index, rule) in self.rules.iter().enumerate() {
+            let mut matched = false;
+            for &area_id in found_areas.iter().filter(|&&id| id != 0) {
+                if !rule.matches(area_id, areas) {
+                    continue;
+                }
+                matched = true;
+                let entry = marks.entry(area_id).or_default();
+                match rule.mark {
+                    Mark::Sea => entry.sea = true,
+                    Mark::Hidden => entry.hidden = true,
+                    Mark::Merge => entry.merge_into_parent = true,
+                }
+            }
+            if !matched {
+                unused.push(rule.describe(index));
+            }
+        }
+        (marks, unused)
+    }
+}
+
+/// `id = 5` or `id = [5, 6]`
+fn ids_at(table: &toml::Table, ctx: &str) -> Result<Vec<u32>, String> {
+    let Some(value) = table.get("id") else {
+        return Ok(Vec::new());
+    };
+    let values = match value.as_array() {
+        Some(list) => list.iter().collect(),
+        None => vec![value],
+    };
+    values
+        .into_iter()
+        .map(|v| v.as_integer().and_then(|n| u32::try_from(n).ok()))
+        .collect::<Option<Vec<u32>>>()
+        .ok_or_else(|| format!("{}.id must be an area ID or a list of them", ctx))
+}
+
+fn name_matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let name: Vec<char> = name.to_lowercase().chars().collect();
+    glob(&pattern, &name)
+}
+
+fn glob(pattern: &[char], text: &[char]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some(('*', rest)) => (0..=text.len()).any(|skip| glob(rest, &text[skip..])),
+        Some(('?', rest)) => !text.is_empty() && glob(rest, &text[1..]),
+        Some((c, rest)) => text.first() == Some(c) && glob(rest, &text[1..]),
+    }
+}
+
+/// The area a merged area is drawn as: its nearest ancestor that is not merged itself
+pub fn merge_target(area_id: u32, marks: &HashMap<u32, AreaMarks>, areas: &AreaTable) -> u32 {
+    let mut id = area_id;
+    let mut seen = BTreeSet::new();
+    while marks.get(&id).is_some_and(|m| m.merge_into_parent) && seen.insert(id) {
+        match areas.get(&id).map(|a| a.parent_id) {
+            Some(parent) if parent != 0 => id = parent,
+            _ => break,
+        }
+    }
+    id
+}
+
+/// The neighbor graph with every merged area folded into the area it is drawn as
+pub fn fold_merged(graph: &NeighborGraph, marks: &HashMap<u32, AreaMarks>, areas: &AreaTable) -> NeighborGraph {
+    let mut folded = NeighborGraph::new();
+    for (&a, neighbors) in graph {
+        let a = merge_target(a, marks, areas);
+        for &b in neighbors {
+            let b = merge_target(b, marks, areas);
+            if a != b {
+                folded.entry(a).or_default().insert(b);
+                folded.entry(b).or_default().insert(a);
+            }
+        }
+    }
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{area_table, TempDir};
+
+    fn areas() -> AreaTable {
+        let mut areas = area_table(&[
+            (1, "Durotar", 0),
+            (2, "Orgrimmar", 1),
+            (3, "Valley of Strength", 2),
+            (4, "Razor Hill", 1),
+            (5, "South Seas", 0),
+            (6, "South Sea Isles", 0),
+            (7, "North Sea", 0),
+            (8, "The Great Sea", 0),
+        ]);
+        areas.get_mut(&8).unwrap().flags = 0x400 | 0x1;
+        areas.get_mut(&7).unwrap().flags = 0x400;
+        areas
+    }
+
+    fn load(text: &str) -> AreaRules {
+        let dir = TempDir::new("rules");
+        let path = dir.0.join("area_rules.toml");
+        fs::write(&path, text).unwrap();
+        AreaRules::load(&path).unwrap()
+    }
+
+    fn marked(marks: &HashMap<u32, AreaMarks>, f: fn(&AreaMarks) -> bool) -> BTreeSet<u32> {
+        marks.iter().filter(|(_, m)| f(m)).map(|(&id, _)| id).collect()
+    }
+
+    #[test]
+    fn name_globs() {
+        assert!(name_matches("South Sea*", "South Seas"));
+        assert!(name_matches("South Sea*", "south sea isles"));
+        assert!(name_matches("South Sea*", "South Sea"));
+        assert!(!name_matches("South Sea*", "The South Seas"));
+        assert!(name_matches("*Sea", "The Great Sea"));
+        assert!(name_matches("Th? Great*", "The Great Sea"));
+        assert!(!name_matches("Th? Great*", "Th Great Sea"));
+        assert!(name_matches("*", ""));
+        assert!(!name_matches("?", ""));
+        assert!(name_matches("a*b*c", "aXXbYc"));
+        assert!(!name_matches("a*b*c", "aXXcYb"));
+    }
+
+    #[test]
+    fn rules_match_on_every_condition_given() {
+        let areas = areas();
+        let rule = |name: Option<&str>, root_parent, flags| Rule {
+            mark: Mark::Sea,
+            ids: Vec::new(),
+            name: name.map(str::to_string),
+            root_parent,
+            flags,
+        };
+        let matching = |rule: Rule| -> Vec<u32> {
+            let mut ids: Vec<u32> = areas.keys().copied().filter(|&id| rule.matches(id, &areas)).collect();
+            ids.sort_unstable();
+            ids
+        };
+
+        assert_eq!(matching(rule(Some("South Sea*"), None, None)), [5, 6]);
+        assert_eq!(matching(rule(None, Some(1), None)), [1, 2, 3, 4]);
+        assert_eq!(matching(rule(Some("*ar"), Some(1), None)), [1, 2]);
+        // Every bit of the mask must be set
+        assert_eq!(matching(rule(None, None, Some(0x400))), [7, 8]);
+        assert_eq!(matching(rule(None, None, Some(0x401))), [8]);
+        assert_eq!(matching(rule(Some("North*"), None, Some(0x401))), Vec::<u32>::new());
+        // An ID missing from the AreaTable has no name or flags to match
+        assert!(!rule(Some("*"), None, None).matches(99, &areas));
+    }
+
+    #[test]
+    fn apply_marks_found_areas_and_reports_unused_rules() {
+        let rules = load(
+            r#"
+[[sea]]
+name = "South Sea*"
+
+[[sea]]
+flags = 0x401
+
+[[hidden]]
+id = [4, 99]
+
+[[hidden]]
+name = "Twisting Nether"
+
+[[merge]]
+id = 2
+
+[[merge]]
+root_parent = 1
+name = "* of *"
+"#,
+        );
+        assert_eq!(rules.rules.len(), 6);
+
+        // 6 is in the AreaTable but has no chunks, so it is not marked
+        let found: BTreeSet<u32> = [0, 1, 2, 3, 4, 5, 8].into_iter().collect();
+        let (marks, unused) = rules.apply(&found, &areas());
+        assert_eq!(marked(&marks, |m| m.sea), [5, 8].into_iter().collect());
+        assert_eq!(marked(&marks, |m| m.hidden), [4].into_iter().collect());
+        assert_eq!(marked(&marks, |m| m.merge_into_parent), [2, 3].into_iter().collect());
+        assert!(!marks.contains_key(&0) && !marks.contains_key(&1));
+        assert_eq!(unused, ["hidden rule 4 (name \"Twisting Nether\")"]);
+
+        let (_, unused) = load("[[sea]]\nid = 6\nname = \"South*\"\n").apply(&found, &areas());
+        assert_eq!(unused, ["sea rule 1 (id 6, name \"South*\")"]);
+    }
+
+    #[test]
+    fn load_rejects_bad_rules() {
+        let dir = TempDir::new("rules-bad");
+        let path = dir.0.join("area_rules.toml");
+        for (text, error) in [
+            ("[[ocean]]\nid = 1\n", "unknown rule type 'ocean' (expected [[sea]], [[hidden]] or [[merge]])"),
+            ("[[sea]]\n", "sea[0] has no id, name, root_parent or flags"),
+            ("[[merge]]\nid = \"5\"\n", "merge[0].id must be an area ID or a list of them"),
+            ("sea = 5\n", "'sea' must be an array of tables ([[sea]])"),
+        ] {
+            fs::write(&path, text).unwrap();
+            assert_eq!(AreaRules::load(&path).unwrap_err().to_string(), error);
+        }
+    }
+
+    #[test]
+    fn merge_chains_fold_into_the_first_unmerged_ancestor() {
+        let areas = areas();
+        let merged = |ids: &[u32]| -> HashMap<u32, AreaMarks> {
+            ids.iter().map(|&id| (id, AreaMarks { merge_into_parent: true, ..Default::default() })).collect()
+        };
+
+        // 3 -> 2 -> 1: both merged, so 3 is drawn as 1
+        let marks = merged(&[2, 3]);
+        assert_eq!(merge_target(3, &marks, &areas), 1);
+        assert_eq!(merge_target(2, &marks, &areas), 1);
+        assert_eq!(merge_target(4, &marks, &areas), 4);
+        // A merged root has no parent to go to
+        assert_eq!(merge_target(1, &merged(&[1]), &areas), 1);
+        // Only 3 merged: it stops at 2
+        assert_eq!(merge_target(3, &merged(&[3]), &areas), 2);
+
+        // A merge cycle stops instead of looping
+        let mut cyclic = areas.clone();
+        cyclic.get_mut(&1).unwrap().parent_id = 3;
+        let target = merge_target(3, &merged(&[1, 2, 3]), &cyclic);
+        assert!([1, 2, 3].contains(&target));
+
+        // 3 borders 4 and 5; 2 borders 4. Folded into 1, they become one edge set of 1's
+        let mut graph = NeighborGraph::new();
+        for (a, b) in [(3, 4), (3, 5), (2, 4), (2, 3), (1, 2)] {
+            graph.entry(a).or_default().insert(b);
+            graph.entry(b).or_default().insert(a);
+        }
+        let folded = fold_merged(&graph, &marks, &areas);
+        let neighbors = |id: u32| -> BTreeSet<u32> { folded.get(&id).into_iter().flatten().copied().collect() };
+        assert_eq!(neighbors(1), [4, 5].into_iter().collect());
+        assert_eq!(neighbors(4), [1].into_iter().collect());
+        assert_eq!(neighbors(5), [1].into_iter().collect());
+        // Merged areas and the edges inside the merge are gone
+        assert!(!folded.contains_key(&2) && !folded.contains_key(&3));
+    }
+}
@@ -0,0 +1,276 @@
+// ============================================================================
+// ADT sources: loose files, MPQ archives and CASC storage
+// ============================================================================
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::casc::CascStorage;
+use crate::config::{Config, MapConfig};
+use crate::mpq::MpqSet;
+use crate::wdt;
+use crate::tiles::parse_root_adt_filename;
+
+/// Where a game file's (root ADT, WDT) bytes come from
+pub enum SourceFile {
+    Loose(PathBuf),
+    Mpq(Rc<MpqSet>, String),
+    Casc(Rc<CascStorage>, u32),
+}
+
+impl SourceFile {
+    pub fn read(&self) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        match self {
+            SourceFile::Loose(path) => Ok(fs::read(path)?),
+            SourceFile::Mpq(set, name) => set.read_file(name)?.ok_or_else(|| format!("{} vanished from the MPQs", name).into()),
+            SourceFile::Casc(storage, fdid) => storage.read_fdid(*fdid),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            SourceFile::Loose(path) => path.display().to_string(),
+            SourceFile::Mpq(_, name) => name.clone(),
+            SourceFile::Casc(_, fdid) => format!("FileDataID {}", fdid),
+        }
+    }
+}
+
+/// Root ADTs of one map, grouped by the `<Map>` prefix of `<Map>_<X>_<Y>.adt`
+pub struct MapAdts {
+    pub map: String,
+    pub tiles: Vec<(u32, u32, SourceFile)>,
+    /// `<Map>.wdt`, if the source has one
+    pub wdt: Option<SourceFile>,
+}
+
+impl MapAdts {
+    pub fn new(map: String) -> Self {
+        Self { map, tiles: Vec::new(), wdt: None }
+    }
+}
+
+/// Collect root ADTs under `dir`, grouped by map prefix (case-insensitive) and sorted by name.
+fn scan_root_adts(dir: &Path, recursive: bool) -> Result<Vec<MapAdts>, Box<dyn std::error::Error>> {
+    if !dir.exists() {
+        return Err(format!("Directory not found: {}", dir.display()).into());
+    }
+
+    let mut groups: BTreeMap<String, MapAdts> = BTreeMap::new();
+    let mut wdts: HashMap<String, PathBuf> = HashMap::new();
+    let mut pending = vec![dir.to_path_buf()];
+
+    while let Some(current) = pending.pop() {
+        for entry in fs::read_dir(&current)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                if recursive {
+                    pending.push(path);
+                }
+                continue;
+            }
+
+            if path.extension().and_then(|e| e.to_str()).is_some_and(|e| e.eq_ignore_ascii_case("wdt")) {
+                if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
+                    wdts.insert(stem.to_ascii_lowercase(), path);
+                }
+                continue;
+            }
+
+            let Some((map, tx, ty)) = parse_root_adt_filename(&path) else {
+                continue;
+            };
+            groups
+                .entry(map.to_ascii_lowercase())
+                .or_insert_with(|| MapAdts::new(map))
+                .tiles
+                .push((tx, ty, SourceFile::Loose(path)));
+        }
+    }
+
+    for (key, group) in &mut groups {
+        group.wdt = wdts.remove(key).map(SourceFile::Loose);
+    }
+    Ok(groups.into_values().collect())
+}
+
+/// Collect root ADTs listed in the MPQs as `World\Maps\<Map>\<Map>_<X>_<Y>.adt`.
+fn scan_mpq_adts(set: &Rc<MpqSet>) -> Result<Vec<MapAdts>, Box<dyn std::error::Error>> {
+    let mut groups: BTreeMap<String, MapAdts> = BTreeMap::new();
+
+    for name in set.list_files()? {
+        let parts: Vec<&str> = name.split(['\\', '/']).collect();
+        let [world, maps, map_dir, file] = parts[..] else {
+            continue;
+        };
+        if !world.eq_ignore_ascii_case("world") || !maps.eq_ignore_ascii_case("maps") {
+            continue;
+        }
+        let Some((map, tx, ty)) = parse_root_adt_filename(Path::new(file)) else {
+            continue;
+        };
+        if !map.eq_ignore_ascii_case(map_dir) {
+            continue;
+        }
+        groups
+            .entry(map.to_ascii_lowercase())
+            .or_insert_with(|| MapAdts::new(map))
+            .tiles
+            .push((tx, ty, SourceFile::Mpq(Rc::clone(set), name.clone())));
+    }
+
+    for group in groups.values_mut() {
+        let wdt_name = format!("World\\Maps\\{}\\{}.wdt", group.map, group.map);
+        if set.contains(&wdt_name) {
+            group.wdt = Some(SourceFile::Mpq(Rc::clone(set), wdt_name));
+        }
+    }
+    Ok(groups.into_values().collect())
+}
+
+/// Collect root ADTs from CASC. With a listfile every `world/maps/<Map>/<Map>_<X>_<Y>.adt`
+/// is found; without one only the configured maps can be located, through their WDTs.
+fn scan_casc_adts(storage: &Rc<CascStorage>, maps: &[MapConfig]) -> Result<Vec<MapAdts>, Box<dyn std::error::Error>> {
+    let mut groups: BTreeMap<String, MapAdts> = BTreeMap::new();
+
+    if !storage.listfile().is_empty() {
+        for (fdid, name) in storage.listfile() {
+            let parts: Vec<&str> = name.split('/').collect();
+            let [world, maps, map_dir, file] = parts[..] else {
+                continue;
+            };
+            if !world.eq_ignore_ascii_case("world") || !maps.eq_ignore_ascii_case("maps") {
+                continue;
+            }
+            let Some((map, tx, ty)) = parse_root_adt_filename(Path::new(file)) else {
+                continue;
+            };
+            if !map.eq_ignore_ascii_case(map_dir) {
+                continue;
+            }
+            groups
+                .entry(map.to_ascii_lowercase())
+                .or_insert_with(|| MapAdts::new(map))
+                .tiles
+                .push((tx, ty, SourceFile::Casc(Rc::clone(storage), *fdid)));
+        }
+        for group in groups.values_mut() {
+            let wdt_name = format!("world/maps/{}/{}.wdt", group.map, group.map);
+            group.wdt = storage.fdid_for_name(&wdt_name).map(|fdid| SourceFile::Casc(Rc::clone(storage), fdid));
+        }
+        return Ok(groups.into_values().collect());
+    }
+
+    for map in maps {
+        let prefix = map.adt_prefix();
+        let wdt_name = format!("world/maps/{}/{}.wdt", prefix, prefix);
+        let Some(wdt_fdid) = storage.fdid_for_name(&wdt_name) else {
+            continue;
+        };
+        let tiles = wdt::parse_maid(&storage.read_fdid(wdt_fdid)?)
+            .into_iter()
+            .map(|(tx, ty, fdid)| (tx, ty, SourceFile::Casc(Rc::clone(storage), fdid)))
+            .collect();
+        let wdt = Some(SourceFile::Casc(Rc::clone(storage), wdt_fdid));
+        groups.insert(prefix.to_ascii_lowercase(), MapAdts { map: prefix.to_string(), tiles, wdt });
+    }
+    Ok(groups.into_values().collect())
+}
+
+/// The maps `plan_maps` chose and how it found them
+pub struct MapPlan {
+    pub maps: Vec<(MapConfig, MapAdts)>,
+    /// Directories scanned, maps discovered and configured maps skipped, one line each
+    pub log: Vec<String>,
+}
+
+/// Pair every map to export with its root ADTs.
+///
+/// Maps with an explicit `adt_dir` read that directory. Everything found under `adt_root`,
+/// in the MPQs of `mpq_dir` or in the CASC storage at `casc_dir` that is not already claimed
+/// becomes a map of its own, picking up grid name, UiMap ID and output file from a
+/// `[[maps]]` entry whose `map` prefix matches, if there is one.
+pub fn plan_maps(config: &Config) -> Result<MapPlan, Box<dyn std::error::Error>> {
+    let mut planned = Vec::new();
+    let mut log = Vec::new();
+
+    for (map, dir) in config.maps.iter().filter_map(|m| m.adt_dir.as_deref().map(|d| (m, d))) {
+        log.push(format!("Scanning: {}", dir.display()));
+        let mut groups = scan_root_adts(dir, false)?;
+
+        let group = match &map.map {
+            Some(prefix) => groups
+                .into_iter()
+                .find(|g| g.map.eq_ignore_ascii_case(prefix))
+                .ok_or_else(|| format!("no {}_X_Y.adt files in {}", prefix, dir.display()))?,
+            None if groups.len() <= 1 => groups.pop().unwrap_or_else(|| MapAdts::new(map.grid.clone())),
+            None => {
+                let names: Vec<&str> = groups.iter().map(|g| g.map.as_str()).collect();
+                return Err(format!(
+                    "{} holds ADTs for several maps ({}); set `map` for grid {}",
+                    dir.display(), names.join(", "), map.grid
+                ).into());
+            }
+        };
+        planned.push((map.clone(), group));
+    }
+
+    let discovered = match (&config.adt_root, &config.mpq_dir, &config.casc_dir) {
+        (Some(root), None, None) => {
+            log.push(format!("Discovering maps under: {}", root.display()));
+            scan_root_adts(root, true)?
+        }
+        (None, Some(dir), None) => {
+            let set = Rc::new(MpqSet::open_dir(dir)?);
+            log.push(format!("Discovering maps in {} MPQs under: {}", set.archives().len(), dir.display()));
+            scan_mpq_adts(&set)?
+        }
+        (None, None, Some(dir)) => {
+            let storage = Rc::new(open_casc(
+                dir,
+                config.casc_product.as_deref(),
+                config.casc_locale.as_deref(),
+                config.listfile.as_deref(),
+            )?);
+            log.push(format!("Discovering maps in CASC storage: {} ({} files)", dir.display(), storage.file_count()));
+            scan_casc_adts(&storage, &config.maps)?
+        }
+        (None, None, None) => Vec::new(),
+        _ => return Err("set only one of adt_root, mpq_dir and casc_dir".into()),
+    };
+
+    for group in discovered {
+        if planned.iter().any(|(m, _)| m.adt_prefix().eq_ignore_ascii_case(&group.map)) {
+            continue;
+        }
+
+        let map = config
+            .maps
+            .iter()
+            .find(|m| m.adt_dir.is_none() && m.adt_prefix().eq_ignore_ascii_case(&group.map))
+            .cloned()
+            .unwrap_or_else(|| MapConfig::new(&group.map));
+        log.push(format!("  Found map {} ({} tiles)", group.map, group.tiles.len()));
+        planned.push((map, group));
+    }
+
+    for map in config.maps.iter().filter(|m| m.adt_dir.is_none()) {
+        if !planned.iter().any(|(m, _)| m.grid == map.grid) {
+            log.push(format!("  Skipping {}: no {}_X_Y.adt files found", map.grid, map.adt_prefix()));
+        }
+    }
+
+    Ok(MapPlan { maps: planned, log })
+}
+
+pub fn open_casc(
+    dir: &Path,
+    product: Option<&str>,
+    locale: Option<&str>,
+    listfile: Option<&Path>,
+) -> Result<CascStorage, Box<dyn std::error::Error>> {
+    CascStorage::open(dir, product, locale, listfile)
+        .map_err(|e| format!("failed to open CASC storage {}: {}", dir.display(), e).into())
+}
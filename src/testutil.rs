@@ -0,0 +1,62 @@
+// ============================================================================
+// Helpers shared by the module tests
+// ============================================================================
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::area::{AreaInfo, AreaTable};
+use crate::tiles::tile_key;
+
+/// Fresh scratch directory, unique per process and per call, removed when dropped
+pub struct TempDir(pub PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let n = NEXT.fetch_add(1, Ordering::Relaxed);
+        let dir = std::env::temp_dir().join(format!("zonemap-{}-{}-{}", name, std::process::id(), n));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Tiles for a `ChunkGrid` drawn as text: one row per chunk row from global (0, 0), one
+/// character per chunk, `.` for AreaID 0 and a base-36 digit for any other AreaID
+pub fn tiles_from_rows(rows: &[&str]) -> HashMap<u32, Vec<u32>> {
+    let mut tiles: HashMap<u32, Vec<u32>> = HashMap::new();
+    for (y, row) in rows.iter().enumerate() {
+        for (x, c) in row.chars().enumerate() {
+            let area = c.to_digit(36).unwrap_or(0);
+            let key = tile_key((x / 16) as u32, (y / 16) as u32);
+            tiles.entry(key).or_insert_with(|| vec![0; 256])[(y % 16) * 16 + x % 16] = area;
+        }
+    }
+    tiles
+}
+
+/// An AreaTable of `(id, name, parent_id)` rows, with no continent, flags or level
+pub fn area_table(rows: &[(u32, &str, u32)]) -> AreaTable {
+    rows.iter()
+        .map(|&(id, name, parent_id)| {
+            let info = AreaInfo {
+                id,
+                name: name.to_string(),
+                parent_id,
+                exploration_level: 0,
+                continent_id: None,
+                flags: 0,
+            };
+            (id, info)
+        })
+        .collect()
+}
@@ -0,0 +1,456 @@
+// ============================================================================
+// ADT / Tile parsing
+// ============================================================================
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::File;
+use std::io::{Cursor, Write};
+use std::path::Path;
+
+use base64::{engine::general_purpose, Engine as _};
+use wow_adt::Adt;
+
+use crate::config::TileEncoding;
+use crate::liquid::{parse_adt_liquids, Liquid};
+use crate::sources::SourceFile;
+use crate::wdt::TileInventory;
+
+pub fn parse_root_adt_filename(path: &Path) -> Option<(String, u32, u32)> {
+    if !path.extension()?.to_str()?.eq_ignore_ascii_case("adt") {
+        return None;
+    }
+    let stem = path.file_stem()?.to_str()?.to_string();
+    let parts: Vec<&str> = stem.split('_').collect();
+    if parts.len() != 3 {
+        return None;
+    }
+    let map = parts[0].to_string();
+    let x: u32 = parts[1].parse().ok()?;
+    let y: u32 = parts[2].parse().ok()?;
+    if x >= 64 || y >= 64 {
+        return None;
+    }
+    Some((map, x, y))
+}
+
+pub fn tile_key(tile_x: u32, tile_y: u32) -> u32 {
+    tile_y * 64 + tile_x
+}
+
+pub fn encode_tile_b64(area_ids_256: &[u32], encoding: TileEncoding) -> Result<String, Box<dyn std::error::Error>> {
+    if area_ids_256.len() != 256 {
+        return Err(format!("expected 256 area IDs, got {}", area_ids_256.len()).into());
+    }
+
+    let raw = match encoding {
+        TileEncoding::U32 => encode_tile_u32(area_ids_256),
+        TileEncoding::PaletteRle => encode_tile_palette_rle(area_ids_256)?,
+    };
+
+    Ok(general_purpose::STANDARD.encode(&raw))
+}
+
+/// `u32le`: 256 little-endian u32 AreaIDs, row by row
+fn encode_tile_u32(area_ids_256: &[u32]) -> Vec<u8> {
+    let mut raw = Vec::with_capacity(256 * 4);
+    for &v in area_ids_256 {
+        raw.extend_from_slice(&v.to_le_bytes());
+    }
+    raw
+}
+
+/// `palette-rle-v1`:
+///   u8        palette length - 1
+///   u16 LE    palette entries (area IDs, in order of first appearance)
+///   (u8, u8)  run length - 1, palette index; runs cover all 256 chunks row by row
+fn encode_tile_palette_rle(area_ids_256: &[u32]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let mut palette: Vec<u16> = Vec::new();
+    let mut indices = Vec::with_capacity(256);
+    for &id in area_ids_256 {
+        let id = u16::try_from(id).map_err(|_| format!("area ID {} does not fit in a u16 palette", id))?;
+        let index = match palette.iter().position(|&p| p == id) {
+            Some(i) => i,
+            None => {
+                palette.push(id);
+                palette.len() - 1
+            }
+        };
+        indices.push(index as u8);
+    }
+
+    let mut raw = Vec::with_capacity(1 + palette.len() * 2 + 32);
+    raw.push((palette.len() - 1) as u8);
+    for id in &palette {
+        raw.extend_from_slice(&id.to_le_bytes());
+    }
+
+    let mut i = 0;
+    while i < indices.len() {
+        let run = indices[i..].iter().take_while(|&&x| x == indices[i]).count();
+        raw.push((run - 1) as u8);
+        raw.push(indices[i]);
+        i += run;
+    }
+    Ok(raw)
+}
+
+/// Inverse of `encode_tile_b64`, for reading generated tile files back
+pub fn decode_tile_b64(blob: &str, encoding: TileEncoding) -> Result<Vec<u32>, Box<dyn std::error::Error>> {
+    let raw = general_purpose::STANDARD.decode(blob.trim())?;
+    let area_ids = match encoding {
+        TileEncoding::U32 => raw
+            .chunks_exact(4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect::<Vec<u32>>(),
+        TileEncoding::PaletteRle => {
+            let count = *raw.first().ok_or("empty tile")? as usize + 1;
+            let palette: Vec<u32> = raw
+                .get(1..1 + count * 2)
+                .ok_or("tile palette is truncated")?
+                .chunks_exact(2)
+                .map(|b| u16::from_le_bytes([b[0], b[1]]) as u32)
+                .collect();
+            let mut area_ids = Vec::with_capacity(256);
+            for run in raw[1 + count * 2..].chunks_exact(2) {
+                let id = *palette.get(run[1] as usize).ok_or("tile palette index out of range")?;
+                area_ids.extend(std::iter::repeat_n(id, run[0] as usize + 1));
+            }
+            area_ids
+        }
+    };
+    if area_ids.len() != 256 {
+        return Err(format!("tile decodes to {} area IDs, expected 256", area_ids.len()).into());
+    }
+    Ok(area_ids)
+}
+
+pub fn parse_adt_areaids(data: Vec<u8>) -> Result<Option<Vec<u32>>, Box<dyn std::error::Error>> {
+    let adt = Adt::from_reader(Cursor::new(data))?;
+
+    let mut area_ids: Vec<u32> = adt
+        .mcnk_chunks
+        .iter()
+        .map(|chunk| chunk.area_id)
+        .collect();
+
+    if area_ids.is_empty() {
+        return Ok(None);
+    }
+    
+    if area_ids.len() != 256 {
+        area_ids.resize(256, 0);
+    }
+
+    Ok(Some(area_ids))
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TileGrid {
+    pub continent_name: String,
+    pub ui_map_id: Option<u32>,
+    pub tiles_raw: HashMap<u32, Vec<u32>>,
+    pub found_areas: BTreeSet<u32>,
+    /// What covers each chunk, for the same tiles as `tiles_raw`
+    pub liquids: HashMap<u32, Vec<Liquid>>,
+    /// The tiles the map's WDT lists, if it has one
+    pub existing_tiles: Option<TileInventory>,
+}
+
+impl TileGrid {
+    pub fn new(continent_name: &str) -> Self {
+        Self {
+            continent_name: continent_name.to_string(),
+            ui_map_id: None,
+            tiles_raw: HashMap::new(),
+            found_areas: BTreeSet::new(),
+            liquids: HashMap::new(),
+            existing_tiles: None,
+        }
+    }
+
+    /// The palette encoding only holds 16-bit area IDs; grids with larger ones stay raw.
+    pub fn effective_encoding(&self, requested: TileEncoding) -> TileEncoding {
+        match requested {
+            TileEncoding::PaletteRle if self.found_areas.iter().any(|&id| id > u16::MAX as u32) => TileEncoding::U32,
+            other => other,
+        }
+    }
+
+    pub fn encode_tiles(&self, encoding: TileEncoding) -> Result<BTreeMap<u32, String>, Box<dyn std::error::Error>> {
+        self.tiles_raw
+            .iter()
+            .map(|(&key, area_ids)| Ok((key, encode_tile_b64(area_ids, encoding)?)))
+            .collect()
+    }
+
+    /// Tiles with any chunk that is not land, in key order
+    pub fn liquid_tiles(&self) -> BTreeMap<u32, &[Liquid]> {
+        self.liquids
+            .iter()
+            .filter(|(_, chunks)| chunks.iter().any(|&l| l != Liquid::Land))
+            .map(|(&key, chunks)| (key, chunks.as_slice()))
+            .collect()
+    }
+
+    /// Total base64 length of the tiles in the given encoding
+    pub fn payload_len(&self, encoding: TileEncoding) -> Result<usize, Box<dyn std::error::Error>> {
+        Ok(self.encode_tiles(encoding)?.values().map(String::len).sum())
+    }
+
+    /// Write the grid. Uniform tiles become a bare AreaID and blobs used by more than one tile
+    /// are written once into a `shared` table that those tiles reference.
+    pub fn export_lua(&self, out_path: &Path, encoding: TileEncoding) -> Result<TileDedupStats, Box<dyn std::error::Error>> {
+        let tiles = self.encode_tiles(encoding)?;
+        let uniform: HashMap<u32, u32> = self
+            .tiles_raw
+            .iter()
+            .filter(|(_, ids)| ids.iter().all(|&id| id == ids[0]))
+            .map(|(&key, ids)| (key, ids[0]))
+            .collect();
+
+        let mut blob_uses: HashMap<&str, usize> = HashMap::new();
+        for (k, v) in &tiles {
+            if !uniform.contains_key(k) {
+                *blob_uses.entry(v.as_str()).or_default() += 1;
+            }
+        }
+        let mut shared: Vec<&str> = Vec::new();
+        let mut shared_index: HashMap<&str, usize> = HashMap::new();
+        for (k, v) in &tiles {
+            if !uniform.contains_key(k) && blob_uses[v.as_str()] > 1 && !shared_index.contains_key(v.as_str()) {
+                shared.push(v);
+                shared_index.insert(v, shared.len());
+            }
+        }
+
+        let mut f = File::create(out_path)?;
+
+        writeln!(f, "-- Auto-generated AreaID grid for {}", self.continent_name)?;
+        match encoding {
+            TileEncoding::U32 => {
+                writeln!(f, "-- Each tile is 16x16 chunks (256 u32 AreaIDs), base64 encoded.")?
+            }
+            TileEncoding::PaletteRle => writeln!(
+                f,
+                "-- Each tile is 16x16 chunks: a u16 AreaID palette plus run-length palette indices, base64 encoded."
+            )?,
+        }
+        writeln!(f, "-- A tile that is a number has that AreaID in all 256 chunks.")?;
+        writeln!(f, "-- liquid: one digit per chunk (0 land, 1 shallow water, 2 deep water, 3 lava, 4 slime),")?;
+        writeln!(f, "-- a single number when the whole tile is one kind. Tiles that are all land are left out.")?;
+        writeln!(f)?;
+        writeln!(f, "local _, addon = ...")?;
+        writeln!(f)?;
+        if !shared.is_empty() {
+            writeln!(f, "local shared = {{")?;
+            for blob in &shared {
+                writeln!(f, "  [[{}]],", blob)?;
+            }
+            writeln!(f, "}}")?;
+            writeln!(f)?;
+        }
+        writeln!(f, "local tiles = {{")?;
+
+        let mut stats = TileDedupStats { uniform: uniform.len(), shared_blobs: shared.len(), shared_tiles: 0 };
+        for (k, v) in &tiles {
+            if let Some(id) = uniform.get(k) {
+                writeln!(f, "  [{}] = {},", k, id)?;
+            } else if let Some(i) = shared_index.get(v.as_str()) {
+                writeln!(f, "  [{}] = shared[{}],", k, i)?;
+                stats.shared_tiles += 1;
+            } else {
+                writeln!(f, "  [{}] = [[{}]],", k, v)?;
+            }
+        }
+
+        writeln!(f, "}}")?;
+        writeln!(f)?;
+
+        let liquid_tiles = self.liquid_tiles();
+        if !liquid_tiles.is_empty() {
+            writeln!(f, "local liquid = {{")?;
+            for (key, chunks) in &liquid_tiles {
+                if chunks.iter().all(|&l| l == chunks[0]) {
+                    writeln!(f, "  [{}] = {},", key, chunks[0].code())?;
+                } else {
+                    let digits: String = chunks.iter().map(|l| char::from(b'0' + l.code())).collect();
+                    writeln!(f, "  [{}] = \"{}\",", key, digits)?;
+                }
+            }
+            writeln!(f, "}}")?;
+            writeln!(f)?;
+        }
+        writeln!(f, "addon:RegisterTileGrid(\"{}\", {{", self.continent_name)?;
+        writeln!(f, "  name = \"{}\",", self.continent_name)?;
+        if let Some(ui_map_id) = self.ui_map_id {
+            writeln!(f, "  uiMapId = {},", ui_map_id)?;
+        }
+        writeln!(f, "  format = \"{}\",", encoding.format_tag())?;
+        writeln!(f, "  tileSize = 16,")?;
+        writeln!(f, "  tilesPerSide = 64,")?;
+        writeln!(f, "  tiles = tiles,")?;
+        if !liquid_tiles.is_empty() {
+            writeln!(f, "  liquid = liquid,")?;
+        }
+        if let Some(inventory) = &self.existing_tiles {
+            // 4096 bits, bit (key % 8) of byte (key / 8): tiles the WDT says exist
+            writeln!(f, "  existingTiles = [[{}]],", inventory.bitmap_b64())?;
+        }
+        writeln!(f, "}})")?;
+        Ok(stats)
+    }
+}
+
+/// How many tiles `export_lua` wrote without a blob of their own
+pub struct TileDedupStats {
+    pub uniform: usize,
+    pub shared_blobs: usize,
+    pub shared_tiles: usize,
+}
+
+pub fn build_tile_grid(
+    continent_name: &str,
+    tiles: &[(u32, u32, SourceFile)],
+) -> Result<TileGrid, Box<dyn std::error::Error>> {
+    let mut export = TileGrid::new(continent_name);
+
+    for (tx, ty, file) in tiles {
+        let data = file.read().map_err(|e| format!("failed to read {}: {}", file.describe(), e))?;
+        let liquids = parse_adt_liquids(&data);
+        match parse_adt_areaids(data) {
+            Ok(Some(area_ids)) => {
+                for &aid in &area_ids {
+                    if aid != 0 {
+                        export.found_areas.insert(aid);
+                    }
+                }
+                
+                export.tiles_raw.insert(tile_key(*tx, *ty), area_ids);
+                export.liquids.insert(tile_key(*tx, *ty), liquids);
+            }
+            Ok(None) => {}
+            Err(e) => {
+                return Err(format!("failed to parse {}: {}", file.describe(), e).into());
+            }
+        }
+    }
+
+    Ok(export)
+}
+
+/// The ADTs found compared against the tiles the WDT lists, as tile keys
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TileCheck {
+    /// Tiles the WDT lists
+    pub listed: usize,
+    pub missing: Vec<u32>,
+    pub not_in_wdt: Vec<u32>,
+    /// Listed tiles whose ADT has no chunks
+    pub without_chunks: Vec<u32>,
+}
+
+/// Compare the ADTs we found (and could parse) against the tiles the WDT lists.
+pub fn check_tile_inventory(
+    inventory: &TileInventory,
+    tiles: &[(u32, u32, SourceFile)],
+    export: &TileGrid,
+) -> TileCheck {
+    let present: BTreeSet<u32> = tiles.iter().map(|(tx, ty, _)| tile_key(*tx, *ty)).collect();
+
+    let missing: Vec<u32> = inventory.keys().filter(|k| !present.contains(k)).collect();
+    let not_in_wdt: Vec<u32> = present.iter().copied().filter(|&k| !inventory.contains(k)).collect();
+    let without_chunks: Vec<u32> = present
+        .iter()
+        .copied()
+        .filter(|k| inventory.contains(*k) && !export.tiles_raw.contains_key(k))
+        .collect();
+
+    TileCheck { listed: inventory.len(), missing, not_in_wdt, without_chunks }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(area_ids: &[u32]) {
+        for encoding in [TileEncoding::U32, TileEncoding::PaletteRle] {
+            let blob = encode_tile_b64(area_ids, encoding).unwrap();
+            assert_eq!(decode_tile_b64(&blob, encoding).unwrap(), area_ids, "{:?}", encoding);
+        }
+    }
+
+    fn palette_rle_bytes(area_ids: &[u32]) -> Vec<u8> {
+        let blob = encode_tile_b64(area_ids, TileEncoding::PaletteRle).unwrap();
+        general_purpose::STANDARD.decode(blob).unwrap()
+    }
+
+    #[test]
+    fn uniform_tile_is_one_full_run() {
+        let tile = vec![1519; 256];
+        round_trip(&tile);
+        // One palette entry (0x05EF), then a single run of 256 chunks of index 0
+        assert_eq!(palette_rle_bytes(&tile), [0, 0xEF, 0x05, 255, 0]);
+    }
+
+    #[test]
+    fn two_value_tiles() {
+        // Halves, a run that wraps from one row into the next, and a checkerboard
+        let halves: Vec<u32> = (0..256).map(|i| if i < 128 { 12 } else { 40 }).collect();
+        round_trip(&halves);
+        assert_eq!(palette_rle_bytes(&halves), [1, 12, 0, 40, 0, 127, 0, 127, 1]);
+
+        let wrapping: Vec<u32> = (0..256).map(|i| if (10..40).contains(&i) { 9 } else { 10 }).collect();
+        round_trip(&wrapping);
+        assert_eq!(palette_rle_bytes(&wrapping)[5..], [9, 0, 29, 1, 215, 0]);
+
+        // Stripes: every run one chunk long, the most runs a tile can have
+        let stripes: Vec<u32> = (0..256).map(|i| if i % 2 == 0 { 1 } else { 2 }).collect();
+        round_trip(&stripes);
+        assert_eq!(palette_rle_bytes(&stripes).len(), 1 + 4 + 256 * 2);
+    }
+
+    #[test]
+    fn many_value_tiles() {
+        // Every chunk its own area: a full 256-entry palette, each run one chunk long
+        let distinct: Vec<u32> = (0..256).map(|i| 1000 + i * 7).collect();
+        round_trip(&distinct);
+        let raw = palette_rle_bytes(&distinct);
+        assert_eq!(raw[0], 255);
+        assert_eq!(raw.len(), 1 + 256 * 2 + 256 * 2);
+
+        let rows: Vec<u32> = (0..256).map(|i| i / 16 * 3 + 1).collect();
+        round_trip(&rows);
+        assert_eq!(palette_rle_bytes(&rows)[33..35], [15, 0]);
+
+        // The largest ID the palette holds, and 0 for unassigned chunks
+        let extremes: Vec<u32> = (0..256).map(|i| if i % 5 == 0 { u16::MAX as u32 } else { 0 }).collect();
+        round_trip(&extremes);
+    }
+
+    #[test]
+    fn encode_rejects_bad_tiles() {
+        assert!(encode_tile_b64(&[1; 255], TileEncoding::U32).is_err());
+        let mut wide = vec![1; 256];
+        wide[17] = 70_000;
+        assert!(encode_tile_b64(&wide, TileEncoding::PaletteRle).is_err());
+        // u32 tiles take any ID
+        let blob = encode_tile_b64(&wide, TileEncoding::U32).unwrap();
+        assert_eq!(decode_tile_b64(&blob, TileEncoding::U32).unwrap(), wide);
+    }
+
+    #[test]
+    fn decode_rejects_malformed_blobs() {
+        let encode = |raw: &[u8]| general_purpose::STANDARD.encode(raw);
+        let palette = TileEncoding::PaletteRle;
+        assert!(decode_tile_b64("", palette).is_err());
+        // Palette of two entries cut short
+        assert!(decode_tile_b64(&encode(&[1, 12, 0]), palette).is_err());
+        // Index 1 with a one-entry palette
+        assert!(decode_tile_b64(&encode(&[0, 12, 0, 255, 1]), palette).is_err());
+        // Runs covering 255 or 257 chunks
+        assert!(decode_tile_b64(&encode(&[0, 12, 0, 254, 0]), palette).is_err());
+        assert!(decode_tile_b64(&encode(&[0, 12, 0, 255, 0, 0, 0]), palette).is_err());
+        assert!(decode_tile_b64(&encode(&[0; 1020]), TileEncoding::U32).is_err());
+    }
+}
@@ -0,0 +1,209 @@
+// ============================================================================
+// Validation: AreaTable consistency and AreaTable vs ADT cross-checks
+// ============================================================================
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+
+use crate::area::AreaTable;
+
+/// Tile coordinates listed per problem before the rest are summarized
+const SHOWN_TILES: usize = 8;
+
+/// Problems found so far. Errors fail `--strict` builds and `validate`; warnings never do.
+#[derive(Default)]
+pub struct Validator {
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+impl Validator {
+    /// Dangling parent IDs and parent cycles
+    pub fn check_area_table(&mut self, areas: &AreaTable) {
+        let ids: BTreeSet<u32> = areas.keys().copied().collect();
+
+        for &id in &ids {
+            let area = &areas[&id];
+            if area.parent_id != 0 && !areas.contains_key(&area.parent_id) {
+                self.errors.push(format!(
+                    "area {} {} has parent {}, which is not in the AreaTable",
+                    id, area.name, area.parent_id
+                ));
+            }
+        }
+
+        // Walk up from every area; a walk that revisits one of its own steps found a cycle.
+        // Each cycle is reported once, starting from its lowest ID.
+        let mut cycles = BTreeSet::new();
+        for &id in &ids {
+            let mut path = vec![id];
+            let mut current = id;
+            while let Some(area) = areas.get(&current) {
+                if area.parent_id == 0 {
+                    break;
+                }
+                current = area.parent_id;
+                if let Some(pos) = path.iter().position(|&p| p == current) {
+                    let mut cycle = path[pos..].to_vec();
+                    let min = cycle.iter().enumerate().min_by_key(|(_, &v)| v).map(|(i, _)| i).unwrap_or(0);
+                    cycle.rotate_left(min);
+                    cycles.insert(cycle);
+                    break;
+                }
+                path.push(current);
+            }
+        }
+        for cycle in cycles {
+            let mut chain: Vec<String> = cycle.iter().map(u32::to_string).collect();
+            chain.push(cycle[0].to_string());
+            self.errors.push(format!("parent cycle: {}", chain.join(" -> ")));
+        }
+    }
+
+    /// Unknown area IDs, `ContinentID` mismatches and area-0 chunks on one map
+    pub fn check_map(
+        &mut self,
+        grid_name: &str,
+        map_id: Option<u32>,
+        tiles: &HashMap<u32, Vec<u32>>,
+        areas: &AreaTable,
+    ) {
+        // area -> (chunk count, tile keys)
+        let mut found: BTreeMap<u32, (usize, BTreeSet<u32>)> = BTreeMap::new();
+        for (&key, ids) in tiles {
+            for &id in ids {
+                let entry = found.entry(id).or_default();
+                entry.0 += 1;
+                entry.1.insert(key);
+            }
+        }
+
+        for (&id, (chunks, keys)) in &found {
+            if id == 0 {
+                self.warnings.push(format!(
+                    "{}: {} chunks have area 0 (tiles {})",
+                    grid_name, chunks, tile_list(keys)
+                ));
+                continue;
+            }
+            let Some(area) = areas.get(&id) else {
+                self.errors.push(format!(
+                    "{}: area {} is not in the AreaTable ({} chunks, tiles {})",
+                    grid_name, id, chunks, tile_list(keys)
+                ));
+                continue;
+            };
+            if let (Some(expected), Some(continent)) = (map_id, area.continent_id) {
+                if continent != expected {
+                    self.errors.push(format!(
+                        "{}: area {} {} has ContinentID {} but was found on map {} ({} chunks, tiles {})",
+                        grid_name, id, area.name, continent, expected, chunks, tile_list(keys)
+                    ));
+                }
+            }
+        }
+    }
+
+}
+
+/// Every error, then every warning, one per line, and the totals
+impl fmt::Display for Validator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for e in &self.errors {
+            writeln!(f, "  error: {}", e)?;
+        }
+        for w in &self.warnings {
+            writeln!(f, "  warning: {}", w)?;
+        }
+        write!(f, "  {} errors, {} warnings", self.errors.len(), self.warnings.len())
+    }
+}
+
+fn tile_list(keys: &BTreeSet<u32>) -> String {
+    let coords: Vec<String> = keys.iter().take(SHOWN_TILES).map(|k| format!("{}_{}", k % 64, k / 64)).collect();
+    if keys.len() > SHOWN_TILES {
+        format!("{} and {} more", coords.join(", "), keys.len() - SHOWN_TILES)
+    } else {
+        coords.join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::area_table;
+    use crate::tiles::tile_key;
+
+    #[test]
+    fn dangling_parents_and_cycles() {
+        let areas = area_table(&[
+            (1, "Durotar", 0),
+            (2, "Orgrimmar", 1),
+            (3, "Lost", 404),
+            // 10 -> 11 -> 12 -> 10, with 13 hanging off the cycle
+            (10, "A", 11),
+            (11, "B", 12),
+            (12, "C", 10),
+            (13, "D", 12),
+            // An area that is its own parent
+            (20, "Loop", 20),
+        ]);
+        let mut validator = Validator::default();
+        validator.check_area_table(&areas);
+        // Every walk that runs into a cycle reports it from its lowest ID, so each is listed once
+        assert_eq!(
+            validator.errors,
+            [
+                "area 3 Lost has parent 404, which is not in the AreaTable",
+                "parent cycle: 10 -> 11 -> 12 -> 10",
+                "parent cycle: 20 -> 20",
+            ]
+        );
+        assert!(validator.warnings.is_empty());
+    }
+
+    #[test]
+    fn map_checks() {
+        let mut areas = area_table(&[(1, "Durotar", 0), (2, "Elwynn Forest", 0)]);
+        areas.get_mut(&1).unwrap().continent_id = Some(1);
+        areas.get_mut(&2).unwrap().continent_id = Some(0);
+
+        let mut tiles = HashMap::new();
+        let mut tile = vec![1; 256];
+        tile[..6].fill(2);
+        tile[6..10].fill(0);
+        tiles.insert(tile_key(30, 20), tile);
+        // Area 99 is in ten tiles, more than the report lists
+        for x in 0..10 {
+            tiles.insert(tile_key(x, 40), vec![99; 256]);
+        }
+
+        let mut validator = Validator::default();
+        validator.check_map("Kalimdor", Some(1), &tiles, &areas);
+        assert_eq!(
+            validator.errors,
+            [
+                "Kalimdor: area 2 Elwynn Forest has ContinentID 0 but was found on map 1 (6 chunks, tiles 30_20)",
+                "Kalimdor: area 99 is not in the AreaTable (2560 chunks, tiles 0_40, 1_40, 2_40, 3_40, 4_40, 5_40, \
+                 6_40, 7_40 and 2 more)",
+            ]
+        );
+        assert_eq!(validator.warnings, ["Kalimdor: 4 chunks have area 0 (tiles 30_20)"]);
+
+        // Without a map ID, or without a ContinentID column, there is nothing to compare
+        let mut validator = Validator::default();
+        validator.check_map("Kalimdor", None, &tiles, &areas);
+        assert_eq!(validator.errors.len(), 1);
+        areas.get_mut(&2).unwrap().continent_id = None;
+        let mut validator = Validator::default();
+        validator.check_map("Kalimdor", Some(1), &tiles, &areas);
+        assert_eq!(validator.errors.len(), 1);
+    }
+
+    #[test]
+    fn report_lists_errors_then_warnings() {
+        let validator = Validator { errors: vec!["e1".into(), "e2".into()], warnings: vec!["w1".into()] };
+        assert_eq!(validator.to_string(), "  error: e1\n  error: e2\n  warning: w1\n  2 errors, 1 warnings");
+        assert_eq!(Validator::default().to_string(), "  0 errors, 0 warnings");
+    }
+}
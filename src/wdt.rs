@@ -0,0 +1,104 @@
+// ============================================================================
+// WDT parsing (which of a map's 64x64 tiles exist)
+// ============================================================================
+
+use base64::{engine::general_purpose, Engine as _};
+
+/// MAIN flag: an ADT exists for this tile
+const MAIN_HAS_ADT: u32 = 0x1;
+
+/// Iterate a chunked file as `(magic, body)`, magic still in on-disk (reversed) order.
+pub(crate) fn chunks(data: &[u8]) -> impl Iterator<Item = (&[u8], &[u8])> {
+    let mut pos = 0;
+    std::iter::from_fn(move || {
+        if pos + 8 > data.len() {
+            return None;
+        }
+        let magic = &data[pos..pos + 4];
+        let size = u32::from_le_bytes([data[pos + 4], data[pos + 5], data[pos + 6], data[pos + 7]]) as usize;
+        let body = &data[pos + 8..(pos + 8 + size).min(data.len())];
+        pos += 8 + size;
+        Some((magic, body))
+    })
+}
+
+/// Root ADT FileDataIDs from a WDT's MAID chunk as `(x, y, fdid)`.
+pub fn parse_maid(data: &[u8]) -> Vec<(u32, u32, u32)> {
+    let Some((_, body)) = chunks(data).find(|(magic, _)| *magic == b"DIAM") else {
+        return Vec::new();
+    };
+    // 64x64 entries of 8 FileDataIDs each; the first is the root ADT
+    body.chunks_exact(32)
+        .take(64 * 64)
+        .enumerate()
+        .filter_map(|(i, entry)| {
+            let fdid = u32::from_le_bytes([entry[0], entry[1], entry[2], entry[3]]);
+            (fdid != 0).then_some((i as u32 % 64, i as u32 / 64, fdid))
+        })
+        .collect()
+}
+
+/// The authoritative list of tiles a map has, from the WDT's MAIN chunk
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TileInventory {
+    /// Indexed by tile key (`y * 64 + x`)
+    exists: Vec<bool>,
+}
+
+impl TileInventory {
+    pub fn parse(data: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        let (_, body) = chunks(data)
+            .find(|(magic, _)| *magic == b"NIAM")
+            .ok_or("WDT has no MAIN chunk")?;
+        if body.len() < 64 * 64 * 8 {
+            return Err(format!("WDT MAIN chunk is {} bytes, expected {}", body.len(), 64 * 64 * 8).into());
+        }
+
+        let exists = body
+            .chunks_exact(8)
+            .take(64 * 64)
+            .map(|entry| u32::from_le_bytes([entry[0], entry[1], entry[2], entry[3]]) & MAIN_HAS_ADT != 0)
+            .collect();
+        Ok(Self { exists })
+    }
+
+    /// Inverse of `bitmap_b64`, for reading generated tile files back
+    pub fn from_bitmap_b64(bitmap: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let bits = general_purpose::STANDARD.decode(bitmap.trim())?;
+        if bits.len() != 64 * 64 / 8 {
+            return Err(format!("tile bitmap is {} bytes, expected {}", bits.len(), 64 * 64 / 8).into());
+        }
+        let exists = (0..64 * 64).map(|key| bits[key / 8] & (1 << (key % 8)) != 0).collect();
+        Ok(Self { exists })
+    }
+
+    pub fn contains(&self, key: u32) -> bool {
+        self.exists.get(key as usize).copied().unwrap_or(false)
+    }
+
+    pub fn len(&self) -> usize {
+        self.exists.iter().filter(|&&e| e).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.exists.contains(&true)
+    }
+
+    /// Tile keys the WDT lists, in ascending order
+    pub fn keys(&self) -> impl Iterator<Item = u32> + '_ {
+        self.exists
+            .iter()
+            .enumerate()
+            .filter(|(_, &e)| e)
+            .map(|(k, _)| k as u32)
+    }
+
+    /// 4096-bit existence bitmap, bit `key % 8` of byte `key / 8`, base64 encoded
+    pub fn bitmap_b64(&self) -> String {
+        let mut bits = vec![0u8; 64 * 64 / 8];
+        for key in self.keys() {
+            bits[key as usize / 8] |= 1 << (key % 8);
+        }
+        general_purpose::STANDARD.encode(&bits)
+    }
+}
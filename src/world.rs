@@ -0,0 +1,208 @@
+// ============================================================================
+// World: the AreaTable plus one tile grid per map, keyed by map
+// ============================================================================
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crate::area::{parse_area_table, AreaTable};
+use crate::config::{Config, MapConfig};
+use crate::grid::{ChunkGrid, ChunkLocation};
+use crate::liquid::Liquid;
+use crate::neighbors::{find_inter_tile_neighbors, find_tile_neighbors, NeighborGraph};
+use crate::sources::{plan_maps, MapAdts, SourceFile};
+use crate::tiles::{build_tile_grid, check_tile_inventory, tile_key, TileCheck, TileGrid};
+use crate::wdt::TileInventory;
+
+/// Which map a grid holds: its Map.db2 ID when the config gives one, else its ADT prefix.
+/// Tile keys only mean something together with this key.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MapKey {
+    Id(u32),
+    Prefix(String),
+}
+
+impl fmt::Display for MapKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MapKey::Id(id) => write!(f, "map {}", id),
+            MapKey::Prefix(prefix) => write!(f, "map {}", prefix),
+        }
+    }
+}
+
+/// Counts reported for every map after it is read
+#[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MapStats {
+    pub tiles: usize,
+    pub chunks: usize,
+    /// Chunks with AreaID 0
+    pub unassigned_chunks: usize,
+    /// Distinct nonzero AreaIDs
+    pub areas: usize,
+    /// Pairs of different areas with touching chunks
+    pub neighbor_pairs: usize,
+    /// Chunks mostly covered by water, lava or slime
+    pub liquid_chunks: usize,
+}
+
+/// One map's tiles and the areas that touch on it
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MapGrid {
+    pub config: MapConfig,
+    /// ADT file prefix (`Kalimdor` in `Kalimdor_31_30.adt`)
+    pub adt_prefix: String,
+    pub tiles: TileGrid,
+    /// Neighbors on this map only; tiles of other maps never touch these
+    pub neighbors: NeighborGraph,
+    /// The ADTs found against the WDT's tile list; `None` when the map has no WDT
+    pub tile_check: Option<TileCheck>,
+}
+
+impl MapGrid {
+    /// Read the map's ADTs and, when there is one, its WDT, and build its neighbor graph
+    pub fn build(config: MapConfig, adts: &MapAdts) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut tiles = build_tile_grid(&config.grid, &adts.tiles)?;
+        tiles.ui_map_id = config.ui_map_id;
+        let mut tile_check = None;
+        if let Some(wdt) = &adts.wdt {
+            let data = wdt.read().map_err(|e| format!("failed to read {}: {}", wdt.describe(), e))?;
+            let inventory = TileInventory::parse(&data)
+                .map_err(|e| format!("failed to parse {}: {}", wdt.describe(), e))?;
+            tile_check = Some(check_tile_inventory(&inventory, &adts.tiles, &tiles));
+            tiles.existing_tiles = Some(inventory);
+        }
+        let mut grid = Self::with_neighbors(config, adts.map.clone(), tiles);
+        grid.tile_check = tile_check;
+        Ok(grid)
+    }
+
+    /// Read the ADT of the one tile with this key and nothing else (no WDT check), for
+    /// looking up a single position without reading the whole map
+    pub fn build_tile(config: MapConfig, adts: MapAdts, key: u32) -> Result<Self, Box<dyn std::error::Error>> {
+        let tile: Vec<(u32, u32, SourceFile)> =
+            adts.tiles.into_iter().filter(|(x, y, _)| tile_key(*x, *y) == key).collect();
+        let mut tiles = build_tile_grid(&config.grid, &tile)?;
+        tiles.ui_map_id = config.ui_map_id;
+        Ok(Self::with_neighbors(config, adts.map, tiles))
+    }
+
+    fn with_neighbors(config: MapConfig, adt_prefix: String, tiles: TileGrid) -> Self {
+        let mut neighbors = NeighborGraph::new();
+        for area_ids in tiles.tiles_raw.values() {
+            find_tile_neighbors(area_ids, &mut neighbors);
+        }
+        find_inter_tile_neighbors(&tiles.tiles_raw, &mut neighbors);
+        Self { config, adt_prefix, tiles, neighbors, tile_check: None }
+    }
+
+    pub fn key(&self) -> MapKey {
+        match self.config.map_id {
+            Some(id) => MapKey::Id(id),
+            None => MapKey::Prefix(self.adt_prefix.clone()),
+        }
+    }
+
+    /// Grid name passed to `RegisterTileGrid`
+    pub fn name(&self) -> &str {
+        &self.config.grid
+    }
+
+    pub fn chunk_grid(&self) -> ChunkGrid<'_> {
+        ChunkGrid::new(&self.tiles.tiles_raw)
+    }
+
+    pub fn stats(&self) -> MapStats {
+        let chunks: usize = self.tiles.tiles_raw.values().map(Vec::len).sum();
+        let unassigned_chunks = self.tiles.tiles_raw.values().flatten().filter(|&&id| id == 0).count();
+        let neighbor_links: usize = self.neighbors.values().map(|n| n.len()).sum();
+        MapStats {
+            tiles: self.tiles.tiles_raw.len(),
+            chunks,
+            unassigned_chunks,
+            areas: self.tiles.found_areas.iter().filter(|&&id| id != 0).count(),
+            neighbor_pairs: neighbor_links / 2,
+            liquid_chunks: self.tiles.liquids.values().flatten().filter(|&&l| l != Liquid::Land).count(),
+        }
+    }
+
+    /// Grid name, ADT prefix or Map.db2 ID, ignoring case
+    pub fn matches(&self, name: &str) -> bool {
+        planned_map_matches(&self.config, &self.adt_prefix, name)
+    }
+}
+
+/// `MapGrid::matches` for a map that has only been planned, not read
+fn planned_map_matches(config: &MapConfig, adt_prefix: &str, name: &str) -> bool {
+    config.grid.eq_ignore_ascii_case(name)
+        || adt_prefix.eq_ignore_ascii_case(name)
+        || config.map_id.is_some_and(|id| id.to_string() == name)
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct World {
+    pub areas: AreaTable,
+    pub maps: BTreeMap<MapKey, MapGrid>,
+}
+
+impl World {
+    /// Read the config's AreaTable and build the grid of every planned map (see `plan_maps`)
+    pub fn load(config: &Config, planned: Vec<(MapConfig, MapAdts)>) -> Result<Self, Box<dyn std::error::Error>> {
+        let areas = parse_area_table(&config.area_table)
+            .map_err(|e| format!("failed to load area table {}: {}", config.area_table.display(), e))?;
+
+        let mut maps: BTreeMap<MapKey, MapGrid> = BTreeMap::new();
+        for (map, adts) in planned {
+            let grid = MapGrid::build(map, &adts)?;
+            if let Some(other) = maps.get(&grid.key()) {
+                return Err(format!("{} and {} are both {}", other.name(), grid.name(), grid.key()).into());
+            }
+            maps.insert(grid.key(), grid);
+        }
+        Ok(Self { areas, maps })
+    }
+
+    /// Read the AreaTable and only the tile under `location` on the map `name` (grid name,
+    /// ADT prefix or Map.db2 ID); `grid(name)` then holds that tile
+    pub fn load_tile(config: &Config, name: &str, location: &ChunkLocation) -> Result<Self, Box<dyn std::error::Error>> {
+        let planned = plan_maps(config)?.maps;
+        let known: Vec<String> = planned.iter().map(|(map, _)| map.grid.clone()).collect();
+        let (map, adts) = planned
+            .into_iter()
+            .find(|(map, adts)| planned_map_matches(map, &adts.map, name))
+            .ok_or_else(|| format!("no map matches '{}' (known: {})", name, known.join(", ")))?;
+        let grid = MapGrid::build_tile(map, adts, location.tile_key())?;
+        let areas = parse_area_table(&config.area_table)
+            .map_err(|e| format!("failed to load area table {}: {}", config.area_table.display(), e))?;
+        Ok(Self { areas, maps: BTreeMap::from([(grid.key(), grid)]) })
+    }
+
+    /// The map with this grid name, ADT prefix or Map.db2 ID
+    pub fn grid(&self, name: &str) -> Option<&MapGrid> {
+        self.maps.values().find(|grid| grid.matches(name))
+    }
+
+    /// Every nonzero AreaID with chunks on any map; chunks without an area are not counted
+    pub fn found_areas(&self) -> BTreeSet<u32> {
+        self.maps.values().flat_map(|grid| grid.tiles.found_areas.iter().copied()).collect()
+    }
+
+    /// The maps an area has chunks on
+    pub fn maps_of(&self, area_id: u32) -> Vec<&MapKey> {
+        self.maps.iter().filter(|(_, grid)| grid.tiles.found_areas.contains(&area_id)).map(|(key, _)| key).collect()
+    }
+
+    /// Every map's neighbor graph in one. Maps share no edges, so an area only gains
+    /// neighbors from the maps it is actually on.
+    pub fn neighbor_graph(&self) -> NeighborGraph {
+        let mut graph = NeighborGraph::new();
+        for grid in self.maps.values() {
+            for (&area_id, neighbors) in &grid.neighbors {
+                graph.entry(area_id).or_default().extend(neighbors);
+            }
+        }
+        graph
+    }
+}